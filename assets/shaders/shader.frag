@@ -5,9 +5,11 @@ const int MAX_LIGHTS = 8;
 in vec4 worldNormal;
 in vec4 worldPos;
 in vec2 UV;
+in vec4 worldTangent;
 
 uniform sampler2D diffuseTexture;
 uniform sampler2D specularTexture;
+uniform sampler2D normalTexture;
 
 struct Light
 {
@@ -30,6 +32,7 @@ uniform materialData{
     float m_specularPower;
     int m_useDiffuseTexture;
     int m_useSpecularTexture;
+    int m_useNormalTexture;
 };
 
 layout(std140)
@@ -66,6 +69,12 @@ vec4 computeSpecular(in vec4 N, in vec4 L, in vec4 V, in vec4 iS, in vec4 kS, fl
     return iS * kS * finalPower;
 }
 
+// The texture is tinted by the diffuse color, like Kd with map_Kd
+vec4 diffuseColor()
+{
+    return (m_useDiffuseTexture != 0) ? texture2D(diffuseTexture, UV) * m_diffuse: m_diffuse;
+}
+
 vec4 computeLighting(in vec4 worldNorm, in vec4 worldPos){
     vec4 litColor = vec4(0);
 
@@ -84,7 +93,7 @@ vec4 computeLighting(in vec4 worldNorm, in vec4 worldPos){
                 break;
         }
 
-        vec4 diffColor = (m_useDiffuseTexture != 0) ? texture2D(diffuseTexture, UV): m_diffuse;
+        vec4 diffColor = diffuseColor();
         vec4 specColor = (m_useSpecularTexture != 0) ? texture2D(specularTexture, UV): m_specular;
         float specPower = (m_useSpecularTexture != 0) ? specColor.r * 255: m_specularPower;
 
@@ -129,8 +138,28 @@ vec4 computeLighting(in vec4 worldNorm, in vec4 worldPos){
     return vec4(litColor.rgb, 1);
 }
 
+// Moves the normal map's tangent space normal into the same space as
+// worldNormal, with the bitangent tangents::generate describes
+vec4 perturbNormal()
+{
+    vec3 N = normalize(worldNormal.xyz);
+
+    if(m_useNormalTexture == 0){
+        return vec4(N, 0);
+    }
+
+    vec3 T = normalize(worldTangent.xyz - N * dot(N, worldTangent.xyz));
+    vec3 B = worldTangent.w * cross(N, T);
+
+    vec3 mapped = texture2D(normalTexture, UV).xyz * 2 - 1;
+
+    return vec4(normalize(mapped.x * T + mapped.y * B + mapped.z * N), 0);
+}
+
 void main()
 {
-    vec4 diffColor = computeLighting(worldNormal, worldPos);
+    vec4 diffColor = computeLighting(perturbNormal(), worldPos);
+    // Draws aren't sorted, so everything is drawn opaque and the diffuse alpha
+    // (the MTL dissolve) is ignored
     Target0 = vec4(diffColor.rgb, 1);
 }
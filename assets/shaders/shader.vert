@@ -3,22 +3,27 @@
 in vec3 vPos;
 in vec3 vNormal;
 in vec2 vUV;
+in vec4 vTangent;
 
 layout(std140)
 uniform Transform{
      mat4 model;
      mat4 view;
      mat4 projection;
+     mat4 normalMatrix;
 };
 
 out vec4 worldNormal;
 out vec4 worldPos;
 out vec2 UV;
+out vec4 worldTangent;
 
 void main()
 {
     UV = vUV;
-    worldNormal = view * model * vec4(vNormal, 0);
+    worldNormal = normalMatrix * vec4(vNormal, 0);
+    // Bitangent sign stays in w
+    worldTangent = vec4((view * model * vec4(vTangent.xyz, 0)).xyz, vTangent.w);
     worldPos = view * model * vec4(vPos, 1);
     
     gl_Position = projection * worldPos;    
@@ -0,0 +1,52 @@
+extern crate rgraphics;
+
+use std::time::{Duration, Instant};
+
+use rgraphics::mesh_loader::{self, LoadOptions, NormalSource};
+
+const ITERATIONS: u32 = 5;
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn bench(file_path: &str, normal_source: Option<NormalSource>) {
+    let mut best = Duration::from_secs(u64::MAX);
+    let mut total = Duration::from_secs(0);
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        mesh_loader::load_file_with(
+            file_path,
+            &LoadOptions {
+                normal_source,
+                ..LoadOptions::default()
+            },
+        ).unwrap();
+        let elapsed = start.elapsed();
+
+        best = best.min(elapsed);
+        total += elapsed;
+    }
+
+    println!(
+        "{:<32} best {:>10.2} ms, mean {:>10.2} ms",
+        file_path,
+        millis(best),
+        millis(total / ITERATIONS)
+    );
+}
+
+fn main() {
+    // Bundled models, the larger ones ship without normals
+    bench("assets/models/cube.obj", None);
+    bench("assets/models/suzanne.obj", None);
+    bench(
+        "assets/models/bunny_old.obj",
+        Some(NormalSource::AreaWeighted),
+    );
+    bench(
+        "assets/models/horse.obj",
+        Some(NormalSource::AreaWeighted),
+    );
+}
@@ -0,0 +1,43 @@
+extern crate rgraphics;
+
+use std::time::Instant;
+
+use rgraphics::mesh_loader::{self, LoadOptions, NormalSource};
+use rgraphics::vertex_cache::DEFAULT_CACHE_SIZE;
+
+// Prints the ACMR of a bundled model before and after optimizing, and fails
+// if the optimizer made it worse
+fn bench(file_path: &str) {
+    let mut mesh = mesh_loader::load_file_with(
+        file_path,
+        &LoadOptions {
+            normal_source: Some(NormalSource::AreaWeighted),
+            ..LoadOptions::default()
+        },
+    ).unwrap();
+
+    let before = mesh.acmr(DEFAULT_CACHE_SIZE);
+
+    let start = Instant::now();
+    mesh.optimize_vertex_cache(DEFAULT_CACHE_SIZE);
+    let elapsed = start.elapsed();
+
+    let after = mesh.acmr(DEFAULT_CACHE_SIZE);
+
+    println!(
+        "{:<32} ACMR {:>6.3} -> {:>6.3} in {:>10.2} ms",
+        file_path,
+        before,
+        after,
+        elapsed.as_secs_f64() * 1000.0
+    );
+
+    assert!(after <= before, "{} got worse", file_path);
+}
+
+fn main() {
+    bench("assets/models/cube.obj");
+    bench("assets/models/suzanne.obj");
+    bench("assets/models/bunny_old.obj");
+    bench("assets/models/horse.obj");
+}
@@ -0,0 +1,85 @@
+extern crate rgraphics;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+use rgraphics::gltf_loader;
+use rgraphics::mesh::Mesh;
+use rgraphics::mesh_loader::{self, LoadOptions, MeshLoadError, NormalSource, ParseErrorKind};
+use rgraphics::ply;
+use rgraphics::stl;
+
+// Every mesh in the file along with a name to report it under
+fn load(file_path: &str) -> Result<Vec<(String, Mesh)>, MeshLoadError> {
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "ply" => Ok(vec![(file_path.to_string(), ply::load_file(file_path)?)]),
+        "stl" => Ok(vec![(file_path.to_string(), stl::load_file(file_path)?)]),
+        "gltf" | "glb" => Ok(gltf_loader::load_file(file_path)?
+            .meshes
+            .into_iter()
+            .enumerate()
+            .map(|(index, model)| (format!("{} mesh {}", file_path, index), model.mesh))
+            .collect()),
+        _ => {
+            // Files without normals get computed ones rather than failing
+            let mesh = match mesh_loader::load_file(file_path) {
+                Err(MeshLoadError::Parse {
+                    reason: ParseErrorKind::MissingNormals,
+                    ..
+                }) => mesh_loader::load_file_with(
+                    file_path,
+                    &LoadOptions {
+                        normal_source: Some(NormalSource::AreaWeighted),
+                        ..LoadOptions::default()
+                    },
+                )?,
+                result => result?,
+            };
+
+            Ok(vec![(file_path.to_string(), mesh)])
+        }
+    }
+}
+
+// Prints Mesh::validate's report for each model file given. Exits with 1 if
+// any file fails to load or has problems
+fn main() {
+    let files: Vec<String> = env::args().skip(1).collect();
+
+    if files.is_empty() {
+        eprintln!("usage: mesh_info <model file>...");
+        eprintln!("supports .obj, .ply, .stl, .gltf and .glb");
+        process::exit(2);
+    }
+
+    let mut failed = false;
+
+    for file_path in &files {
+        match load(file_path) {
+            Ok(meshes) => for (name, mesh) in meshes {
+                let report = mesh.validate();
+
+                println!("{}", name);
+                println!("{}", report);
+                println!();
+
+                failed |= !report.is_valid();
+            },
+            Err(error) => {
+                eprintln!("{}", error);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,532 @@
+use std::f32::consts::FRAC_PI_2;
+
+use glutin::{ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use na::{Matrix4, Point3, Vector3, Vector4};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // Vertical field of view in radians
+    Perspective { fov_y: f32 },
+    // Height of the view volume in world units, the width follows the aspect
+    Orthographic { height: f32 },
+}
+
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub position: Point3<f32>,
+    pub up: Vector3<f32>,
+    pub projection: Projection,
+    pub near: f32,
+    pub far: f32,
+    direction: Vector3<f32>,
+    width: u32,
+    height: u32,
+}
+
+impl Camera {
+    pub fn new(projection: Projection, width: u32, height: u32, near: f32, far: f32) -> Self {
+        Camera {
+            position: Point3::origin(),
+            up: Vector3::y(),
+            projection,
+            near,
+            far,
+            direction: -Vector3::z(),
+            width: width.max(1),
+            height: height.max(1),
+        }
+    }
+
+    pub fn perspective(fov_y: f32, width: u32, height: u32, near: f32, far: f32) -> Self {
+        Camera::new(Projection::Perspective { fov_y }, width, height, near, far)
+    }
+
+    pub fn orthographic(view_height: f32, width: u32, height: u32, near: f32, far: f32) -> Self {
+        Camera::new(
+            Projection::Orthographic {
+                height: view_height,
+            },
+            width,
+            height,
+            near,
+            far,
+        )
+    }
+
+    // Call on WindowEvent::Resized so the aspect ratio follows the window
+    pub fn resize(&mut self, width: u32, height: u32) {
+        // Minimized windows report 0, which would make the aspect NaN
+        self.width = width.max(1);
+        self.height = height.max(1);
+    }
+
+    pub fn viewport(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    // Unit vector the camera looks along
+    pub fn direction(&self) -> Vector3<f32> {
+        self.direction
+    }
+
+    pub fn set_direction(&mut self, direction: Vector3<f32>) {
+        if direction.norm() > 0.0 {
+            self.direction = direction.normalize();
+        }
+    }
+
+    pub fn look_at(&mut self, target: &Point3<f32>) {
+        let direction = target - self.position;
+        self.set_direction(direction);
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_at_rh(&self.position, &(self.position + self.direction), &self.up)
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        match self.projection {
+            Projection::Perspective { fov_y } => {
+                Matrix4::new_perspective(self.aspect(), fov_y, self.near, self.far)
+            }
+            Projection::Orthographic { height } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * self.aspect();
+
+                Matrix4::new_orthographic(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+            }
+        }
+    }
+
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        self.projection_matrix() * self.view()
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&self.view_projection())
+    }
+
+    // Ray through a window position in pixels, measured from the top left
+    // corner like glutin's MouseMoved. Starts on the near plane
+    pub fn screen_ray(&self, x: f32, y: f32) -> Ray {
+        let ndc_x = 2.0 * x / self.width as f32 - 1.0;
+        let ndc_y = 1.0 - 2.0 * y / self.height as f32;
+
+        let inverse = self.view_projection()
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+
+        let unproject = |ndc_z: f32| {
+            let point = inverse * Vector4::new(ndc_x, ndc_y, ndc_z, 1.0);
+            Point3::new(point.x, point.y, point.z) / point.w
+        };
+
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+
+        Ray {
+            origin: near,
+            direction: (far - near).normalize(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+}
+
+// Points with normal.dot(p) + distance >= 0 are on the inside
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    pub fn signed_distance(&self, point: &Point3<f32>) -> f32 {
+        self.normal.dot(&point.coords) + self.distance
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    // Left, right, bottom, top, near, far
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    // Extracts the planes from a view-projection matrix (Gribb and Hartmann)
+    pub fn from_matrix(m: &Matrix4<f32>) -> Self {
+        let row = |r: usize| Vector4::new(m[(r, 0)], m[(r, 1)], m[(r, 2)], m[(r, 3)]);
+        let plane = |v: Vector4<f32>| {
+            let normal = Vector3::new(v.x, v.y, v.z);
+            let length = normal.norm();
+
+            Plane {
+                normal: normal / length,
+                distance: v.w / length,
+            }
+        };
+
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        Frustum {
+            planes: [
+                plane(w + x),
+                plane(w - x),
+                plane(w + y),
+                plane(w - y),
+                plane(w + z),
+                plane(w - z),
+            ],
+        }
+    }
+
+    pub fn contains_point(&self, point: &Point3<f32>) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    // Conservative, spheres near the corners may pass without touching
+    pub fn intersects_sphere(&self, center: &Point3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(center) >= -radius)
+    }
+}
+
+// Drives a camera from window input. Feed every event to handle_event, then
+// call update once per frame
+pub trait CameraController {
+    fn handle_event(&mut self, event: &Event);
+    fn update(&mut self, camera: &mut Camera, dt: f32);
+}
+
+// Cursor movement, accumulated between updates while `button` is held or
+// always if there is none
+#[derive(Clone, Debug)]
+struct MouseLook {
+    button: Option<MouseButton>,
+    pressed: bool,
+    last_position: Option<(f64, f64)>,
+    delta: (f32, f32),
+}
+
+impl MouseLook {
+    fn new(button: Option<MouseButton>) -> Self {
+        MouseLook {
+            button,
+            pressed: false,
+            last_position: None,
+            delta: (0.0, 0.0),
+        }
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::MouseInput { state, button, .. } if Some(button) == self.button => {
+                self.pressed = state == ElementState::Pressed;
+            }
+            WindowEvent::MouseMoved {
+                position: (x, y), ..
+            } => {
+                if let Some((last_x, last_y)) = self.last_position {
+                    if self.pressed || self.button.is_none() {
+                        self.delta.0 += (x - last_x) as f32;
+                        self.delta.1 += (y - last_y) as f32;
+                    }
+                }
+
+                self.last_position = Some((x, y));
+            }
+            WindowEvent::MouseLeft { .. } | WindowEvent::Focused(false) => {
+                self.pressed = false;
+                self.last_position = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn take_delta(&mut self) -> (f32, f32) {
+        let delta = self.delta;
+        self.delta = (0.0, 0.0);
+        delta
+    }
+}
+
+// WASD to move, E and Q to go up and down
+#[derive(Clone, Debug, Default)]
+struct MoveKeys {
+    forward: bool,
+    back: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+}
+
+impl MoveKeys {
+    fn handle_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                let pressed = input.state == ElementState::Pressed;
+
+                match input.virtual_keycode {
+                    Some(VirtualKeyCode::W) => self.forward = pressed,
+                    Some(VirtualKeyCode::S) => self.back = pressed,
+                    Some(VirtualKeyCode::A) => self.left = pressed,
+                    Some(VirtualKeyCode::D) => self.right = pressed,
+                    Some(VirtualKeyCode::E) => self.up = pressed,
+                    Some(VirtualKeyCode::Q) => self.down = pressed,
+                    _ => {}
+                }
+            }
+            // Key releases are missed while unfocused
+            WindowEvent::Focused(false) => *self = MoveKeys::default(),
+            _ => {}
+        }
+    }
+
+    // Forward, right and up amounts in -1..=1
+    fn axes(&self) -> (f32, f32, f32) {
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+
+        (
+            axis(self.forward, self.back),
+            axis(self.right, self.left),
+            axis(self.up, self.down),
+        )
+    }
+}
+
+// Keeps just short of straight up or down, where look_at_rh breaks down
+const MAX_PITCH: f32 = FRAC_PI_2 * 0.99;
+
+// Yaw 0 looks down -z, positive yaw turns left
+pub fn yaw_pitch_direction(yaw: f32, pitch: f32) -> Vector3<f32> {
+    Vector3::new(
+        -yaw.sin() * pitch.cos(),
+        pitch.sin(),
+        -yaw.cos() * pitch.cos(),
+    )
+}
+
+// Inverse of yaw_pitch_direction, for pitches within ±π/2
+pub fn direction_yaw_pitch(direction: &Vector3<f32>) -> (f32, f32) {
+    let direction = direction.normalize();
+
+    (
+        (-direction.x).atan2(-direction.z),
+        direction.y.clamp(-1.0, 1.0).asin(),
+    )
+}
+
+fn window_event(event: &Event) -> Option<&WindowEvent> {
+    match *event {
+        Event::WindowEvent { ref event, .. } => Some(event),
+        _ => None,
+    }
+}
+
+// Rotates around a target while the left button is dragged, the scroll wheel
+// zooms
+#[derive(Clone, Debug)]
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    // Radians per pixel dragged
+    pub rotate_speed: f32,
+    // Fraction of the distance per scroll line
+    pub zoom_speed: f32,
+    mouse: MouseLook,
+    scroll: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Point3<f32>, distance: f32) -> Self {
+        OrbitController {
+            target,
+            distance,
+            min_distance: 0.01,
+            yaw: 0.0,
+            pitch: 0.0,
+            rotate_speed: 0.01,
+            zoom_speed: 0.1,
+            mouse: MouseLook::new(Some(MouseButton::Left)),
+            scroll: 0.0,
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn handle_event(&mut self, event: &Event) {
+        if let Some(event) = window_event(event) {
+            self.mouse.handle_event(event);
+
+            if let WindowEvent::MouseWheel { delta, .. } = *event {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    // Roughly one line per 20 pixels
+                    MouseScrollDelta::PixelDelta(_, y) => y / 20.0,
+                };
+            }
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, _dt: f32) {
+        let (dx, dy) = self.mouse.take_delta();
+
+        self.yaw -= dx * self.rotate_speed;
+        self.pitch = (self.pitch + dy * self.rotate_speed).clamp(-MAX_PITCH, MAX_PITCH);
+
+        self.distance = (self.distance * (1.0 - self.zoom_speed).powf(self.scroll))
+            .max(self.min_distance);
+        self.scroll = 0.0;
+
+        // The camera sits opposite the direction it looks in
+        camera.position = self.target - yaw_pitch_direction(self.yaw, self.pitch) * self.distance;
+        camera.look_at(&self.target);
+    }
+}
+
+// Free flight along the view direction, looks around while the right button
+// is dragged
+#[derive(Clone, Debug)]
+pub struct FlyController {
+    pub yaw: f32,
+    pub pitch: f32,
+    // Units per second
+    pub speed: f32,
+    // Radians per pixel
+    pub look_speed: f32,
+    mouse: MouseLook,
+    keys: MoveKeys,
+}
+
+impl FlyController {
+    // Starts out looking where the camera already does
+    pub fn new(camera: &Camera) -> Self {
+        let (yaw, pitch) = direction_yaw_pitch(&camera.direction());
+
+        FlyController {
+            yaw,
+            pitch,
+            speed: 2.0,
+            look_speed: 0.005,
+            mouse: MouseLook::new(Some(MouseButton::Right)),
+            keys: MoveKeys::default(),
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn handle_event(&mut self, event: &Event) {
+        if let Some(event) = window_event(event) {
+            self.mouse.handle_event(event);
+            self.keys.handle_event(event);
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let (dx, dy) = self.mouse.take_delta();
+
+        self.yaw -= dx * self.look_speed;
+        self.pitch = (self.pitch - dy * self.look_speed).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let forward = yaw_pitch_direction(self.yaw, self.pitch);
+        let right = forward.cross(&camera.up).normalize();
+        let up = right.cross(&forward);
+
+        let (f, r, u) = self.keys.axes();
+        let movement = forward * f + right * r + up * u;
+
+        if movement.norm() > 0.0 {
+            camera.position += movement.normalize() * self.speed * dt;
+        }
+
+        camera.set_direction(forward);
+    }
+}
+
+// Walks on the horizontal plane at a fixed height and always looks with the
+// mouse, pair with a grabbed cursor
+#[derive(Clone, Debug)]
+pub struct FirstPersonController {
+    pub yaw: f32,
+    pub pitch: f32,
+    // Units per second
+    pub speed: f32,
+    // Radians per pixel
+    pub look_speed: f32,
+    mouse: MouseLook,
+    keys: MoveKeys,
+}
+
+impl FirstPersonController {
+    // Starts out looking where the camera already does
+    pub fn new(camera: &Camera) -> Self {
+        let (yaw, pitch) = direction_yaw_pitch(&camera.direction());
+
+        FirstPersonController {
+            yaw,
+            pitch,
+            speed: 2.0,
+            look_speed: 0.005,
+            mouse: MouseLook::new(None),
+            keys: MoveKeys::default(),
+        }
+    }
+}
+
+impl CameraController for FirstPersonController {
+    fn handle_event(&mut self, event: &Event) {
+        if let Some(event) = window_event(event) {
+            self.mouse.handle_event(event);
+            self.keys.handle_event(event);
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let (dx, dy) = self.mouse.take_delta();
+
+        self.yaw -= dx * self.look_speed;
+        self.pitch = (self.pitch - dy * self.look_speed).clamp(-MAX_PITCH, MAX_PITCH);
+
+        // Moving ignores pitch so looking down doesn't walk into the ground
+        let forward = yaw_pitch_direction(self.yaw, 0.0);
+        let right = forward.cross(&Vector3::y());
+
+        let (f, r, _) = self.keys.axes();
+        let movement = forward * f + right * r;
+
+        if movement.norm() > 0.0 {
+            camera.position += movement.normalize() * self.speed * dt;
+        }
+
+        camera.set_direction(yaw_pitch_direction(self.yaw, self.pitch));
+    }
+}
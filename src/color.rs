@@ -12,16 +12,27 @@ pub struct Color {
 impl Color {
     pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
         Color {
-            r: r,
-            g: g,
-            b: b,
-            a: a,
+            r,
+            g,
+            b,
+            a,
         }
     }
     pub fn rgb(r: u8, g: u8, b: u8) -> Self {
         Color::rgba(r, g, b, 255)
     }
 
+    // Components in the 0 to 1 range, values outside it are clamped
+    pub fn rgba_f32(r: f32, g: f32, b: f32, a: f32) -> Self {
+        let to_byte = |val: f32| (val.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        Color::rgba(to_byte(r), to_byte(g), to_byte(b), to_byte(a))
+    }
+
+    pub fn rgb_f32(r: f32, g: f32, b: f32) -> Self {
+        Color::rgba_f32(r, g, b, 1.0)
+    }
+
     pub fn black() -> Self {
         Color::rgb(0, 0, 0)
     }
@@ -48,24 +59,24 @@ impl Color {
 }
 
 
-impl Into<Vector4<f32>> for Color {
-    fn into(self) -> Vector4<f32> {
+impl From<Color> for Vector4<f32> {
+    fn from(color: Color) -> Self {
         Vector4::new(
-            (f32::from(self.r)) / 255.0,
-            (f32::from(self.g)) / 255.0,
-            (f32::from(self.b)) / 255.0,
-            (f32::from(self.a)) / 255.0,
+            (f32::from(color.r)) / 255.0,
+            (f32::from(color.g)) / 255.0,
+            (f32::from(color.b)) / 255.0,
+            (f32::from(color.a)) / 255.0,
         )
     }
 }
 
-impl Into<[f32; 4]> for Color {
-    fn into(self) -> [f32; 4] {
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
         [
-            (f32::from(self.r)) / 255.0,
-            (f32::from(self.g)) / 255.0,
-            (f32::from(self.b)) / 255.0,
-            (f32::from(self.a)) / 255.0,
+            (f32::from(color.r)) / 255.0,
+            (f32::from(color.g)) / 255.0,
+            (f32::from(color.b)) / 255.0,
+            (f32::from(color.a)) / 255.0,
         ]
     }
 }
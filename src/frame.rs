@@ -0,0 +1,148 @@
+use gfx;
+use gfx::{CommandBuffer, Resources};
+use gfx::handle::{Buffer, DepthStencilView, RenderTargetView, Sampler};
+use gfx::traits::FactoryExt;
+
+use na::Matrix4;
+
+use color::Color;
+use light::{self, Light};
+use material::Material;
+use program::{pipe, ColorFormat, DepthFormat, LightData, LightMeta, MaterialData, Transform,
+              Vertex, MAX_LIGHTS};
+use texture::{self, Texture};
+
+// GPU state shared by every draw in a frame: the render targets, camera,
+// lights, and the transform and material buffers that are rewritten before
+// each draw. Created once, meshes only hold their own geometry
+pub struct FrameResources<R: Resources> {
+    color_view: RenderTargetView<R, ColorFormat>,
+    depth_view: DepthStencilView<R, DepthFormat>,
+    view: Matrix4<f32>,
+    projection: Matrix4<f32>,
+    transform: Buffer<R, Transform>,
+    material: Buffer<R, MaterialData>,
+    lights: Buffer<R, LightData>,
+    light_meta: Buffer<R, LightMeta>,
+    sampler: Sampler<R>,
+    // Bound to texture slots the material doesn't use
+    empty_texture: Texture<R>,
+}
+
+impl<R: Resources> FrameResources<R> {
+    pub fn new<F: FactoryExt<R>>(
+        factory: &mut F,
+        color_view: RenderTargetView<R, ColorFormat>,
+        depth_view: DepthStencilView<R, DepthFormat>,
+    ) -> Result<Self, String> {
+        Ok(FrameResources {
+            color_view,
+            depth_view,
+            view: Matrix4::identity(),
+            projection: Matrix4::identity(),
+            transform: factory.create_constant_buffer(1),
+            material: factory.create_constant_buffer(1),
+            lights: factory.create_constant_buffer(MAX_LIGHTS),
+            light_meta: factory.create_constant_buffer(1),
+            sampler: factory.create_sampler_linear(),
+            empty_texture: texture::create_solid_texture(factory, Color::black())?,
+        })
+    }
+
+    pub fn color_view(&self) -> &RenderTargetView<R, ColorFormat> {
+        &self.color_view
+    }
+
+    pub fn depth_view(&self) -> &DepthStencilView<R, DepthFormat> {
+        &self.depth_view
+    }
+
+    // Call after the window's views are recreated, e.g. on resize
+    pub fn update_views(
+        &mut self,
+        color_view: RenderTargetView<R, ColorFormat>,
+        depth_view: DepthStencilView<R, DepthFormat>,
+    ) {
+        self.color_view = color_view;
+        self.depth_view = depth_view;
+    }
+
+    pub fn view(&self) -> &Matrix4<f32> {
+        &self.view
+    }
+
+    pub fn projection(&self) -> &Matrix4<f32> {
+        &self.projection
+    }
+
+    pub fn set_camera(&mut self, view: Matrix4<f32>, projection: Matrix4<f32>) {
+        self.view = view;
+        self.projection = projection;
+    }
+
+    pub fn upload_lights<C: CommandBuffer<R>>(
+        &self,
+        encoder: &mut gfx::Encoder<R, C>,
+        lights: &[Light],
+    ) {
+        light::upload_lights(encoder, &self.lights, &self.light_meta, lights);
+    }
+
+    pub fn update_transform<C: CommandBuffer<R>>(
+        &self,
+        encoder: &mut gfx::Encoder<R, C>,
+        transform: Transform,
+    ) {
+        encoder
+            .update_buffer(&self.transform, &[transform], 0)
+            .unwrap(); //update buffers
+    }
+
+    // Uploads the material and returns the pipeline data for drawing
+    // `vertex_buffer` with it
+    pub fn bind<C: CommandBuffer<R>>(
+        &self,
+        encoder: &mut gfx::Encoder<R, C>,
+        vertex_buffer: &Buffer<R, Vertex>,
+        material: &Material<R>,
+    ) -> pipe::Data<R> {
+        encoder
+            .update_buffer(&self.material, &[material.clone().into()], 0)
+            .unwrap(); //update buffers
+
+        let (diffuse, specular, normal) = match *material {
+            Material::Textured {
+                ref diffuse_texture,
+                ref specular_texture,
+                ref normal_texture,
+                ..
+            } => (
+                diffuse_texture.clone(),
+                specular_texture
+                    .clone()
+                    .unwrap_or_else(|| self.empty_texture.clone()),
+                normal_texture
+                    .clone()
+                    .unwrap_or_else(|| self.empty_texture.clone()),
+            ),
+            Material::Untextured { .. } => (
+                self.empty_texture.clone(),
+                self.empty_texture.clone(),
+                self.empty_texture.clone(),
+            ),
+        };
+
+        pipe::Data {
+            vbuf: vertex_buffer.clone(),
+            transform: self.transform.clone(),
+            out: self.color_view.clone(),
+            out_depth: self.depth_view.clone(),
+            light_meta: self.light_meta.clone(),
+            lights: self.lights.clone(),
+            material: self.material.clone(),
+            diffuse_texture: (diffuse, self.sampler.clone()),
+            specular_texture: (specular, self.sampler.clone()),
+            normal_texture: (normal, self.sampler.clone()),
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::f32;
+
+use gfx::Resources;
+use gfx::traits::FactoryExt;
+use gltf;
+use gltf::image::Format;
+use gltf::mesh::Mode;
+
+use na::{Matrix4, Vector3};
+
+use color::Color;
+use material::Material;
+use mesh::Mesh;
+use mesh_loader::MeshLoadError;
+use model::{Model, Submesh};
+use normals;
+use program::Vertex;
+use texture::{self, Texture};
+
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color: Color,
+    pub metallic: f32,
+    pub roughness: f32,
+    // Indices into GltfScene::images
+    pub base_color_image: Option<usize>,
+    pub normal_image: Option<usize>,
+}
+
+// Always stored as 8 bit RGBA
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+pub struct SceneNode {
+    pub name: String,
+    pub transform: Matrix4<f32>,
+    // Index into GltfScene::meshes
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+// Each glTF mesh becomes a Model with a submesh per primitive, submesh
+// materials refer to GltfMaterial names
+pub struct GltfScene {
+    pub meshes: Vec<Model>,
+    pub materials: Vec<GltfMaterial>,
+    pub images: Vec<ImageData>,
+    pub nodes: Vec<SceneNode>,
+    // Top level nodes of the default scene
+    pub roots: Vec<usize>,
+}
+
+impl GltfScene {
+    // World transform of every node, indexed like `nodes`
+    pub fn world_transforms(&self) -> Vec<Matrix4<f32>> {
+        let mut world = vec![Matrix4::identity(); self.nodes.len()];
+        let mut stack: Vec<(usize, Matrix4<f32>)> = self.roots
+            .iter()
+            .map(|&root| (root, Matrix4::identity()))
+            .collect();
+
+        while let Some((node, parent)) = stack.pop() {
+            world[node] = parent * self.nodes[node].transform;
+
+            for &child in &self.nodes[node].children {
+                stack.push((child, world[node]));
+            }
+        }
+
+        world
+    }
+
+    // Metallic-roughness parameters are approximated with the Blinn-Phong
+    // parameters Material has
+    pub fn build_materials<R: Resources, F: FactoryExt<R>>(
+        &self,
+        factory: &mut F,
+    ) -> Result<Vec<(String, Material<R>)>, MeshLoadError> {
+        self.materials
+            .iter()
+            .map(|gltf_mat| {
+                let specular_color = Color::rgb_f32(
+                    0.04 + 0.96 * gltf_mat.metallic,
+                    0.04 + 0.96 * gltf_mat.metallic,
+                    0.04 + 0.96 * gltf_mat.metallic,
+                );
+                let specular_power = roughness_to_power(gltf_mat.roughness);
+
+                if gltf_mat.base_color_image.is_none() && gltf_mat.normal_image.is_none() {
+                    let material = Material::Untextured {
+                        diffuse_color: gltf_mat.base_color,
+                        ambient_color: Color::black(),
+                        specular_color,
+                        specular_power,
+                    };
+
+                    return Ok((gltf_mat.name.clone(), material));
+                }
+
+                // base_color multiplies the image, like baseColorFactor does
+                let diffuse_texture = match gltf_mat.base_color_image {
+                    Some(index) => self.image_texture(factory, index),
+                    None => texture::create_solid_texture(factory, Color::white()),
+                }.map_err(|error| texture_error(&gltf_mat.name, error))?;
+
+                let normal_texture = match gltf_mat.normal_image {
+                    Some(index) => Some(
+                        self.image_texture(factory, index)
+                            .map_err(|error| texture_error(&gltf_mat.name, error))?,
+                    ),
+                    None => None,
+                };
+
+                let material = Material::Textured {
+                    diffuse_color: gltf_mat.base_color,
+                    ambient_color: Color::black(),
+                    specular_color,
+                    specular_power,
+                    diffuse_texture,
+                    specular_texture: None,
+                    normal_texture,
+                };
+
+                Ok((gltf_mat.name.clone(), material))
+            })
+            .collect()
+    }
+
+    fn image_texture<R: Resources, F: FactoryExt<R>>(
+        &self,
+        factory: &mut F,
+        index: usize,
+    ) -> Result<Texture<R>, String> {
+        let image = &self.images[index];
+
+        texture::create_texture_rgba(factory, image.width, image.height, image.pixels.clone())
+    }
+}
+
+fn texture_error(material: &str, error: String) -> MeshLoadError {
+    MeshLoadError::Texture {
+        file: material.to_string(),
+        error,
+    }
+}
+
+fn roughness_to_power(roughness: f32) -> f32 {
+    let alpha = f32::max(roughness * roughness, 0.01);
+
+    f32::max(2.0 / (alpha * alpha) - 2.0, 1.0)
+}
+
+fn format_error(file_path: &str, error: String) -> MeshLoadError {
+    MeshLoadError::Format {
+        file: file_path.to_string(),
+        error,
+    }
+}
+
+fn material_name(material: &gltf::Material) -> String {
+    match (material.name(), material.index()) {
+        (Some(name), _) => name.to_string(),
+        (None, Some(index)) => format!("material_{}", index),
+        (None, None) => String::from("default"),
+    }
+}
+
+fn convert_image(image: gltf::image::Data) -> Result<ImageData, String> {
+    let channels = match image.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 => 3,
+        Format::R8G8B8A8 => 4,
+        _ => return Err(String::from("only 8 bit images are supported")),
+    };
+
+    let pixels = image
+        .pixels
+        .chunks(channels)
+        .flat_map(|pixel| match channels {
+            1 => vec![pixel[0], pixel[0], pixel[0], 255],
+            2 => vec![pixel[0], pixel[0], pixel[0], pixel[1]],
+            3 => vec![pixel[0], pixel[1], pixel[2], 255],
+            _ => pixel.to_vec(),
+        })
+        .collect();
+
+    Ok(ImageData {
+        width: image.width,
+        height: image.height,
+        pixels,
+    })
+}
+
+// Triangle list indices for the primitive's mode, None for points and lines
+fn triangle_indices(mode: Mode, indices: &[u32]) -> Option<Vec<(u32, u32, u32)>> {
+    let count = indices.len();
+
+    match mode {
+        Mode::Triangles => Some(
+            indices
+                .chunks(3)
+                .filter(|tri| tri.len() == 3)
+                .map(|tri| (tri[0], tri[1], tri[2]))
+                .collect(),
+        ),
+        // Every other strip triangle is flipped to keep the winding
+        Mode::TriangleStrip => Some(
+            (0..count.saturating_sub(2))
+                .map(|i| {
+                    if i % 2 == 0 {
+                        (indices[i], indices[i + 1], indices[i + 2])
+                    } else {
+                        (indices[i + 1], indices[i], indices[i + 2])
+                    }
+                })
+                .collect(),
+        ),
+        Mode::TriangleFan => Some(
+            (1..count.saturating_sub(1))
+                .map(|i| (indices[0], indices[i], indices[i + 1]))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn load_mesh(
+    file_path: &str,
+    mesh: &gltf::Mesh,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Model, MeshLoadError> {
+    let mut result = Mesh::new();
+    let mut submeshes = Vec::new();
+    let mut vertex_count = 0;
+    let mut index_count = 0;
+
+    let mesh_name = mesh.name()
+        .map(String::from)
+        .unwrap_or_else(|| format!("mesh_{}", mesh.index()));
+
+    for primitive in mesh.primitives() {
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+
+        let positions: Vec<Vector3<f32>> = match reader.read_positions() {
+            Some(positions) => positions.map(Vector3::from).collect(),
+            None => {
+                return Err(format_error(
+                    file_path,
+                    format!("{} has a primitive without positions", mesh_name),
+                ))
+            }
+        };
+
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        if let Some(&index) = indices.iter().find(|&&index| index as usize >= positions.len()) {
+            return Err(format_error(
+                file_path,
+                format!("{} has an out of range index {}", mesh_name, index),
+            ));
+        }
+
+        let tris = match triangle_indices(primitive.mode(), &indices) {
+            Some(tris) => tris,
+            // Points and lines have nothing to shade
+            None => continue,
+        };
+
+        // Every attribute needs a value per position
+        let check_count = |attribute: &str, count: usize| {
+            if count == positions.len() {
+                Ok(())
+            } else {
+                Err(format_error(
+                    file_path,
+                    format!(
+                        "{} has {} {} values for {} positions",
+                        mesh_name,
+                        count,
+                        attribute,
+                        positions.len()
+                    ),
+                ))
+            }
+        };
+
+        let normals: Vec<Vector3<f32>> = match reader.read_normals() {
+            Some(normals) => normals.map(Vector3::from).collect(),
+            None => normals::smooth_area_weighted(&positions, &tris),
+        };
+        check_count("NORMAL", normals.len())?;
+
+        let uvs: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+            Some(uvs) => uvs.into_f32().collect(),
+            None => vec![[0.0, 0.0]; positions.len()],
+        };
+        check_count("TEXCOORD_0", uvs.len())?;
+
+        let tangents: Option<Vec<[f32; 4]>> = reader.read_tangents().map(Iterator::collect);
+        if let Some(ref tangents) = tangents {
+            check_count("TANGENT", tangents.len())?;
+        }
+
+        let mut primitive_mesh = Mesh::new();
+
+        for (i, pos) in positions.iter().enumerate() {
+            primitive_mesh.add_vertex(&Vertex {
+                pos: (*pos).into(),
+                normal: normals[i].into(),
+                uv: uvs[i],
+                tangent: tangents.as_ref().map_or([0.0; 4], |tangents| tangents[i]),
+            });
+        }
+
+        primitive_mesh.add_tris(&tris);
+
+        // Primitives without tangents get generated ones, see tangents::generate
+        if tangents.is_none() {
+            primitive_mesh.generate_tangents();
+        }
+
+        result.add_verticies(primitive_mesh.vertices());
+
+        for tri in primitive_mesh.indices().chunks(3) {
+            result.add_tri((
+                tri[0] + vertex_count,
+                tri[1] + vertex_count,
+                tri[2] + vertex_count,
+            ));
+        }
+
+        let material = primitive.material();
+
+        submeshes.push(Submesh {
+            name: mesh_name.clone(),
+            material: material.index().map(|_| material_name(&material)),
+            start: index_count,
+            count: tris.len() as u32 * 3,
+        });
+
+        vertex_count += primitive_mesh.vertices().len() as u32;
+        index_count += tris.len() as u32 * 3;
+    }
+
+    Ok(Model::new(result, submeshes))
+}
+
+// Loads a .gltf (with embedded or external buffers) or .glb file
+pub fn load_file(file_path: &str) -> Result<GltfScene, MeshLoadError> {
+    let (document, buffers, images) =
+        gltf::import(file_path).map_err(|error| format_error(file_path, error.to_string()))?;
+
+    let meshes = document
+        .meshes()
+        .map(|mesh| load_mesh(file_path, &mesh, &buffers))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            let [r, g, b, a] = pbr.base_color_factor();
+
+            GltfMaterial {
+                name: material_name(&material),
+                base_color: Color::rgba_f32(r, g, b, a),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                base_color_image: pbr.base_color_texture()
+                    .map(|info| info.texture().source().index()),
+                normal_image: material
+                    .normal_texture()
+                    .map(|normal| normal.texture().source().index()),
+            }
+        })
+        .collect();
+
+    let images = images
+        .into_iter()
+        .map(convert_image)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format_error(file_path, error))?;
+
+    let nodes = document
+        .nodes()
+        .map(|node| SceneNode {
+            name: node.name()
+                .map(String::from)
+                .unwrap_or_else(|| format!("node_{}", node.index())),
+            transform: Matrix4::from(node.transform().matrix()),
+            mesh: node.mesh().map(|mesh| mesh.index()),
+            children: node.children().map(|child| child.index()).collect(),
+        })
+        .collect();
+
+    let roots = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+        None => Vec::new(),
+    };
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        images,
+        nodes,
+        roots,
+    })
+}
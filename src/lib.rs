@@ -1,15 +1,39 @@
 #[macro_use]
 extern crate gfx;
+extern crate gfx_device_gl;
+extern crate gfx_window_glutin;
+extern crate glutin;
+extern crate gltf;
 extern crate image;
 extern crate nalgebra as na;
-extern crate regex;
 
+pub mod camera;
 pub mod color;
+pub mod frame;
+pub mod gltf_loader;
 pub mod light;
+pub mod lod;
 pub mod mesh;
+pub mod mesh_cache;
 pub mod program;
+pub mod renderer;
 pub mod object;
+pub mod primitives;
 pub mod material;
 pub mod mesh_loader;
+pub mod model;
+pub mod mtl_loader;
+pub mod obj_writer;
+pub mod normals;
+pub mod ply;
+pub mod scene;
+pub mod simplify;
+pub mod stl;
+pub mod subdivision;
+pub mod tangents;
 pub mod texture;
+pub mod triangulation;
 pub mod utility;
+pub mod uv_mapping;
+pub mod validate;
+pub mod vertex_cache;
@@ -1,10 +1,10 @@
 use gfx;
 use gfx::{CommandBuffer, Resources};
+use gfx::handle::Buffer;
 
-use mesh::MeshData;
 use program::{LightData, LightMeta, MAX_LIGHTS};
 use color::Color;
-use na::{Point3, Vector3};
+use na::{Matrix4, Point3, Vector3};
 
 #[derive(Clone, Copy)]
 pub struct SpotLightInfo {
@@ -28,12 +28,10 @@ pub struct Light {
     pub ambient_color: Color,
 }
 
-impl Light {}
-
 impl Light {
     pub fn new(light_type: LightType, diffuse: Color, spec: Color, amb: Color) -> Self {
         Light {
-            light_type: light_type,
+            light_type,
             diffuse_color: diffuse,
             specular_color: spec,
             ambient_color: amb,
@@ -58,17 +56,45 @@ impl Light {
     pub fn new_point(pos: Point3<f32>, diffuse: Color, spec: Color, amb: Color) -> Self {
         Self::new(LightType::Point(pos), diffuse, spec, amb)
     }
+
+    // The light moved by `matrix`, e.g. from a node's space into the world
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let point = |p: &Point3<f32>| {
+            Point3::from_homogeneous(matrix * p.to_homogeneous()).unwrap_or(*p)
+        };
+        let vector = |v: &Vector3<f32>| {
+            let v = matrix * v.to_homogeneous();
+            let v = Vector3::new(v.x, v.y, v.z);
+
+            // Keep directions unit length when the matrix scales
+            if v.norm() > 0.0 {
+                v.normalize()
+            } else {
+                v
+            }
+        };
+
+        let light_type = match self.light_type {
+            LightType::Directional(ref dir) => LightType::Directional(vector(dir)),
+            LightType::Point(ref pos) => LightType::Point(point(pos)),
+            LightType::Spot(ref pos, ref dir, info) => {
+                LightType::Spot(point(pos), vector(dir), info)
+            }
+        };
+
+        Light { light_type, ..*self }
+    }
 }
 
-impl Into<LightData> for Light {
-    fn into(self) -> LightData {
-        match self.light_type {
+impl From<Light> for LightData {
+    fn from(light: Light) -> Self {
+        match light.light_type {
             LightType::Directional(ref dir) => LightData {
                 position: [0.0, 0.0, 0.0, 1.0],
                 direction: dir.to_homogeneous().into(),
-                diffuse_color: self.diffuse_color.into(),
-                specular_color: self.specular_color.into(),
-                ambient_color: self.ambient_color.into(),
+                diffuse_color: light.diffuse_color.into(),
+                specular_color: light.specular_color.into(),
+                ambient_color: light.ambient_color.into(),
                 light_type: 0,
                 spotlight_outer: 0.0,
                 spotlight_inner: 0.0,
@@ -77,9 +103,9 @@ impl Into<LightData> for Light {
             LightType::Point(ref pos) => LightData {
                 position: pos.to_homogeneous().into(),
                 direction: [0.0, 0.0, 0.0, 0.0],
-                diffuse_color: self.diffuse_color.into(),
-                specular_color: self.specular_color.into(),
-                ambient_color: self.ambient_color.into(),
+                diffuse_color: light.diffuse_color.into(),
+                specular_color: light.specular_color.into(),
+                ambient_color: light.ambient_color.into(),
                 light_type: 1,
                 spotlight_outer: 0.0,
                 spotlight_inner: 0.0,
@@ -94,9 +120,9 @@ impl Into<LightData> for Light {
                             }) => LightData {
                 position: pos.to_homogeneous().into(),
                 direction: dir.to_homogeneous().into(),
-                diffuse_color: self.diffuse_color.into(),
-                specular_color: self.specular_color.into(),
-                ambient_color: self.ambient_color.into(),
+                diffuse_color: light.diffuse_color.into(),
+                specular_color: light.specular_color.into(),
+                ambient_color: light.ambient_color.into(),
                 light_type: 2,
                 spotlight_outer: *outer_radius,
                 spotlight_inner: *inner_radius,
@@ -106,9 +132,11 @@ impl Into<LightData> for Light {
     }
 }
 
-pub fn upload_lights<R: Resources, C: CommandBuffer<R>>(
+// Used through FrameResources::upload_lights, which owns the buffers
+pub(crate) fn upload_lights<R: Resources, C: CommandBuffer<R>>(
     encoder: &mut gfx::Encoder<R, C>,
-    mesh_data: &mut MeshData<R>,
+    light_buffer: &Buffer<R, LightData>,
+    meta_buffer: &Buffer<R, LightMeta>,
     lights: &[Light],
 ) {
     // Number of lights to be sent to the shader
@@ -122,15 +150,11 @@ pub fn upload_lights<R: Resources, C: CommandBuffer<R>>(
 
     // Send light metadata
     encoder
-        .update_buffer(
-            &mesh_data.data_ref_mut().light_meta,
-            &[LightMeta { count: count as i32 }],
-            0,
-        )
+        .update_buffer(meta_buffer, &[LightMeta { count: count as i32 }], 0)
         .unwrap();
 
     // Send light data
     encoder
-        .update_buffer(&mesh_data.data_ref_mut().lights, slice.as_slice(), 0)
+        .update_buffer(light_buffer, slice.as_slice(), 0)
         .unwrap()
 }
@@ -0,0 +1,143 @@
+use std::f32;
+
+use gfx::Resources;
+use gfx::traits::FactoryExt;
+
+use na::{Matrix4, Vector3, Vector4};
+
+use mesh::{Mesh, MeshData};
+use simplify::SimplifyTarget;
+
+pub struct LodLevel {
+    pub mesh: Mesh,
+    // Smallest projected size, as a fraction of the viewport height, this
+    // level is drawn at
+    pub min_screen_size: f32,
+}
+
+// Levels from most to least detailed
+pub struct LodChain {
+    pub levels: Vec<LodLevel>,
+}
+
+pub struct LodChainData<R: Resources> {
+    levels: Vec<(MeshData<R>, f32)>,
+    // Bounding sphere of the most detailed level, in model space
+    center: Vector3<f32>,
+    radius: f32,
+}
+
+impl<R: Resources> LodChainData<R> {
+    // Projected diameter of the bounding sphere over the viewport height
+    pub fn screen_size(
+        &self,
+        model: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+        projection: &Matrix4<f32>,
+    ) -> f32 {
+        // Largest axis scale, so the sphere still bounds a stretched mesh
+        let scale = (0..3)
+            .map(|col| Vector3::new(model[(0, col)], model[(1, col)], model[(2, col)]).norm())
+            .fold(0.0, f32::max);
+
+        let radius = self.radius * scale;
+
+        // Orthographic projections have no perspective divide
+        if projection[(3, 3)] != 0.0 {
+            return radius * projection[(1, 1)];
+        }
+
+        let center = view * model * Vector4::new(self.center.x, self.center.y, self.center.z, 1.0);
+        let depth = center.z.abs();
+
+        if depth <= radius {
+            return f32::INFINITY;
+        }
+
+        radius * projection[(1, 1)] / depth
+    }
+
+    pub fn select(&self, screen_size: f32) -> &MeshData<R> {
+        let index = self.levels
+            .iter()
+            .position(|&(_, min_size)| screen_size >= min_size)
+            .unwrap_or(self.levels.len() - 1);
+
+        &self.levels[index].0
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+}
+
+impl LodChain {
+    pub fn new(levels: Vec<LodLevel>) -> Self {
+        LodChain { levels }
+    }
+
+    // Each level has half the triangles of the one before and takes over once
+    // the object covers half as much of the screen. The full mesh is drawn
+    // down to a quarter of the viewport height, below that its triangles get
+    // too small to tell apart
+    pub fn generate(mesh: Mesh, level_count: usize) -> Self {
+        let mut levels = vec![LodLevel {
+            mesh,
+            min_screen_size: 0.25,
+        }];
+
+        for level in 1..level_count.max(1) {
+            let mesh = {
+                let previous = &levels[level - 1].mesh;
+                let target = previous.indices().len() / 6;
+
+                previous.simplify(SimplifyTarget::Triangles(target))
+            };
+
+            levels.push(LodLevel {
+                mesh,
+                min_screen_size: 0.25 * 0.5f32.powi(level as i32),
+            });
+        }
+
+        if let Some(last) = levels.last_mut() {
+            last.min_screen_size = 0.0;
+        }
+
+        LodChain { levels }
+    }
+
+    pub fn build<R: Resources, F: FactoryExt<R>>(
+        &self,
+        factory: &mut F,
+    ) -> Result<LodChainData<R>, &'static str> {
+        if self.levels.is_empty() {
+            return Err("LOD chain has no levels");
+        }
+
+        let (min, max) = self.levels[0].mesh.extents();
+        let center = (min + max) / 2.0;
+        let radius = self.levels[0]
+            .mesh
+            .vertices()
+            .iter()
+            .map(|vert| (Vector3::from(vert.pos) - center).norm())
+            .fold(0.0, f32::max);
+
+        let levels = self.levels
+            .iter()
+            .map(|level| {
+                level
+                    .mesh
+                    .build(factory)
+                    .map(|data| (data, level.min_screen_size))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(LodChainData {
+            levels,
+            center,
+            radius,
+        })
+    }
+}
@@ -1,32 +1,28 @@
-extern crate gfx;
-extern crate gfx_window_glutin;
 extern crate glutin;
 extern crate nalgebra as na;
-extern crate regex;
 extern crate rgraphics;
 extern crate time;
 
-use gfx::traits::FactoryExt;
-use gfx::Device;
-use gfx_window_glutin as gfx_glutin;
-use glutin::{Event, GlContext, GlRequest, WindowEvent};
+use std::rc::Rc;
+
+use glutin::{Event, GlRequest, WindowEvent};
 use glutin::Api::OpenGl;
 
-use na::{Matrix4, Point3, Vector3};
+use na::{Point3, UnitQuaternion, Vector3};
 
 use rgraphics as rg;
 
+use rg::camera::{Camera, CameraController, FlyController};
 use rg::color::Color;
 use rg::light::Light;
 // use mesh::Mesh;
-use rg::program::{pipe, ColorFormat, DepthFormat};
 use rg::material::Material;
 use rg::mesh_loader;
-use rg::light;
-use rg::object;
+use rg::mesh_loader::LoadOptions;
 use rg::object::Object;
+use rg::renderer::Renderer;
+use rg::scene::{Drawable, Scene};
 use rg::texture;
-use rg::utility;
 
 fn main() {
     let mut width = 800;
@@ -42,25 +38,18 @@ fn main() {
     let context_builder = glutin::ContextBuilder::new()
         .with_gl(GlRequest::Specific(OpenGl, (4, 1)))
         .with_vsync(true);
-    let (window, mut device, mut factory, mut color_view, mut depth_view) =
-        gfx_glutin::init::<ColorFormat, DepthFormat>(window_builder, context_builder, &event_loop);
-
-    let program = factory
-        .create_pipeline_simple(
-            utility::read_in_file("assets/shaders/shader.vert")
-                .unwrap()
-                .as_bytes(),
-            utility::read_in_file("assets/shaders/shader.frag")
-                .unwrap()
-                .as_bytes(),
-            pipe::new(),
-        )
-        .unwrap();
+    let mut renderer = Renderer::new(
+        window_builder,
+        context_builder,
+        &event_loop,
+        "assets/shaders/shader.vert",
+        "assets/shaders/shader.frag",
+    ).unwrap();
 
     let mut running = true;
 
 
-    let mut model_trans2 = Object::new(
+    let model_trans2 = Object::new(
         Material::Untextured {
             diffuse_color: Color::gray(),
             ambient_color: Color::black(),
@@ -69,21 +58,14 @@ fn main() {
         },
         Point3::new(-0.25, 0.0, -8.0),
         Vector3::from_element(0.5),
-        Vector3::zeros(),
+        UnitQuaternion::identity(),
     );
 
-    let view_mat = Matrix4::look_at_rh(
-        &Point3::new(0.0, 0.0, 0.0),
-        &Point3::new(0.0, 0.0, -1.0),
-        &Vector3::new(0.0, 1.0, 0.0),
-    );
+    // Starts at the origin looking down -z
+    let camera = Camera::perspective(90f32.to_radians(), width, height, 0.01, 100.0);
 
-    let mut projection_mat = Matrix4::new_perspective(
-        width as f32 / height as f32,
-        90f32.to_radians(),
-        0.01,
-        100.0,
-    );
+    // Hold the right mouse button to look around, WASD to move
+    let mut camera_controller = FlyController::new(&camera);
 
 
     const LIGHT_COUNT: usize = 1;
@@ -102,13 +84,19 @@ fn main() {
         LIGHT_COUNT
     ];
 
-    let diff_tex = texture::load_texture(&mut factory, "assets/textures/diffuse.tga").unwrap();
-    let spec_tex = texture::load_texture(&mut factory, "assets/textures/specular.tga").unwrap();
+    let diff_tex =
+        texture::load_texture(renderer.factory_mut(), "assets/textures/diffuse.tga").unwrap();
+    let spec_tex =
+        texture::load_texture(renderer.factory_mut(), "assets/textures/specular.tga").unwrap();
 
     let mat = Material::Textured {
-        diffuse_texture: diff_tex,
-        specular_texture: spec_tex,
+        diffuse_color: Color::white(),
         ambient_color: Color::black(),
+        specular_color: Color::white(),
+        specular_power: 1.0,
+        diffuse_texture: diff_tex,
+        specular_texture: Some(spec_tex),
+        normal_texture: None,
     };
 
     // let mat = Material::Untextured {
@@ -118,30 +106,32 @@ fn main() {
     //     specular_power: 5.0,
     // };
 
-    let mut model_trans = Object::new(
+    let model_trans = Object::new(
         mat,
         Point3::new(0.0, 0.0, -1.0),
         Vector3::from_element(1.0),
-        Vector3::zeros(),
+        UnitQuaternion::identity(),
     );
 
-    let bunny_mesh = mesh_loader::load_file("assets/models/suzanne.obj").unwrap();
-    let horse_mesh = mesh_loader::load_file("assets/models/cube.obj").unwrap();
-    //let cube_mesh = mesh_loader::load_file("assets/models/cube.obj").unwrap();
+    let load_options = LoadOptions {
+        rescale: true,
+        cache: true,
+        ..LoadOptions::default()
+    };
 
-    let mut bunny_data = bunny_mesh
-        .build(&mut factory, color_view.clone(), depth_view.clone())
-        .unwrap();
+    let bunny_mesh =
+        mesh_loader::load_file_with("assets/models/suzanne.obj", &load_options).unwrap();
+    let horse_mesh = mesh_loader::load_file_with("assets/models/cube.obj", &load_options).unwrap();
+    //let cube_mesh = mesh_loader::load_file("assets/models/cube.obj").unwrap();
 
-    let mut horse_data = horse_mesh
-        .build(&mut factory, color_view.clone(), depth_view.clone())
-        .unwrap();
+    let bunny_data = bunny_mesh.build(renderer.factory_mut()).unwrap();
+    let horse_data = horse_mesh.build(renderer.factory_mut()).unwrap();
 
-    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    let mut scene = Scene::new(camera);
+    scene.lights = lights;
 
-    // Upload light data before loop as they do not currently change
-    light::upload_lights(&mut encoder, &mut bunny_data, lights.as_slice());
-    light::upload_lights(&mut encoder, &mut horse_data, lights.as_slice());
+    let bunny = scene.add("bunny", model_trans, Some(Drawable::Mesh(Rc::new(bunny_data))));
+    let horse = scene.add("horse", model_trans2, Some(Drawable::Mesh(Rc::new(horse_data))));
 
     while running {
         // Update times and get dt
@@ -155,6 +145,8 @@ fn main() {
 
         // Poll events
         event_loop.poll_events(|event| {
+            camera_controller.handle_event(&event);
+
             if let Event::WindowEvent { event, .. } = event {
                 match event {
                     // Receive window closed event or excape key pressed
@@ -174,22 +166,9 @@ fn main() {
                         width = w;
                         height = h;
 
-                        // Resize the context (necessary in Walyand and OSX)
-                        window.resize(width, height);
-
-                        // Update render views for the window
-                        gfx_glutin::update_views(&window, &mut color_view, &mut depth_view);
-
-                        // Update remder views for mesh
-                        bunny_data.update_views(color_view.clone(), depth_view.clone());
-                        horse_data.update_views(color_view.clone(), depth_view.clone());
-
-                        projection_mat = Matrix4::new_perspective(
-                            width as f32 / height as f32,
-                            90f32.to_radians(),
-                            0.01,
-                            100.0,
-                        );
+                        // Resize the context and its render views
+                        renderer.resize(width, height);
+                        scene.camera.resize(width, height);
                     }
 
                     _ => {}
@@ -197,45 +176,28 @@ fn main() {
             }
         });
 
+        camera_controller.update(&mut scene.camera, dt);
+
         // Rotate the cube
-        model_trans.rotation += Vector3::new(45f32.to_radians(), 90f32.to_radians(), 0.0) * dt;
-        model_trans2.rotation +=
-            Vector3::new((-45f32).to_radians(), (-90f32).to_radians(), 0.0) * dt;
-        model_trans2.position = Point3::new(
+        scene.object_mut(bunny).rotate(&UnitQuaternion::from_euler_angles(
+            45f32.to_radians() * dt,
+            90f32.to_radians() * dt,
+            0.0,
+        ));
+
+        let horse_trans = scene.object_mut(horse);
+        horse_trans.rotate(&UnitQuaternion::from_euler_angles(
+            (-45f32).to_radians() * dt,
+            (-90f32).to_radians() * dt,
+            0.0,
+        ));
+        horse_trans.position = Point3::new(
             f32::cos(elapsed_time) * 2.0,
             0.0,
             f32::sin(elapsed_time) * 2.0 + -1.0,
         );
-        // Clear buffers
-        encoder.clear(&color_view, Color::black().into());
-        encoder.clear_depth(&depth_view, 1.0);
-
-        // Draw the bunny
-        object::draw(
-            &mut encoder,
-            &mut bunny_data,
-            &program,
-            &model_trans,
-            &view_mat,
-            &projection_mat,
-        );
-
-        // Draw the horse
-        object::draw(
-            &mut encoder,
-            &mut horse_data,
-            &program,
-            &model_trans2,
-            &view_mat,
-            &projection_mat,
-        );
-
-
-        // Flush command buffers
-        encoder.flush(&mut device);
 
-        // Swap buffers
-        window.swap_buffers().unwrap();
-        device.cleanup();
+        // Draw the scene and swap buffers
+        renderer.render(&scene).unwrap();
     }
 }
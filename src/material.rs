@@ -12,15 +12,22 @@ pub enum Material<R: Resources> {
         specular_power: f32,
     },
     Textured {
+        // Multiplied with the diffuse texture, the alpha is the opacity
+        diffuse_color: Color,
         ambient_color: Color,
+        specular_color: Color,
+        specular_power: f32,
         diffuse_texture: Texture<R>,
-        specular_texture: Texture<R>,
+        // Replaces specular_color, and specular_power with its red channel * 255
+        specular_texture: Option<Texture<R>>,
+        // Tangent space normals, needs tangents on the mesh
+        normal_texture: Option<Texture<R>>,
     },
 }
 
-impl<R: Resources> Into<MaterialData> for Material<R> {
-    fn into(self) -> MaterialData {
-        match self {
+impl<R: Resources> From<Material<R>> for MaterialData {
+    fn from(material: Material<R>) -> Self {
+        match material {
             Material::Untextured {
                 diffuse_color,
                 ambient_color,
@@ -30,17 +37,27 @@ impl<R: Resources> Into<MaterialData> for Material<R> {
                 diffuse_color: diffuse_color.into(),
                 specular_color: specular_color.into(),
                 ambient_color: ambient_color.into(),
-                specular_power: specular_power,
+                specular_power,
                 use_diffuse_texture: 0,
                 use_specular_texture: 0,
+                use_normal_texture: 0,
             },
-            Material::Textured { ambient_color, .. } => MaterialData {
-                diffuse_color: Color::black().into(),
-                specular_color: Color::black().into(),
+            Material::Textured {
+                diffuse_color,
+                ambient_color,
+                specular_color,
+                specular_power,
+                specular_texture,
+                normal_texture,
+                ..
+            } => MaterialData {
+                diffuse_color: diffuse_color.into(),
+                specular_color: specular_color.into(),
                 ambient_color: ambient_color.into(),
-                specular_power: 1.0,
+                specular_power,
                 use_diffuse_texture: 1,
-                use_specular_texture: 1,
+                use_specular_texture: specular_texture.is_some() as i32,
+                use_normal_texture: normal_texture.is_some() as i32,
             },
         }
     }
@@ -1,21 +1,23 @@
+use std::collections::{HashMap, HashSet};
 use std::f32;
 
-use image;
 use gfx::{Resources, Slice};
-use gfx::handle::{DepthStencilView, RenderTargetView};
+use gfx::handle::Buffer;
 use gfx::traits::FactoryExt;
-use gfx::format::Rgba8;
 
-use na::Vector3;
+use na::{Matrix4, Vector3, Vector4};
 
-use texture::Texture;
+use normals;
+use tangents;
+use vertex_cache;
 
-use program::{pipe, ColorFormat, DepthFormat, Vertex, MAX_LIGHTS};
+use program::Vertex;
 
 
+// Geometry only, everything else a draw needs comes from FrameResources
 pub struct MeshData<R: Resources> {
     slice: Slice<R>,
-    data: pipe::Data<R>,
+    vertex_buffer: Buffer<R, Vertex>,
 }
 
 impl<R: Resources> MeshData<R> {
@@ -23,34 +25,63 @@ impl<R: Resources> MeshData<R> {
         &self.slice
     }
 
-    pub fn data_ref(&self) -> &pipe::Data<R> {
-        &self.data
-    }
-
-    pub fn data_ref_mut(&mut self) -> &mut pipe::Data<R> {
-        &mut self.data
-    }
-
-    pub fn update_views(
-        &mut self,
-        color_view: RenderTargetView<R, ColorFormat>,
-        depth_view: DepthStencilView<R, DepthFormat>,
-    ) {
-        self.data.out = color_view;
-        self.data.out_depth = depth_view;
-    }
-
-    pub fn update_diffuse_texture(&mut self, tex: Texture<R>) {
-        let (_, sampler) = self.data.diffuse_texture.clone();
-
-        self.data.diffuse_texture = (tex, sampler);
+    pub fn vertex_buffer(&self) -> &Buffer<R, Vertex> {
+        &self.vertex_buffer
     }
+}
 
-    pub fn update_specular_texture(&mut self, tex: Texture<R>) {
-        let (_, sampler) = self.data.specular_texture.clone();
+// How far apart, relative to the largest coordinate, two positions can be and
+// still count as the same point
+pub const POSITION_TOLERANCE: f32 = 1e-5;
+
+// Finds which vertices are at the same point, for anything that works on
+// connectivity rather than on the split vertices. Points within
+// POSITION_TOLERANCE are joined, since ones that should meet (like the
+// sines at either end of a seam) can be a rounding error apart, which also
+// joins -0.0 and 0.0. Returns the point each vertex is at, and the points
+// where their first vertex is
+pub fn match_positions(verts: &[Vertex]) -> (Vec<usize>, Vec<Vector3<f32>>) {
+    let largest = verts
+        .iter()
+        .flat_map(|vert| vert.pos.iter())
+        .filter(|value| value.is_finite())
+        .fold(1.0, |largest: f32, value| largest.max(value.abs()));
+    let tolerance = largest * POSITION_TOLERANCE;
+
+    let cell = |pos: &Vector3<f32>| {
+        let cell = |value: f32| (value / tolerance).floor() as i64;
+        (cell(pos.x), cell(pos.y), cell(pos.z))
+    };
+
+    let mut grid: HashMap<_, Vec<usize>> = HashMap::new();
+    let mut points: Vec<Vector3<f32>> = Vec::new();
+
+    let positions = verts
+        .iter()
+        .map(|vert| {
+            let pos = Vector3::from(vert.pos);
+            let (x, y, z) = cell(&pos);
+
+            // Anything within the tolerance is in this cell or a neighbouring one
+            let neighbours = (-1..2).flat_map(|dx| {
+                (-1..2).flat_map(move |dy| (-1..2).map(move |dz| (x + dx, y + dy, z + dz)))
+            });
+
+            let existing = neighbours
+                .filter_map(|key| grid.get(&key))
+                .flat_map(|indices| indices.iter())
+                .find(|&&other| (pos - points[other]).norm() <= tolerance)
+                .cloned();
+
+            existing.unwrap_or_else(|| {
+                grid.entry((x, y, z)).or_insert_with(Vec::new).push(points.len());
+                points.push(pos);
+                points.len() - 1
+            })
+        })
+        .collect();
 
-        self.data.specular_texture = (tex, sampler);
-    }
+    (positions, points)
 }
 
 #[derive(Default)]
@@ -70,51 +101,13 @@ impl Mesh {
     pub fn build<R: Resources, F: FactoryExt<R>>(
         &self,
         factory: &mut F,
-        color_view: RenderTargetView<R, ColorFormat>,
-        depth_view: DepthStencilView<R, DepthFormat>,
     ) -> Result<MeshData<R>, &'static str> {
-        let (vbo, slice) = factory
+        let (vertex_buffer, slice) = factory
             .create_vertex_buffer_with_slice(self.vertex_list.as_slice(), self.tri_list.as_slice());
 
-        // Transform buffer
-        let constant_buffer = factory.create_constant_buffer(1);
-
-        // Material buffer
-        let material_buffer = factory.create_constant_buffer(1);
-
-        // Light buffers
-        let light_buffer = factory.create_constant_buffer(MAX_LIGHTS);
-        let light_meta = factory.create_constant_buffer(1);
-
-        use gfx::texture as t;
-
-        let sampler = factory.create_sampler_linear();
-
-        // Width/height have to be power of 2, so blank texture must be minumum 1x1
-        let img = image::DynamicImage::new_rgba8(1, 1).to_rgba();
-        let kind = t::Kind::D2(
-            img.width() as t::Size,
-            img.height() as t::Size,
-            t::AaMode::Single,
-        );
-
-        let (_, empty_tex) = factory
-            .create_texture_immutable_u8::<Rgba8>(kind, &[&img])
-            .unwrap();
-
-        Result::Ok(MeshData {
-            slice: slice,
-            data: pipe::Data {
-                vbuf: vbo,
-                transform: constant_buffer,
-                out: color_view,
-                out_depth: depth_view,
-                light_meta: light_meta,
-                lights: light_buffer,
-                material: material_buffer,
-                diffuse_texture: (empty_tex.clone(), sampler.clone()),
-                specular_texture: (empty_tex, sampler),
-            },
+        Ok(MeshData {
+            slice,
+            vertex_buffer,
         })
     }
 
@@ -146,6 +139,45 @@ impl Mesh {
         self
     }
 
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertex_list
+    }
+
+    // Three indices per triangle
+    pub fn indices(&self) -> &[u32] {
+        &self.tri_list
+    }
+
+    // Sets the tangent of every vertex, see tangents::generate. Vertices on
+    // mirrored UV seams are copied, so this can add vertices and change indices
+    pub fn generate_tangents(&mut self) -> &mut Self {
+        self.generate_tangents_with_copies();
+        self
+    }
+
+    // Like generate_tangents, but returns the vertex each added copy was made
+    // from so per-vertex data kept outside the mesh can be copied the same way
+    pub fn generate_tangents_with_copies(&mut self) -> Vec<u32> {
+        tangents::generate(&mut self.vertex_list, &mut self.tri_list)
+    }
+
+    // Reorders triangles for the post-transform vertex cache, see
+    // vertex_cache::optimize
+    pub fn optimize_vertex_cache(&mut self, cache_size: usize) -> &mut Self {
+        vertex_cache::optimize(&mut self.tri_list, self.vertex_list.len(), cache_size);
+        self
+    }
+
+    // Best done after optimize_vertex_cache, since it follows the index order
+    pub fn optimize_vertex_fetch(&mut self) -> &mut Self {
+        vertex_cache::optimize_fetch(&mut self.vertex_list, &mut self.tri_list);
+        self
+    }
+
+    pub fn acmr(&self, cache_size: usize) -> f32 {
+        vertex_cache::acmr(&self.tri_list, cache_size)
+    }
+
     pub fn preprocess(&mut self) -> &mut Self {
         self.normalize_size();
         self.move_to_origin();
@@ -189,6 +221,285 @@ impl Mesh {
         sum / self.vertex_list.len() as f32
     }
 
+    // Merges vertices whose position, normal, UV and tangent are all within
+    // `epsilon` of an earlier vertex, which keeps its attributes
+    pub fn weld(&mut self, epsilon: f32) -> &mut Self {
+        let epsilon = f32::max(epsilon, f32::EPSILON);
+        let cell = |pos: &[f32; 3]| {
+            let cell = |value: f32| (value / epsilon).floor() as i64;
+            (cell(pos[0]), cell(pos[1]), cell(pos[2]))
+        };
+
+        let close = |first: &[f32], second: &[f32]| {
+            first
+                .iter()
+                .zip(second)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f32>() <= epsilon * epsilon
+        };
+
+        let mut grid: HashMap<_, Vec<u32>> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.vertex_list.len());
+
+        for (index, vert) in self.vertex_list.iter().enumerate() {
+            let (x, y, z) = cell(&vert.pos);
+
+            // Anything within epsilon is in this cell or a neighbouring one
+            let neighbours = (-1..2).flat_map(|dx| {
+                (-1..2).flat_map(move |dy| (-1..2).map(move |dz| (x + dx, y + dy, z + dz)))
+            });
+
+            let existing = neighbours
+                .filter_map(|key| grid.get(&key))
+                .flat_map(|indices| indices.iter())
+                .find(|&&other| {
+                    let other = &self.vertex_list[other as usize];
+
+                    close(&vert.pos, &other.pos) && close(&vert.normal, &other.normal)
+                        && close(&vert.uv, &other.uv)
+                        && close(&vert.tangent, &other.tangent)
+                })
+                .cloned();
+
+            remap.push(existing.unwrap_or_else(|| {
+                grid.entry((x, y, z)).or_insert_with(Vec::new).push(index as u32);
+                index as u32
+            }));
+        }
+
+        for index in &mut self.tri_list {
+            *index = remap[*index as usize];
+        }
+
+        self.remove_unreferenced_vertices()
+    }
+
+    // Drops triangles that reuse a vertex or whose area is negligible next to
+    // their longest edge
+    pub fn remove_degenerate_tris(&mut self) -> &mut Self {
+        let verts = &self.vertex_list;
+
+        self.tri_list = self.tri_list
+            .chunks(3)
+            .filter(|tri| {
+                let pos = |corner: usize| Vector3::from(verts[tri[corner] as usize].pos);
+
+                tri[0] != tri[1] && tri[1] != tri[2] && tri[2] != tri[0]
+                    && !normals::is_degenerate(&pos(0), &pos(1), &pos(2))
+            })
+            .flat_map(|tri| tri.iter().cloned())
+            .collect();
+
+        self
+    }
+
+    // Drops repeats of a triangle with the same vertices and winding, the
+    // first one is kept. Triangles with opposite windings are both kept
+    pub fn remove_duplicate_tris(&mut self) -> &mut Self {
+        let mut seen = HashSet::new();
+
+        self.tri_list = self.tri_list
+            .chunks(3)
+            .filter(|tri| {
+                // Rotated so the smallest index is first
+                let first = (0..3).min_by_key(|&corner| tri[corner]).unwrap_or(0);
+                seen.insert((tri[first], tri[(first + 1) % 3], tri[(first + 2) % 3]))
+            })
+            .flat_map(|tri| tri.iter().cloned())
+            .collect();
+
+        self
+    }
+
+    // Keeps the order of the remaining vertices
+    pub fn remove_unreferenced_vertices(&mut self) -> &mut Self {
+        let mut referenced = vec![false; self.vertex_list.len()];
+
+        for &index in &self.tri_list {
+            referenced[index as usize] = true;
+        }
+
+        let mut remap = vec![0; self.vertex_list.len()];
+        let mut count = 0;
+
+        for (index, _) in referenced.iter().enumerate().filter(|&(_, &used)| used) {
+            self.vertex_list[count] = self.vertex_list[index];
+            remap[index] = count as u32;
+            count += 1;
+        }
+
+        self.vertex_list.truncate(count);
+
+        for index in &mut self.tri_list {
+            *index = remap[*index as usize];
+        }
+
+        self
+    }
+
+    // Reverses the winding of every triangle, normals are left as they are
+    pub fn flip_winding(&mut self) -> &mut Self {
+        for tri in self.tri_list.chunks_mut(3) {
+            tri.swap(1, 2);
+        }
+
+        self
+    }
+
+    // Gives every vertex one copy per group of faces around it that meet at
+    // less than `crease_angle` (radians), with a normal averaged over just
+    // those faces. Faces only count as neighbours when they share vertices,
+    // so seams already split by UVs stay split. Tangents are regenerated
+    pub fn split_hard_edges(&mut self, crease_angle: f32) -> &mut Self {
+        let verts = &self.vertex_list;
+        let tri_count = self.tri_list.len() / 3;
+
+        let face_normals: Vec<_> = self.tri_list
+            .chunks(3)
+            .map(|tri| {
+                let pos = |corner: usize| Vector3::from(verts[tri[corner] as usize].pos);
+                normals::face_normal(&pos(0), &pos(1), &pos(2))
+            })
+            .collect();
+
+        // Triangles on each edge, keyed by its vertices in increasing order
+        let mut edges: HashMap<_, Vec<usize>> = HashMap::new();
+
+        for (tri_ind, tri) in self.tri_list.chunks(3).enumerate() {
+            for corner in 0..3 {
+                let (first, second) = (tri[corner], tri[(corner + 1) % 3]);
+                edges
+                    .entry((u32::min(first, second), u32::max(first, second)))
+                    .or_insert_with(Vec::new)
+                    .push(tri_ind);
+            }
+        }
+
+        // Union find over corners, corners in the same set share a vertex
+        let mut parents: Vec<usize> = (0..tri_count * 3).collect();
+
+        fn find(parents: &mut [usize], mut corner: usize) -> usize {
+            while parents[corner] != corner {
+                parents[corner] = parents[parents[corner]];
+                corner = parents[corner];
+            }
+
+            corner
+        }
+
+        let cos_crease = crease_angle.cos();
+        let tri_list = &self.tri_list;
+        let corner_of = |tri: usize, vert: u32| {
+            (0..3)
+                .find(|&corner| tri_list[tri * 3 + corner] == vert)
+                .map(|corner| tri * 3 + corner)
+        };
+
+        for (&(first, second), tris) in &edges {
+            for (i, &tri) in tris.iter().enumerate() {
+                for &other in &tris[i + 1..] {
+                    let smooth = match (
+                        face_normals[tri].try_normalize(f32::EPSILON),
+                        face_normals[other].try_normalize(f32::EPSILON),
+                    ) {
+                        (Some(normal), Some(other_normal)) => {
+                            normal.dot(&other_normal) >= cos_crease
+                        }
+                        // Degenerate faces go along with their neighbours
+                        _ => true,
+                    };
+
+                    if !smooth {
+                        continue;
+                    }
+
+                    for &vert in &[first, second] {
+                        if let (Some(a), Some(b)) = (corner_of(tri, vert), corner_of(other, vert)) {
+                            let (a, b) = (find(&mut parents, a), find(&mut parents, b));
+                            parents[a] = b;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut group_normals = HashMap::new();
+
+        for corner in 0..tri_count * 3 {
+            let root = find(&mut parents, corner);
+            *group_normals.entry(root).or_insert_with(Vector3::zeros) += face_normals[corner / 3];
+        }
+
+        // The first group around a vertex keeps it, the others get copies
+        let mut group_verts = HashMap::new();
+        let mut used = vec![false; self.vertex_list.len()];
+
+        for corner in 0..tri_count * 3 {
+            let root = find(&mut parents, corner);
+            let index = self.tri_list[corner];
+
+            let new_index = *group_verts.entry(root).or_insert_with(|| {
+                let mut vert = self.vertex_list[index as usize];
+
+                if let Some(normal) = group_normals[&root].try_normalize(f32::EPSILON) {
+                    vert.normal = normal.into();
+                }
+
+                if used[index as usize] {
+                    self.vertex_list.push(vert);
+                    self.vertex_list.len() as u32 - 1
+                } else {
+                    used[index as usize] = true;
+                    self.vertex_list[index as usize] = vert;
+                    index
+                }
+            });
+
+            self.tri_list[corner] = new_index;
+        }
+
+        self.generate_tangents()
+    }
+
+    // Positions are transformed by `matrix`, normals by its inverse transpose
+    // and tangents like directions along the surface. Mirroring transforms
+    // also flip the winding and bitangent sign so faces keep facing out
+    pub fn transform(&mut self, matrix: &Matrix4<f32>) -> &mut Self {
+        let normal_matrix = matrix
+            .try_inverse()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or(*matrix);
+
+        let mirrored = matrix.fixed_view::<3, 3>(0, 0).determinant() < 0.0;
+        let sign = if mirrored { -1.0 } else { 1.0 };
+
+        let direction = |matrix: &Matrix4<f32>, value: &[f32], fallback: Vector3<f32>| {
+            let transformed = matrix * Vector4::new(value[0], value[1], value[2], 0.0);
+
+            Vector3::new(transformed.x, transformed.y, transformed.z)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or(fallback)
+        };
+
+        for vert in &mut self.vertex_list {
+            let pos = matrix * Vector4::new(vert.pos[0], vert.pos[1], vert.pos[2], 1.0);
+            let w = if pos.w != 0.0 { pos.w } else { 1.0 };
+
+            let normal = direction(&normal_matrix, &vert.normal, Vector3::from(vert.normal));
+            let tangent = direction(matrix, &vert.tangent[..3], Vector3::x());
+
+            vert.pos = [pos.x / w, pos.y / w, pos.z / w];
+            vert.normal = normal.into();
+            vert.tangent = [tangent.x, tangent.y, tangent.z, vert.tangent[3] * sign];
+        }
+
+        if mirrored {
+            self.flip_winding();
+        }
+
+        self
+    }
+
     fn normalize_size(&mut self) {
         let (min, max) = self.extents();
         let diff = max - min;
@@ -200,13 +511,12 @@ impl Mesh {
 
         self.vertex_list = self.vertex_list
             .iter()
-            .map(|&Vertex { pos, normal, uv }| {
-                let vert = Vector3::from(pos);
+            .map(|vert| {
+                let pos = Vector3::from(vert.pos);
 
                 Vertex {
-                    pos: ((1.0 / max_extent) * vert).into(),
-                    normal,
-                    uv,
+                    pos: ((1.0 / max_extent) * pos).into(),
+                    ..*vert
                 }
             })
             .collect();
@@ -216,12 +526,11 @@ impl Mesh {
         let center = self.centroid();
         self.vertex_list = self.vertex_list
             .iter()
-            .map(|&Vertex { pos, normal, uv }| {
-                let vert = Vector3::from(pos);
+            .map(|vert| {
+                let pos = Vector3::from(vert.pos);
                 Vertex {
-                    pos: (vert - center).into(),
-                    normal: normal,
-                    uv: uv,
+                    pos: (pos - center).into(),
+                    ..*vert
                 }
             })
             .collect();
@@ -0,0 +1,370 @@
+use std::f32;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+
+use na::Vector3;
+
+use mesh::Mesh;
+use mesh_loader::{LoadOptions, MeshLoadError, NormalSource, Recenter, UVSource, UpAxis};
+use model::{Model, Submesh};
+use program::Vertex;
+
+const MAGIC: &[u8; 4] = b"RGMC";
+// Bump whenever the layout below or the loader's output changes
+pub const CACHE_VERSION: u32 = 5;
+
+// Layout, all little endian:
+//   magic, version: u32, source hash: u64
+//   vertex count: u32, index count: u32, submesh count: u32
+//   bounds min: 3 x f32, bounds max: 3 x f32
+//   vertices: pos, normal, uv and tangent as 12 x f32 each
+//   indices: u32 each
+//   submeshes: name, has material: u8, material, start: u32, count: u32
+//   material library count: u32, material libraries
+// with strings stored as a u32 length followed by UTF-8 bytes
+pub struct CachedModel {
+    pub source_hash: u64,
+    pub bounds: (Vector3<f32>, Vector3<f32>),
+    pub model: Model,
+}
+
+// 64 bit FNV-1a, which unlike std's hashers is stable between builds
+struct Hasher(u64);
+
+impl Hasher {
+    fn new() -> Self {
+        Hasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.write(&value.to_bits().to_le_bytes());
+    }
+}
+
+fn hash_options(hasher: &mut Hasher, options: &LoadOptions) {
+    hasher.write(&[match options.uv_source {
+        None => 0,
+        Some(UVSource::PlanarX) => 1,
+        Some(UVSource::PlanarY) => 2,
+        Some(UVSource::PlanarZ) => 3,
+        Some(UVSource::Cylindrical) => 4,
+        Some(UVSource::Spherical) => 5,
+        Some(UVSource::Cube) => 6,
+    }]);
+
+    match options.normal_source {
+        None => hasher.write(&[0]),
+        Some(NormalSource::AreaWeighted) => hasher.write(&[1]),
+        Some(NormalSource::AngleWeighted) => hasher.write(&[2]),
+        Some(NormalSource::Flat) => hasher.write(&[3]),
+        Some(NormalSource::SmoothingGroups(crease_angle)) => {
+            hasher.write(&[4]);
+            hasher.write_f32(crease_angle);
+        }
+    }
+
+    hasher.write(&[
+        options.rescale as u8,
+        match options.recenter {
+            Recenter::None => 0,
+            Recenter::BoundingBox => 1,
+            Recenter::Centroid => 2,
+        },
+        match options.up_axis {
+            UpAxis::Y => 0,
+            UpAxis::Z => 1,
+        },
+        options.flip_winding as u8,
+        options.flip_v as u8,
+    ]);
+}
+
+// Hash of the source file's contents and the options it's loaded with, a
+// cache is only valid for the same pair
+pub fn source_hash(source: &[u8], options: &LoadOptions) -> u64 {
+    let mut hasher = Hasher::new();
+
+    hasher.write(source);
+    hash_options(&mut hasher, options);
+
+    hasher.0
+}
+
+pub fn cache_path(file_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.cache", file_path))
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    write_u32(writer, value.len() as u32)?;
+    writer.write_all(value.as_bytes())
+}
+
+fn write_floats<W: Write>(writer: &mut W, values: &[f32]) -> io::Result<()> {
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+pub fn write_cache<W: Write>(writer: &mut W, model: &Model, source_hash: u64) -> io::Result<()> {
+    let verts = model.mesh.vertices();
+    let indices = model.mesh.indices();
+
+    // An empty mesh has infinite extents, which are stored as zero
+    let (min, max) = if verts.is_empty() {
+        (Vector3::zeros(), Vector3::zeros())
+    } else {
+        model.mesh.extents()
+    };
+
+    writer.write_all(MAGIC)?;
+    write_u32(writer, CACHE_VERSION)?;
+    writer.write_all(&source_hash.to_le_bytes())?;
+
+    write_u32(writer, verts.len() as u32)?;
+    write_u32(writer, indices.len() as u32)?;
+    write_u32(writer, model.submeshes.len() as u32)?;
+
+    write_floats(writer, min.as_slice())?;
+    write_floats(writer, max.as_slice())?;
+
+    for vert in verts {
+        write_floats(writer, &vert.pos)?;
+        write_floats(writer, &vert.normal)?;
+        write_floats(writer, &vert.uv)?;
+        write_floats(writer, &vert.tangent)?;
+    }
+
+    for &index in indices {
+        write_u32(writer, index)?;
+    }
+
+    for submesh in &model.submeshes {
+        write_string(writer, &submesh.name)?;
+
+        match submesh.material {
+            Some(ref material) => {
+                writer.write_all(&[1])?;
+                write_string(writer, material)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+
+        write_u32(writer, submesh.start)?;
+        write_u32(writer, submesh.count)?;
+    }
+
+    write_u32(writer, model.material_libraries.len() as u32)?;
+
+    for library in &model.material_libraries {
+        write_string(writer, library)?;
+    }
+
+    Ok(())
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.data.len() - self.offset < count {
+            return Err(String::from("unexpected end of file"));
+        }
+
+        self.offset += count;
+        Ok(&self.data[self.offset - count..self.offset])
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from(self.u32()?) | (u64::from(self.u32()?) << 32))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        self.u32().map(f32::from_bits)
+    }
+
+    fn vector3(&mut self) -> Result<Vector3<f32>, String> {
+        Ok(Vector3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| String::from("bad string"))
+    }
+}
+
+fn parse_cache(data: &[u8]) -> Result<CachedModel, String> {
+    let mut reader = ByteReader { data, offset: 0 };
+
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(String::from("not a mesh cache"));
+    }
+
+    let version = reader.u32()?;
+
+    if version != CACHE_VERSION {
+        return Err(format!("cache version {}, expected {}", version, CACHE_VERSION));
+    }
+
+    let source_hash = reader.u64()?;
+
+    let vert_count = reader.u32()? as usize;
+    let index_count = reader.u32()? as usize;
+    let submesh_count = reader.u32()? as usize;
+
+    let bounds = (reader.vector3()?, reader.vector3()?);
+
+    let mut mesh = Mesh::new();
+
+    for _ in 0..vert_count {
+        let (pos, normal) = (reader.vector3()?, reader.vector3()?);
+        let uv = [reader.f32()?, reader.f32()?];
+        let tangent = [reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?];
+
+        mesh.add_vertex(&Vertex {
+            pos: pos.into(),
+            normal: normal.into(),
+            uv,
+            tangent,
+        });
+    }
+
+    if !index_count.is_multiple_of(3) {
+        return Err(String::from("index count is not a multiple of 3"));
+    }
+
+    for _ in 0..index_count / 3 {
+        let tri = (reader.u32()?, reader.u32()?, reader.u32()?);
+
+        if tri.0 as usize >= vert_count || tri.1 as usize >= vert_count
+            || tri.2 as usize >= vert_count
+        {
+            return Err(String::from("index out of range"));
+        }
+
+        mesh.add_tri(tri);
+    }
+
+    // Not preallocated, a corrupt count would otherwise reserve gigabytes
+    let mut submeshes = Vec::new();
+
+    for _ in 0..submesh_count {
+        let name = reader.string()?;
+        let material = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.string()?),
+        };
+        let (start, count) = (reader.u32()?, reader.u32()?);
+
+        if !start.is_multiple_of(3) || !count.is_multiple_of(3) {
+            return Err(format!("submesh '{}' doesn't cover whole triangles", name));
+        }
+
+        if start as usize + count as usize > index_count {
+            return Err(format!("submesh '{}' out of range", name));
+        }
+
+        submeshes.push(Submesh {
+            name,
+            material,
+            start,
+            count,
+        });
+    }
+
+    let mut model = Model::new(mesh, submeshes);
+
+    for _ in 0..reader.u32()? {
+        model.material_libraries.push(reader.string()?);
+    }
+
+    Ok(CachedModel {
+        source_hash,
+        bounds,
+        model,
+    })
+}
+
+pub fn read_cache(data: &[u8], file: &str) -> Result<CachedModel, MeshLoadError> {
+    parse_cache(data).map_err(|error| MeshLoadError::Format {
+        file: file.to_string(),
+        error,
+    })
+}
+
+// Reuses the cache next to the source file when its hash matches, otherwise
+// calls `load` and writes a new cache. Failing to write the cache doesn't
+// fail the load, the file is just parsed again next time. Warnings come from
+// parsing, so a cache hit reports none
+//
+// The cache is written to a temporary file and renamed into place, so a
+// crash or a concurrent load never leaves a partly written one behind
+pub(crate) fn load_or_build<F>(
+    file_path: &str,
+    options: &LoadOptions,
+    load: F,
+) -> Result<Model, MeshLoadError>
+where
+    F: FnOnce() -> Result<Model, MeshLoadError>,
+{
+    let source = fs::read(file_path).map_err(|error| MeshLoadError::Io {
+        file: file_path.to_string(),
+        error,
+    })?;
+
+    let hash = source_hash(&source, options);
+    let cache_file = cache_path(file_path);
+
+    if let Ok(data) = fs::read(&cache_file) {
+        if let Ok(cached) = parse_cache(&data) {
+            if cached.source_hash == hash {
+                return Ok(cached.model);
+            }
+        }
+    }
+
+    let model = load()?;
+
+    let temp_file = PathBuf::from(format!("{}.{}.tmp", cache_file.display(), process::id()));
+
+    let written = File::create(&temp_file).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+
+        write_cache(&mut writer, &model, hash)?;
+        writer.flush()
+    });
+
+    if written.and_then(|_| fs::rename(&temp_file, &cache_file)).is_err() {
+        let _ = fs::remove_file(&temp_file);
+    }
+
+    Ok(model)
+}
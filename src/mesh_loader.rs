@@ -1,31 +1,197 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
 use std::f32;
-use std::u32;
+use std::fmt;
+use std::fs::File;
+use std::hash::Hash;
 use std::str::FromStr;
 use std::io;
+use std::io::{BufRead, BufReader};
+use std::mem;
+use std::path::Path;
+
+use gfx::Resources;
+use gfx::traits::FactoryExt;
 
 use na::{Vector2, Vector3};
-use regex::{Captures, Regex};
 
+use material::Material;
 use mesh::Mesh;
+use mesh_cache;
+use model::{Model, Submesh};
+use mtl_loader::{self, MtlMaterial};
+use normals;
 use program::Vertex;
-use utility;
+use subdivision::PolygonMesh;
+use triangulation;
+use uv_mapping;
 
-type UVMapFn = fn(&Vector3<f32>) -> Vector2<f32>;
-type NormalComputeFn = fn(&[Vector3<f32>], &[(u32, u32, u32)])
-    -> Vec<Vector3<f32>>;
-type VertexIndex = (u32, u32, u32);
+// Reports a problem that doesn't stop the load
+pub type WarnFn = fn(&MeshLoadError);
+// Position, UV and normal indices of a face corner, already made zero based
+type VertexIndex = (u32, Option<u32>, Option<u32>);
 type Triangle = (VertexIndex, VertexIndex, VertexIndex);
 
-fn find_or_insert<T: Eq + Clone>(val: &T, cont: &mut Vec<T>) -> usize {
-    let index;
+// How UVs are generated when the file's own are not used, one of the
+// projections in uv_mapping. Named rather than given as functions so caches
+// can tell them apart between runs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UVSource {
+    PlanarX,
+    PlanarY,
+    PlanarZ,
+    Cylindrical,
+    Spherical,
+    Cube,
+}
 
-    if let Some(pos) = cont.iter().position(|check| check == val) {
-        index = pos;
-    } else {
-        index = cont.len();
-        cont.push(val.clone());
+impl UVSource {
+    // Called with the position and normal of a face corner
+    pub fn map(self, pos: &Vector3<f32>, normal: &Vector3<f32>) -> Vector2<f32> {
+        match self {
+            UVSource::PlanarX => uv_mapping::planar_x(pos, normal),
+            UVSource::PlanarY => uv_mapping::planar_y(pos, normal),
+            UVSource::PlanarZ => uv_mapping::planar_z(pos, normal),
+            UVSource::Cylindrical => uv_mapping::cylindrical(pos, normal),
+            UVSource::Spherical => uv_mapping::spherical(pos, normal),
+            UVSource::Cube => uv_mapping::cube(pos, normal),
+        }
+    }
+
+    // Whether u wraps from 1 back to 0, vertices of triangles crossing the
+    // seam are then split
+    pub fn wraps(self) -> bool {
+        matches!(self, UVSource::Cylindrical | UVSource::Spherical)
+    }
+}
+
+// How normals are generated when the file's own are not used
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalSource {
+    // One normal per position, see normals::smooth_area_weighted
+    AreaWeighted,
+    // One normal per position, see normals::smooth_angle_weighted
+    AngleWeighted,
+    Flat,
+    // Smooths within `s` groups, faces further apart than the crease angle
+    // (in radians) stay sharp
+    SmoothingGroups(f32),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Recenter {
+    None,
+    BoundingBox,
+    // Average of the positions, as Mesh::preprocess does
+    Centroid,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum UpAxis {
+    Y,
+    // Rotated so the file's Z axis ends up pointing along Y
+    Z,
+}
+
+// The default loads the file as is, with no generated normals or UVs
+#[derive(Clone, Copy)]
+pub struct LoadOptions {
+    pub uv_source: Option<UVSource>,
+    pub normal_source: Option<NormalSource>,
+    // Scales the largest side of the bounding box to 1, as Mesh::preprocess
+    pub rescale: bool,
+    pub recenter: Recenter,
+    pub up_axis: UpAxis,
+    // Turns the faces inside out, the file's normals are negated to match
+    pub flip_winding: bool,
+    pub flip_v: bool,
+    // Reuses or writes a binary cache next to the file, see mesh_cache
+    pub cache: bool,
+    // e.g. a missing material library, print_warning by default
+    pub warn: WarnFn,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            uv_source: None,
+            normal_source: None,
+            rescale: false,
+            recenter: Recenter::None,
+            up_axis: UpAxis::Y,
+            flip_winding: false,
+            flip_v: false,
+            cache: false,
+            warn: print_warning,
+        }
+    }
+}
+
+pub fn print_warning(warning: &MeshLoadError) {
+    eprintln!("warning: {}", warning);
+}
+
+struct Face {
+    verts: Vec<VertexIndex>,
+    smoothing_group: u32,
+    part: usize,
+}
+
+#[derive(Default)]
+struct ObjData {
+    verts: Vec<Vector3<f32>>,
+    normals: Vec<Vector3<f32>>,
+    uvs: Vec<Vector2<f32>>,
+    faces: Vec<Face>,
+    smoothing_group: u32,
+    has_smoothing_groups: bool,
+    // Name and material of every distinct o/g/usemtl combination
+    parts: Vec<(String, Option<String>)>,
+    material_libraries: Vec<String>,
+    object: String,
+    group: Option<String>,
+    material: Option<String>,
+    part: Option<usize>,
+}
+
+impl ObjData {
+    // Index of the part faces are currently added to, created on first use
+    fn current_part(&mut self) -> usize {
+        if let Some(part) = self.part {
+            return part;
+        }
+
+        let key = (
+            self.group.clone().unwrap_or_else(|| self.object.clone()),
+            self.material.clone(),
+        );
+
+        let part = match self.parts.iter().position(|existing| *existing == key) {
+            Some(part) => part,
+            None => {
+                self.parts.push(key);
+                self.parts.len() - 1
+            }
+        };
+
+        self.part = Some(part);
+        part
+    }
+}
+
+fn find_or_insert<T: Eq + Hash + Clone>(
+    val: &T,
+    cont: &mut Vec<T>,
+    lookup: &mut HashMap<T, usize>,
+) -> usize {
+    if let Some(&index) = lookup.get(val) {
+        return index;
     }
 
+    let index = cont.len();
+    cont.push(val.clone());
+    lookup.insert(val.clone(), index);
+
     index
 }
 
@@ -61,268 +227,857 @@ fn normalize_scale(verts: &[Vector3<f32>]) -> Vec<Vector3<f32>> {
 
     let max_extent = f32::max(x, f32::max(y, z));
 
+    // Nothing to scale for empty or single point meshes
+    if max_extent <= 0.0 || !max_extent.is_finite() {
+        return verts.to_vec();
+    }
+
     verts
         .iter()
-        .map(|vert| ((1.0 / max_extent) * vert))
+        .map(|vert| (1.0 / max_extent) * vert)
         .collect()
 }
 
-fn center_verts(verts: &[Vector3<f32>]) -> Vec<Vector3<f32>> {
-    let (min, max) = get_extents(verts);
-    let center = 0.5 * (max - min);
+fn center_verts(verts: &[Vector3<f32>], recenter: Recenter) -> Vec<Vector3<f32>> {
+    if verts.is_empty() {
+        return Vec::new();
+    }
+
+    let center = match recenter {
+        Recenter::None => return verts.to_vec(),
+        Recenter::BoundingBox => {
+            let (min, max) = get_extents(verts);
+            0.5 * (max + min)
+        }
+        Recenter::Centroid => {
+            verts.iter().fold(Vector3::zeros(), |acc, vert| acc + vert) / verts.len() as f32
+        }
+    };
 
     verts.iter().map(|vert| vert - center).collect()
 }
 
-fn get_tri_match(
-    uv_fn: &Option<UVMapFn>,
-    normal_fn: &Option<NormalComputeFn>,
-) -> Box<Fn(&str) -> Option<Triangle>> {
-    let normal_compute = normal_fn.is_some();
-    let uv_compute = uv_fn.is_some();
+fn z_up_to_y_up(vec: &Vector3<f32>) -> Vector3<f32> {
+    Vector3::new(vec.x, vec.z, -vec.y)
+}
 
-    let reg = Regex::new(get_tri_regex(&uv_fn, &normal_fn)).unwrap();
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    BadFloat(String),
+    BadIndex(String),
+    IndexOutOfRange { index: i64, count: usize },
+    MissingValue,
+    MalformedFace(String),
+    MissingNormals,
+    BadSmoothingGroup(String),
+    UnsupportedDirective(String),
+}
 
-    match (uv_compute, normal_compute) {
-        (false, false) => Box::new(move |line| {
-            let caps = reg.captures(line)?;
-            let v1 = (
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[2]).unwrap() - 1,
-                u32::from_str(&caps[3]).unwrap() - 1,
-            );
-            let v2 = (
-                u32::from_str(&caps[4]).unwrap() - 1,
-                u32::from_str(&caps[5]).unwrap() - 1,
-                u32::from_str(&caps[6]).unwrap() - 1,
-            );
-            let v3 = (
-                u32::from_str(&caps[7]).unwrap() - 1,
-                u32::from_str(&caps[8]).unwrap() - 1,
-                u32::from_str(&caps[9]).unwrap() - 1,
-            );
+#[derive(Debug)]
+pub enum MeshLoadError {
+    Io {
+        file: String,
+        error: io::Error,
+    },
+    Parse {
+        file: String,
+        line: usize,
+        column: usize,
+        reason: ParseErrorKind,
+    },
+    Texture {
+        file: String,
+        error: String,
+    },
+    // Problems with files that aren't line based
+    Format {
+        file: String,
+        error: String,
+    },
+}
 
-            Some((v1, v2, v3))
-        }),
-        (true, false) => Box::new(move |line| {
-            let caps = reg.captures(line)?;
-            let v1 = (
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[2]).unwrap() - 1,
-            );
-            let v2 = (
-                u32::from_str(&caps[3]).unwrap() - 1,
-                u32::from_str(&caps[3]).unwrap() - 1,
-                u32::from_str(&caps[4]).unwrap() - 1,
-            );
-            let v3 = (
-                u32::from_str(&caps[5]).unwrap() - 1,
-                u32::from_str(&caps[5]).unwrap() - 1,
-                u32::from_str(&caps[6]).unwrap() - 1,
-            );
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseErrorKind::BadFloat(ref token) => write!(f, "invalid number '{}'", token),
+            ParseErrorKind::BadIndex(ref token) => write!(f, "invalid index '{}'", token),
+            ParseErrorKind::IndexOutOfRange { index, count } => {
+                write!(f, "index {} out of range ({} defined)", index, count)
+            }
+            ParseErrorKind::MissingValue => write!(f, "expected a value"),
+            ParseErrorKind::MalformedFace(ref token) => {
+                write!(f, "malformed face vertex '{}'", token)
+            }
+            ParseErrorKind::MissingNormals => {
+                write!(f, "face vertex has no normal and no normal function was given")
+            }
+            ParseErrorKind::BadSmoothingGroup(ref token) => {
+                write!(f, "invalid smoothing group '{}'", token)
+            }
+            ParseErrorKind::UnsupportedDirective(ref name) => {
+                write!(f, "unsupported directive '{}'", name)
+            }
+        }
+    }
+}
+
+impl fmt::Display for MeshLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MeshLoadError::Io {
+                ref file,
+                ref error,
+            } => write!(f, "{}: {}", file, error),
+            MeshLoadError::Parse {
+                ref file,
+                line,
+                column,
+                ref reason,
+            } => write!(f, "{}:{}:{}: {}", file, line, column, reason),
+            MeshLoadError::Texture {
+                ref file,
+                ref error,
+            }
+            | MeshLoadError::Format {
+                ref file,
+                ref error,
+            } => write!(f, "{}: {}", file, error),
+        }
+    }
+}
+
+impl Error for MeshLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            MeshLoadError::Io { ref error, .. } => Some(error),
+            MeshLoadError::Parse { .. }
+            | MeshLoadError::Texture { .. }
+            | MeshLoadError::Format { .. } => None,
+        }
+    }
+}
+
+// Column and reason, the line and file are added by the caller
+pub(crate) type TokenError = (usize, ParseErrorKind);
 
-            Some((v1, v2, v3))
-        }),
-        _ => Box::new(|_| None),
+pub(crate) fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(pos) => &line[..pos],
+        None => line,
     }
 }
 
-fn get_tri_regex(uv_fn: &Option<UVMapFn>, normal_fn: &Option<NormalComputeFn>) -> &'static str {
-    let tri_reg;
+// Splits a line on whitespace, pairing each token with its one based byte
+// column. Statement::position turns these into what gets reported
+pub(crate) fn tokenize(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
 
-    let normal_compute = normal_fn.is_some();
-    let uv_compute = uv_fn.is_some();
+    for (pos, ch) in line.char_indices() {
+        match (ch.is_whitespace(), start) {
+            (true, Some(begin)) => {
+                tokens.push((begin + 1, &line[begin..pos]));
+                start = None;
+            }
+            (false, None) => start = Some(pos),
+            _ => {}
+        }
+    }
+
+    if let Some(begin) = start {
+        tokens.push((begin + 1, &line[begin..]));
+    }
+
+    tokens
+}
 
-    if normal_compute && uv_compute {
-        tri_reg = r"^(?:f) (\d+)(?:/\d*)* (\d+)(?:/\d*)* (\d+)(?:/\d*)*";
-    } else if normal_compute {
-        tri_reg = r"^(?:f) (\d+)/(\d+)(?:/\d*)* (\d+)/(\d+)(?:/\d*)* (\d+)/(\d+)(?:/\d*)*";
-    } else if uv_compute {
-        tri_reg = r"^(?:f) (\d+)/(?:\d+)?/(\d+) (\d+)/(?:\d+)?/(\d+) (\d+)/(?:\d+)?/(\d+)";
+pub(crate) fn parse_float(
+    token: Option<&(usize, &str)>,
+    line_len: usize,
+) -> Result<f32, TokenError> {
+    let &(column, text) = token.ok_or((line_len + 1, ParseErrorKind::MissingValue))?;
+
+    f32::from_str(text).map_err(|_| (column, ParseErrorKind::BadFloat(text.to_string())))
+}
+
+// Converts a one based (or negative, relative) OBJ index into a zero based one
+fn resolve_index(token: &str, count: usize, column: usize) -> Result<u32, TokenError> {
+    let index = i64::from_str(token)
+        .map_err(|_| (column, ParseErrorKind::BadIndex(token.to_string())))?;
+
+    let resolved = if index > 0 {
+        index - 1
+    } else if index < 0 {
+        count as i64 + index
     } else {
-        tri_reg = r"^(?:f) (\d+)/(\d+)/(\d+) (\d+)/(\d+)/(\d+) (\d+)/(\d+)/(\d+)";
+        return Err((column, ParseErrorKind::BadIndex(token.to_string())));
+    };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err((column, ParseErrorKind::IndexOutOfRange { index, count }));
     }
 
-    tri_reg
+    Ok(resolved as u32)
 }
 
-fn get_tri_from_caps(
-    caps: &Captures,
-    uv_compute: &Option<UVMapFn>,
-    normal_compute: &Option<NormalComputeFn>,
-) -> Triangle {
-    let (v1, v2, v3);
+fn parse_vertex_index(
+    (column, token): (usize, &str),
+    data: &ObjData,
+    require_normals: bool,
+) -> Result<VertexIndex, TokenError> {
+    let parts: Vec<_> = token.split('/').collect();
 
-    match (uv_compute.is_some(), normal_compute.is_some()) {
-        (true, true) => {
-            v1 = (
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[1]).unwrap() - 1,
-            );
-            v2 = (
-                u32::from_str(&caps[2]).unwrap() - 1,
-                u32::from_str(&caps[2]).unwrap() - 1,
-                u32::from_str(&caps[2]).unwrap() - 1,
-            );
-            v3 = (
-                u32::from_str(&caps[3]).unwrap() - 1,
-                u32::from_str(&caps[3]).unwrap() - 1,
-                u32::from_str(&caps[3]).unwrap() - 1,
-            );
+    if parts.len() > 3 || parts[0].is_empty() {
+        return Err((column, ParseErrorKind::MalformedFace(token.to_string())));
+    }
+
+    // Columns of the uv and normal parts within the line
+    let uv_column = column + parts[0].len() + 1;
+    let normal_column = uv_column + parts.get(1).map_or(0, |part| part.len()) + 1;
+
+    let vert = resolve_index(parts[0], data.verts.len(), column)?;
+
+    let uv = match parts.get(1) {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, data.uvs.len(), uv_column)?),
+        _ => None,
+    };
+
+    let normal = match parts.get(2) {
+        Some(part) if !part.is_empty() => {
+            Some(resolve_index(part, data.normals.len(), normal_column)?)
         }
-        (true, false) => {
-            v1 = (
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[2]).unwrap() - 1,
-            );
-            v2 = (
-                u32::from_str(&caps[3]).unwrap() - 1,
-                u32::from_str(&caps[3]).unwrap() - 1,
-                u32::from_str(&caps[4]).unwrap() - 1,
-            );
-            v3 = (
-                u32::from_str(&caps[5]).unwrap() - 1,
-                u32::from_str(&caps[5]).unwrap() - 1,
-                u32::from_str(&caps[6]).unwrap() - 1,
-            );
+        _ if require_normals => return Err((column, ParseErrorKind::MissingNormals)),
+        _ => None,
+    };
+
+    Ok((vert, uv, normal))
+}
+
+fn parse_line(line: &str, data: &mut ObjData, require_normals: bool) -> Result<(), TokenError> {
+    let tokens = tokenize(line);
+    let line_len = line.len();
+
+    let (directive_column, directive) = match tokens.first() {
+        Some(&first) => first,
+        None => return Ok(()),
+    };
+    let mut args = tokens[1..].iter();
+
+    match directive {
+        // Verts, the optional w component is ignored
+        "v" => {
+            let x = parse_float(args.next(), line_len)?;
+            let y = parse_float(args.next(), line_len)?;
+            let z = parse_float(args.next(), line_len)?;
+
+            data.verts.push(Vector3::new(x, y, z));
         }
-        (false, true) => {
-            v1 = (
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[2]).unwrap() - 1,
-                u32::from_str(&caps[1]).unwrap() - 1,
-            );
-            v2 = (
-                u32::from_str(&caps[3]).unwrap() - 1,
-                u32::from_str(&caps[4]).unwrap() - 1,
-                u32::from_str(&caps[3]).unwrap() - 1,
-            );
-            v3 = (
-                u32::from_str(&caps[5]).unwrap() - 1,
-                u32::from_str(&caps[6]).unwrap() - 1,
-                u32::from_str(&caps[5]).unwrap() - 1,
-            );
+        // Normals
+        "vn" => {
+            let x = parse_float(args.next(), line_len)?;
+            let y = parse_float(args.next(), line_len)?;
+            let z = parse_float(args.next(), line_len)?;
+
+            data.normals.push(Vector3::new(x, y, z));
         }
-        (false, false) => {
-            v1 = (
-                u32::from_str(&caps[1]).unwrap() - 1,
-                u32::from_str(&caps[2]).unwrap() - 1,
-                u32::from_str(&caps[3]).unwrap() - 1,
-            );
-            v2 = (
-                u32::from_str(&caps[4]).unwrap() - 1,
-                u32::from_str(&caps[5]).unwrap() - 1,
-                u32::from_str(&caps[6]).unwrap() - 1,
-            );
-            v3 = (
-                u32::from_str(&caps[7]).unwrap() - 1,
-                u32::from_str(&caps[8]).unwrap() - 1,
-                u32::from_str(&caps[9]).unwrap() - 1,
-            );
+        // UVs, v defaults to 0 and w is ignored
+        "vt" => {
+            let u = parse_float(args.next(), line_len)?;
+            let v = match args.next() {
+                Some(token) => parse_float(Some(token), line_len)?,
+                None => 0.0,
+            };
+
+            data.uvs.push(Vector2::new(u, v));
+        }
+        // Faces
+        "f" => {
+            let face = args.map(|&token| parse_vertex_index(token, data, require_normals))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if face.len() < 3 {
+                return Err((line_len + 1, ParseErrorKind::MissingValue));
+            }
+
+            let part = data.current_part();
+
+            data.faces.push(Face {
+                verts: face,
+                smoothing_group: data.smoothing_group,
+                part,
+            });
+        }
+        // Smoothing groups, 0 and off both mean flat shading
+        "s" => {
+            let &(column, token) = args.next()
+                .ok_or((line_len + 1, ParseErrorKind::MissingValue))?;
+
+            data.smoothing_group = match token {
+                "off" => 0,
+                _ => u32::from_str(token).map_err(|_| {
+                    (column, ParseErrorKind::BadSmoothingGroup(token.to_string()))
+                })?,
+            };
+            data.has_smoothing_groups = true;
+        }
+        // Objects, groups and materials split the model into submeshes
+        "o" | "g" | "usemtl" => {
+            let name = args.map(|&(_, text)| text).collect::<Vec<_>>().join(" ");
+
+            match directive {
+                "o" => {
+                    data.object = name;
+                    data.group = None;
+                }
+                "g" => data.group = if name.is_empty() { None } else { Some(name) },
+                _ => data.material = if name.is_empty() { None } else { Some(name) },
+            }
+
+            data.part = None;
+        }
+        // Libraries are loaded by load_materials
+        "mtllib" => {
+            data.material_libraries
+                .extend(args.map(|&(_, name)| name.to_string()));
+        }
+        // Merging groups, lines, points and render attributes have no effect
+        // on the mesh
+        "mg" | "l" | "p" | "bevel" | "c_interp"
+        | "d_interp" | "lod" | "maplib" | "usemap" | "shadow_obj" | "trace_obj" | "ctech"
+        | "stech" => {}
+        // Free-form geometry and anything unknown, which parse_obj skips with
+        // a warning since the mesh may be missing parts
+        _ => {
+            return Err((
+                directive_column,
+                ParseErrorKind::UnsupportedDirective(directive.to_string()),
+            ))
         }
     }
 
-    (v1, v2, v3)
+    Ok(())
 }
 
-pub fn load_file_with(
-    file_path: &str,
-    uv_fn: &Option<UVMapFn>,
-    normal_fn: &Option<NormalComputeFn>,
-) -> Result<Mesh, io::Error> {
-    let content = utility::read_in_file(file_path)?;
+// A statement joined from one or more lines, with where each line starts in
+// it so positions can be reported on the line they came from
+pub(crate) struct Statement<'a> {
+    pub text: &'a str,
+    // Line number and byte offset into `text` of each line
+    lines: &'a [(usize, usize)],
+}
 
-    // Raw vectors for constructing final info
-    // Verts, normals, UVs, faces
-    let initial = (vec![], vec![], vec![], vec![]);
+impl<'a> Statement<'a> {
+    // Line number and one based character column of a one based byte column
+    // in the statement, such as those from tokenize
+    pub fn position(&self, column: usize) -> (usize, usize) {
+        let mut offset = column.saturating_sub(1).min(self.text.len());
 
-    // Final vectors after parsing is done
-    let mut tri_enumerate = Vec::new();
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
 
-    let mut mesh = Mesh::new();
+        let (line, start) = self.lines
+            .iter()
+            .rev()
+            .find(|&&(_, start)| start <= offset)
+            .cloned()
+            .unwrap_or((0, 0));
 
-    let tri_reg = get_tri_match(&uv_fn, &normal_fn);
-
-    let vert_reg = Regex::new(
-        r"^(?:v) (-?[\d]+(?:\.[\d]+)?) (-?[\d]+(?:\.[\d]+)?) (-?[\d]+(?:\.[\d]+)?)",
-    ).unwrap();
-    let normal_reg = Regex::new(
-        r"^(?:vn) (-?[\d]+(?:\.[\d]+)?) (-?[\d]+(?:\.[\d]+)?) (-?[\d]+(?:\.[\d]+)?)",
-    ).unwrap();
-    let uv_reg = Regex::new(r"^(?:vt) (-?[\d]+(?:\.[\d]+)?) (-?[\d]+(?:\.[\d]+)?)").unwrap();
-
-    // Parse verts into raw components
-    let (mut verts_raw, normals_raw, mut uvs_raw, tris_raw) = content.lines().fold(
-        initial,
-        |(mut verts, mut norms, mut uvs, mut tris), line| {
-            // Verts
-            if let Some(caps) = vert_reg.captures(line) {
-                let x = f32::from_str(&caps[1]).unwrap();
-                let y = f32::from_str(&caps[2]).unwrap();
-                let z = f32::from_str(&caps[3]).unwrap();
-
-                verts.push(Vector3::new(x, y, z));
-            // Normals
-            } else if let Some(caps) = normal_reg.captures(line) {
-                if normal_fn.is_none() {
-                    let x = f32::from_str(&caps[1]).unwrap();
-                    let y = f32::from_str(&caps[2]).unwrap();
-                    let z = f32::from_str(&caps[3]).unwrap();
-
-                    norms.push(Vector3::new(x, y, z));
-                }
-            // UVs
-            } else if let Some(caps) = uv_reg.captures(line) {
-                if uv_fn.is_none() {
-                    let x = f32::from_str(&caps[1]).unwrap();
-                    let y = f32::from_str(&caps[2]).unwrap();
+        (line, self.text[start..offset].chars().count() + 1)
+    }
+
+    pub fn error(&self, file: &str, (column, reason): TokenError) -> MeshLoadError {
+        let (line, column) = self.position(column);
+
+        MeshLoadError::Parse {
+            file: file.to_string(),
+            line,
+            column,
+            reason,
+        }
+    }
+}
+
+// Reads the file a line at a time, joining lines ending in a backslash and
+// stripping comments, then calls `parse` with each statement. Shared with the
+// MTL and STL loaders
+pub(crate) fn for_each_statement<B, F>(
+    reader: B,
+    file: &str,
+    mut parse: F,
+) -> Result<(), MeshLoadError>
+where
+    B: BufRead,
+    F: FnMut(&Statement) -> Result<(), TokenError>,
+{
+    let mut current = String::new();
+    let mut lines = Vec::new();
+
+    let mut parse_lines = |text: &str, lines: &[(usize, usize)]| {
+        let statement = Statement { text, lines };
 
-                    uvs.push(Vector2::new(x, y));
+        parse(&statement).map_err(|error| statement.error(file, error))
+    };
+
+    for (num, line) in reader.lines().enumerate() {
+        let line = line.map_err(|error| MeshLoadError::Io {
+            file: file.to_string(),
+            error,
+        })?;
+
+        lines.push((num + 1, current.len()));
+
+        let trimmed = strip_comment(&line).trim_end();
+
+        if let Some(joined) = trimmed.strip_suffix('\\') {
+            current.push_str(joined);
+            current.push(' ');
+        } else if current.is_empty() {
+            parse_lines(trimmed, &lines)?;
+            lines.clear();
+        } else {
+            current.push_str(trimmed);
+            parse_lines(&current, &lines)?;
+            current.clear();
+            lines.clear();
+        }
+    }
+
+    if !current.is_empty() {
+        parse_lines(&current, &lines)?;
+    }
+
+    Ok(())
+}
+
+// Unsupported directives are skipped, with a warning the first time each one
+// is seen
+fn parse_obj<B: BufRead>(
+    reader: B,
+    file: &str,
+    require_normals: bool,
+    warn: WarnFn,
+) -> Result<ObjData, MeshLoadError> {
+    let mut data = ObjData::default();
+    let mut skipped = HashSet::new();
+
+    for_each_statement(reader, file, |statement| {
+        match parse_line(statement.text, &mut data, require_normals) {
+            Err((column, ParseErrorKind::UnsupportedDirective(name))) => {
+                if skipped.insert(name.clone()) {
+                    warn(&statement.error(
+                        file,
+                        (column, ParseErrorKind::UnsupportedDirective(name)),
+                    ));
                 }
-            // tris
-            } else if let Some(tri) = tri_reg(line) {
-                tris.push(tri);
+
+                Ok(())
             }
+            result => result,
+        }
+    })?;
 
-            (verts, norms, uvs, tris)
-        },
-    );
-    //verts_raw = center_verts(&verts_raw);
-    verts_raw = normalize_scale(&verts_raw);
-    //verts_raw = center_verts(&verts_raw);
+    Ok(data)
+}
+
+// Returns the triangles along with the smoothing group and part of each.
+// Triangles are ordered by part so every part is one contiguous range
+fn triangulate(faces: &[Face], verts: &[Vector3<f32>]) -> (Vec<Triangle>, Vec<u32>, Vec<usize>) {
+    let mut tris = Vec::new();
+    let mut groups = Vec::new();
+    let mut parts = Vec::new();
+
+    let mut order: Vec<_> = faces.iter().collect();
+    order.sort_by_key(|face| face.part);
+
+    for face in order {
+        let points: Vec<_> = face.verts
+            .iter()
+            .map(|&(vert, ..)| verts[vert as usize])
+            .collect();
+
+        for (first, second, third) in triangulation::triangulate_polygon(&points) {
+            tris.push((face.verts[first], face.verts[second], face.verts[third]));
+            groups.push(face.smoothing_group);
+            parts.push(face.part);
+        }
+    }
+
+    (tris, groups, parts)
+}
+
+fn build_submeshes(parts: &[(String, Option<String>)], tri_parts: &[usize]) -> Vec<Submesh> {
+    let mut submeshes: Vec<Submesh> = Vec::new();
+
+    for (tri_ind, &part) in tri_parts.iter().enumerate() {
+        match submeshes.last_mut() {
+            Some(ref mut submesh) if tri_ind > 0 && tri_parts[tri_ind - 1] == part => {
+                submesh.count += 3;
+                continue;
+            }
+            _ => {}
+        }
+
+        let (ref name, ref material) = parts[part];
+
+        submeshes.push(Submesh {
+            name: name.clone(),
+            material: material.clone(),
+            start: tri_ind as u32 * 3,
+            count: 3,
+        });
+    }
+
+    submeshes
+}
 
-    if let &Some(ref func) = uv_fn {
-        uvs_raw = verts_raw.iter().map(func).collect();
+// Loads the file as one mesh split into a submesh per o/g/usemtl group
+pub fn load_model_with(file_path: &str, options: &LoadOptions) -> Result<Model, MeshLoadError> {
+    if options.cache {
+        mesh_cache::load_or_build(file_path, options, || parse_model(file_path, options))
+    } else {
+        parse_model(file_path, options)
+    }
+}
+
+fn parse_model(file_path: &str, options: &LoadOptions) -> Result<Model, MeshLoadError> {
+    let uv_source = &options.uv_source;
+    let normal_source = &options.normal_source;
+
+    let file = File::open(file_path).map_err(|error| MeshLoadError::Io {
+        file: file_path.to_string(),
+        error,
+    })?;
+
+    let ObjData {
+        mut verts,
+        mut normals,
+        uvs,
+        faces,
+        has_smoothing_groups,
+        parts,
+        material_libraries,
+        ..
+    } = parse_obj(
+        BufReader::new(file),
+        file_path,
+        normal_source.is_none(),
+        options.warn,
+    )?;
+
+    if options.up_axis == UpAxis::Z {
+        verts = verts.iter().map(z_up_to_y_up).collect();
+        normals = normals.iter().map(z_up_to_y_up).collect();
+    }
+
+    if options.rescale {
+        verts = normalize_scale(&verts);
     }
 
+    verts = center_verts(&verts, options.recenter);
+
+    let (mut tris, mut groups, tri_parts) = triangulate(&faces, &verts);
+
+    if options.flip_winding {
+        for &mut (_, ref mut second, ref mut third) in &mut tris {
+            mem::swap(second, third);
+        }
+
+        normals = normals.iter().map(|normal| -normal).collect();
+    }
+
+    let pos_tris: Vec<_> = tris.iter()
+        .map(|&((first, ..), (second, ..), (third, ..))| (first, second, third))
+        .collect();
+
+    // Normals and the normal index of every triangle corner, if generated
+    let (normals, corner_normals) = match *normal_source {
+        Some(NormalSource::AreaWeighted) | Some(NormalSource::AngleWeighted) => {
+            let corners = pos_tris
+                .iter()
+                .flat_map(|&(first, second, third)| vec![first, second, third])
+                .collect();
+
+            let normals = if *normal_source == Some(NormalSource::AreaWeighted) {
+                normals::smooth_area_weighted(&verts, &pos_tris)
+            } else {
+                normals::smooth_angle_weighted(&verts, &pos_tris)
+            };
+
+            (normals, Some(corners))
+        }
+        Some(NormalSource::Flat) => {
+            let (normals, corners) = normals::flat(&verts, &pos_tris);
+            (normals, Some(corners))
+        }
+        Some(NormalSource::SmoothingGroups(crease_angle)) => {
+            // Without any s directives the crease angle alone decides
+            if !has_smoothing_groups {
+                groups = vec![1; groups.len()];
+            }
+
+            let (normals, corners) =
+                normals::smoothing_groups(&verts, &pos_tris, &groups, crease_angle);
+            (normals, Some(corners))
+        }
+        None => (normals, None),
+    };
+
+    // Missing normals are rejected while parsing
+    let normal_index = |(_, _, normal): VertexIndex, corner: usize| -> u32 {
+        match corner_normals {
+            Some(ref corners) => corners[corner],
+            None => normal.unwrap(),
+        }
+    };
+
+    // UVs and the UV index of every triangle corner, if generated
+    let (uvs, corner_uvs) = match *uv_source {
+        Some(source) => {
+            let mut generated = Vec::new();
+            let mut corners = Vec::with_capacity(tris.len() * 3);
+            let mut lookup = HashMap::new();
+
+            for (tri_ind, &(first, second, third)) in tris.iter().enumerate() {
+                let tri = [first, second, third];
+
+                let mut tri_uvs = [Vector2::zeros(); 3];
+                for (offset, &vert_ind) in tri.iter().enumerate() {
+                    let normal = normals[normal_index(vert_ind, tri_ind * 3 + offset) as usize];
+                    tri_uvs[offset] = source.map(&verts[vert_ind.0 as usize], &normal);
+                }
+
+                if source.wraps() {
+                    uv_mapping::fix_seam(&mut tri_uvs);
+                }
+
+                for (&(vert, ..), uv) in tri.iter().zip(tri_uvs.iter()) {
+                    let key = (vert, uv.x.to_bits(), uv.y.to_bits());
+
+                    let index = *lookup.entry(key).or_insert_with(|| {
+                        generated.push(*uv);
+                        generated.len() as u32 - 1
+                    });
+
+                    corners.push(index);
+                }
+            }
+
+            (generated, Some(corners))
+        }
+        None => (uvs, None),
+    };
+
+    let resolve = |vert_ind: VertexIndex, corner: usize| -> VertexIndex {
+        (
+            vert_ind.0,
+            match corner_uvs {
+                Some(ref corners) => Some(corners[corner]),
+                None => vert_ind.1,
+            },
+            Some(normal_index(vert_ind, corner)),
+        )
+    };
+
+    // Final vectors after parsing is done
+    let mut tri_enumerate = Vec::new();
+    let mut tri_lookup = HashMap::new();
+
+    let mut mesh = Mesh::new();
+
     // Enumerate unique verticies and index faces
-    for (first, second, third) in tris_raw {
-        let first_ind = find_or_insert(&first, &mut tri_enumerate) as u32;
-        let second_ind = find_or_insert(&second, &mut tri_enumerate) as u32;
-        let third_ind = find_or_insert(&third, &mut tri_enumerate) as u32;
+    for (tri_ind, (first, second, third)) in tris.into_iter().enumerate() {
+        let corner = tri_ind * 3;
+
+        let mut index = |vert_ind: VertexIndex, corner: usize| {
+            find_or_insert(&resolve(vert_ind, corner), &mut tri_enumerate, &mut tri_lookup) as u32
+        };
+
+        let first_ind = index(first, corner);
+        let second_ind = index(second, corner + 1);
+        let third_ind = index(third, corner + 2);
 
         mesh.add_tri((first_ind, second_ind, third_ind));
     }
 
     // Stitch together verticies from raw data
     for (vert_ind, uv_ind, norm_ind) in tri_enumerate {
-        let vert = verts_raw[vert_ind as usize];
-        let norm = normals_raw[norm_ind as usize];
-        let uv = uvs_raw[uv_ind as usize];
+        let vert = verts[vert_ind as usize];
+
+        let norm = normals[norm_ind.unwrap() as usize];
+
+        // Faces without texture coordinates get a zero UV
+        let mut uv = uv_ind
+            .and_then(|ind| uvs.get(ind as usize))
+            .cloned()
+            .unwrap_or_else(Vector2::zeros);
+
+        if options.flip_v {
+            uv.y = 1.0 - uv.y;
+        }
 
         mesh.add_vertex(&Vertex {
             pos: vert.into(),
             normal: norm.into(),
             uv: uv.into(),
+            tangent: [0.0; 4],
         });
     }
 
-    Ok(mesh)
+    mesh.generate_tangents();
+
+    let mut model = Model::new(mesh, build_submeshes(&parts, &tri_parts));
+    model.material_libraries = material_libraries;
+
+    Ok(model)
+}
+
+pub fn load_file_with(file_path: &str, options: &LoadOptions) -> Result<Mesh, MeshLoadError> {
+    load_model_with(file_path, options).map(|model| model.mesh)
+}
+
+// Loads the faces without triangulating them, for subdivision. Normals are
+// taken from the file or left zero, generated UVs and normals and the cache
+// aren't used, the other options are
+pub fn load_polygons_with(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<PolygonMesh, MeshLoadError> {
+    let file = File::open(file_path).map_err(|error| MeshLoadError::Io {
+        file: file_path.to_string(),
+        error,
+    })?;
+
+    let ObjData {
+        mut verts,
+        mut normals,
+        uvs,
+        faces,
+        ..
+    } = parse_obj(BufReader::new(file), file_path, false, options.warn)?;
+
+    if options.up_axis == UpAxis::Z {
+        verts = verts.iter().map(z_up_to_y_up).collect();
+        normals = normals.iter().map(z_up_to_y_up).collect();
+    }
+
+    if options.rescale {
+        verts = normalize_scale(&verts);
+    }
+
+    verts = center_verts(&verts, options.recenter);
+
+    if options.flip_winding {
+        normals = normals.iter().map(|normal| -normal).collect();
+    }
+
+    let mut polygons = PolygonMesh::new();
+    let mut lookup = HashMap::new();
+
+    for face in &faces {
+        let mut indices: Vec<u32> = face.verts
+            .iter()
+            .map(|&(vert_ind, uv_ind, norm_ind)| {
+                *lookup.entry((vert_ind, uv_ind, norm_ind)).or_insert_with(|| {
+                    let mut uv = uv_ind
+                        .and_then(|ind| uvs.get(ind as usize))
+                        .cloned()
+                        .unwrap_or_else(Vector2::zeros);
+
+                    if options.flip_v {
+                        uv.y = 1.0 - uv.y;
+                    }
+
+                    let normal = norm_ind
+                        .and_then(|ind| normals.get(ind as usize))
+                        .cloned()
+                        .unwrap_or_else(Vector3::zeros);
+
+                    polygons.vertices.push(Vertex {
+                        pos: verts[vert_ind as usize].into(),
+                        normal: normal.into(),
+                        uv: uv.into(),
+                        tangent: [0.0; 4],
+                    });
+
+                    polygons.vertices.len() as u32 - 1
+                })
+            })
+            .collect();
+
+        if options.flip_winding {
+            indices.reverse();
+        }
+
+        polygons.faces.push(indices);
+    }
+
+    Ok(polygons)
+}
+
+pub fn load_polygons(file_path: &str) -> Result<PolygonMesh, MeshLoadError> {
+    load_polygons_with(file_path, &LoadOptions::default())
+}
+
+// The materials the model's submeshes use, from the libraries its OBJ named,
+// `file_path` being that OBJ. A missing library or a material none of them
+// define is a warning, submeshes without a material are drawn with the
+// object's. Texture paths are relative to the OBJ file
+pub fn load_mtl_materials(
+    file_path: &str,
+    model: &Model,
+    options: &LoadOptions,
+) -> Result<Vec<MtlMaterial>, MeshLoadError> {
+    let base_dir = Path::new(file_path).parent().unwrap_or_else(|| Path::new(""));
+
+    let mut available = Vec::new();
+
+    for library in &model.material_libraries {
+        let library = base_dir.join(library).to_string_lossy().into_owned();
+
+        match mtl_loader::load_file(&library, base_dir) {
+            Ok(materials) => available.extend(materials),
+            Err(error @ MeshLoadError::Io { .. }) => (options.warn)(&error),
+            Err(error) => return Err(error),
+        }
+    }
+
+    let mut used: Vec<MtlMaterial> = Vec::new();
+
+    for name in model.submeshes.iter().filter_map(|submesh| submesh.material.as_ref()) {
+        if used.iter().any(|material| material.name == *name) {
+            continue;
+        }
+
+        match available.iter().find(|material| material.name == *name) {
+            Some(material) => used.push(material.clone()),
+            None => (options.warn)(&MeshLoadError::Format {
+                file: file_path.to_string(),
+                error: format!("unknown material '{}', using the object's instead", name),
+            }),
+        }
+    }
+
+    Ok(used)
+}
+
+// load_mtl_materials with the materials built, for Model::build
+pub fn load_materials<R: Resources, F: FactoryExt<R>>(
+    factory: &mut F,
+    file_path: &str,
+    model: &Model,
+    options: &LoadOptions,
+) -> Result<Vec<(String, Material<R>)>, MeshLoadError> {
+    load_mtl_materials(file_path, model, options)?
+        .iter()
+        .map(|material| Ok((material.name.clone(), material.build(factory)?)))
+        .collect()
+}
+
+pub fn load_file(file_path: &str) -> Result<Mesh, MeshLoadError> {
+    load_file_with(file_path, &LoadOptions::default())
 }
 
-pub fn load_file(file_path: &str) -> Result<Mesh, io::Error> {
-    load_file_with(file_path, &None, &None)
+pub fn load_model(file_path: &str) -> Result<Model, MeshLoadError> {
+    load_model_with(file_path, &LoadOptions::default())
 }
@@ -0,0 +1,81 @@
+use gfx::{Resources, Slice};
+use gfx::traits::FactoryExt;
+
+use material::Material;
+use mesh::{Mesh, MeshData};
+
+// Range of the shared index list drawn with one material
+#[derive(Clone)]
+pub struct Submesh {
+    pub name: String,
+    pub material: Option<String>,
+    pub start: u32,
+    pub count: u32,
+}
+
+pub struct ModelPart<R: Resources> {
+    pub slice: Slice<R>,
+    pub material: Option<Material<R>>,
+}
+
+pub struct ModelData<R: Resources> {
+    mesh_data: MeshData<R>,
+    parts: Vec<ModelPart<R>>,
+}
+
+impl<R: Resources> ModelData<R> {
+    pub fn mesh_data_ref(&self) -> &MeshData<R> {
+        &self.mesh_data
+    }
+
+    pub fn parts(&self) -> &[ModelPart<R>] {
+        &self.parts
+    }
+}
+
+pub struct Model {
+    pub mesh: Mesh,
+    pub submeshes: Vec<Submesh>,
+    // mtllib names from an OBJ, relative to its directory
+    pub material_libraries: Vec<String>,
+}
+
+impl Model {
+    pub fn new(mesh: Mesh, submeshes: Vec<Submesh>) -> Self {
+        Model {
+            mesh,
+            submeshes,
+            material_libraries: Vec::new(),
+        }
+    }
+
+    // Submeshes take their material from `materials` by name, ones without a
+    // match are drawn with the object's material
+    pub fn build<R: Resources, F: FactoryExt<R>>(
+        &self,
+        factory: &mut F,
+        materials: &[(String, Material<R>)],
+    ) -> Result<ModelData<R>, &'static str> {
+        let mesh_data = self.mesh.build(factory)?;
+
+        let parts = self.submeshes
+            .iter()
+            .map(|submesh| {
+                let mut slice = mesh_data.slice_ref().clone();
+                slice.start = submesh.start;
+                slice.end = submesh.start + submesh.count;
+
+                let material = submesh.material.as_ref().and_then(|name| {
+                    materials
+                        .iter()
+                        .find(|(mat_name, _)| mat_name == name)
+                        .map(|(_, material)| material.clone())
+                });
+
+                ModelPart { slice, material }
+            })
+            .collect();
+
+        Ok(ModelData { mesh_data, parts })
+    }
+}
@@ -0,0 +1,280 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use gfx::Resources;
+use gfx::traits::FactoryExt;
+
+use color::Color;
+use material::Material;
+use mesh_loader::{self, MeshLoadError, ParseErrorKind, TokenError};
+use texture::{self, Texture};
+
+#[derive(Clone)]
+pub struct MtlMaterial {
+    pub name: String,
+    pub ambient_color: Color,
+    pub diffuse_color: Color,
+    pub specular_color: Color,
+    pub specular_power: f32,
+    // Kept in the diffuse color's alpha, which is drawn opaque
+    pub dissolve: f32,
+    pub illumination: u32,
+    pub diffuse_map: Option<PathBuf>,
+    pub specular_map: Option<PathBuf>,
+    // Used as the normal map when there's no "norm", since exporters such as
+    // Blender's write tangent space normal maps as map_Bump
+    pub bump_map: Option<PathBuf>,
+    // Tangent space normal map from the "norm" extension
+    pub normal_map: Option<PathBuf>,
+}
+
+impl MtlMaterial {
+    pub fn new(name: &str) -> Self {
+        MtlMaterial {
+            name: name.to_string(),
+            ambient_color: Color::black(),
+            diffuse_color: Color::rgb_f32(0.8, 0.8, 0.8),
+            specular_color: Color::black(),
+            specular_power: 1.0,
+            dissolve: 1.0,
+            illumination: 2,
+            diffuse_map: None,
+            specular_map: None,
+            bump_map: None,
+            normal_map: None,
+        }
+    }
+
+    // Loads the maps and converts to a Material. Illumination models 0 and 1
+    // have no highlights, so their specular color is dropped
+    pub fn build<R: Resources, F: FactoryExt<R>>(
+        &self,
+        factory: &mut F,
+    ) -> Result<Material<R>, MeshLoadError> {
+        let specular_color = if self.illumination < 2 {
+            Color::black()
+        } else {
+            self.specular_color
+        };
+
+        let mut diffuse_color = self.diffuse_color;
+        diffuse_color.a = (self.dissolve.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        let normal_map = if self.normal_map.is_some() {
+            &self.normal_map
+        } else {
+            &self.bump_map
+        };
+
+        if self.diffuse_map.is_none() && self.specular_map.is_none() && normal_map.is_none() {
+            return Ok(Material::Untextured {
+                diffuse_color,
+                ambient_color: self.ambient_color,
+                specular_color,
+                specular_power: self.specular_power,
+            });
+        }
+
+        let normal_texture = match *normal_map {
+            Some(_) => Some(load_map(factory, normal_map, Color::black())?),
+            None => None,
+        };
+
+        // Only a real map_Ks replaces Ks and Ns, a missing map_Kd is white so
+        // Kd comes through unchanged
+        let specular_texture = match self.specular_map {
+            Some(_) => Some(load_map(factory, &self.specular_map, Color::black())?),
+            None => None,
+        };
+
+        Ok(Material::Textured {
+            diffuse_color,
+            ambient_color: self.ambient_color,
+            specular_color,
+            specular_power: self.specular_power,
+            diffuse_texture: load_map(factory, &self.diffuse_map, Color::white())?,
+            specular_texture,
+            normal_texture,
+        })
+    }
+}
+
+fn load_map<R: Resources, F: FactoryExt<R>>(
+    factory: &mut F,
+    map: &Option<PathBuf>,
+    fallback: Color,
+) -> Result<Texture<R>, MeshLoadError> {
+    match *map {
+        Some(ref path) => {
+            let file = path.to_string_lossy().into_owned();
+
+            texture::load_texture(factory, &file)
+                .map_err(|error| MeshLoadError::Texture { file, error })
+        }
+        None => texture::create_solid_texture(factory, fallback).map_err(|error| {
+            MeshLoadError::Texture {
+                file: String::new(),
+                error,
+            }
+        }),
+    }
+}
+
+fn parse_color(args: &[(usize, &str)], line_len: usize) -> Result<Color, TokenError> {
+    // "spectral" and "xyz" color forms are not supported
+    if let Some(&(column, text)) = args.first() {
+        if text == "spectral" || text == "xyz" {
+            return Err((column, ParseErrorKind::UnsupportedDirective(text.to_string())));
+        }
+    }
+
+    let r = mesh_loader::parse_float(args.first(), line_len)?;
+
+    // A single value is used for all three channels
+    let (g, b) = if args.len() == 1 {
+        (r, r)
+    } else {
+        (
+            mesh_loader::parse_float(args.get(1), line_len)?,
+            mesh_loader::parse_float(args.get(2), line_len)?,
+        )
+    };
+
+    Ok(Color::rgb_f32(r, g, b))
+}
+
+// Map statements can start with options such as "-bm 0.5", the file name is
+// everything after them, so it can contain spaces
+fn parse_map(
+    args: &[(usize, &str)],
+    line_len: usize,
+    base_dir: &Path,
+) -> Result<PathBuf, TokenError> {
+    let mut rest = args;
+
+    while let Some((&(_, option), after)) = rest.split_first() {
+        if !option.starts_with('-') {
+            break;
+        }
+
+        // -o, -s and -t take one to three numbers, -mm two, the rest one
+        let value_count = match option {
+            "-o" | "-s" | "-t" => after
+                .iter()
+                .take(3)
+                .take_while(|&&(_, text)| f32::from_str(text).is_ok())
+                .count(),
+            "-mm" => 2,
+            _ => 1,
+        };
+
+        rest = &after[value_count.min(after.len())..];
+    }
+
+    if rest.is_empty() {
+        return Err((line_len + 1, ParseErrorKind::MissingValue));
+    }
+
+    let file = rest.iter()
+        .map(|&(_, text)| text)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(base_dir.join(file.replace('\\', "/")))
+}
+
+fn parse_line(
+    line: &str,
+    materials: &mut Vec<MtlMaterial>,
+    base_dir: &Path,
+) -> Result<(), TokenError> {
+    let tokens = mesh_loader::tokenize(line);
+    let line_len = line.len();
+
+    let (directive_column, directive) = match tokens.first() {
+        Some(&first) => first,
+        None => return Ok(()),
+    };
+    let args = &tokens[1..];
+
+    if directive == "newmtl" {
+        let name = args.iter()
+            .map(|&(_, text)| text)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if name.is_empty() {
+            return Err((line_len + 1, ParseErrorKind::MissingValue));
+        }
+
+        materials.push(MtlMaterial::new(&name));
+        return Ok(());
+    }
+
+    let material = match materials.last_mut() {
+        Some(material) => material,
+        None => {
+            return Err((
+                directive_column,
+                ParseErrorKind::UnsupportedDirective(format!("{} before newmtl", directive)),
+            ))
+        }
+    };
+
+    match directive {
+        "Ka" => material.ambient_color = parse_color(args, line_len)?,
+        "Kd" => material.diffuse_color = parse_color(args, line_len)?,
+        "Ks" => material.specular_color = parse_color(args, line_len)?,
+        "Ns" => material.specular_power = mesh_loader::parse_float(args.first(), line_len)?,
+        "d" => {
+            // "-halo" only changes how the dissolve is applied
+            let value = args.iter().find(|&&(_, text)| text != "-halo");
+            material.dissolve = mesh_loader::parse_float(value, line_len)?;
+        }
+        "Tr" => material.dissolve = 1.0 - mesh_loader::parse_float(args.first(), line_len)?,
+        "illum" => {
+            let &(column, text) = args.first()
+                .ok_or((line_len + 1, ParseErrorKind::MissingValue))?;
+
+            material.illumination = u32::from_str(text)
+                .map_err(|_| (column, ParseErrorKind::BadIndex(text.to_string())))?;
+        }
+        "map_Kd" => material.diffuse_map = Some(parse_map(args, line_len, base_dir)?),
+        "map_Ks" => material.specular_map = Some(parse_map(args, line_len, base_dir)?),
+        "map_Bump" | "map_bump" | "bump" => {
+            material.bump_map = Some(parse_map(args, line_len, base_dir)?)
+        }
+        "norm" => material.normal_map = Some(parse_map(args, line_len, base_dir)?),
+        // Everything else (emission, refraction, other maps) has no
+        // counterpart in Material
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// Texture paths are resolved against `base_dir`
+pub fn parse_mtl<B: BufRead>(
+    reader: B,
+    file: &str,
+    base_dir: &Path,
+) -> Result<Vec<MtlMaterial>, MeshLoadError> {
+    let mut materials = Vec::new();
+
+    mesh_loader::for_each_statement(reader, file, |statement| {
+        parse_line(statement.text, &mut materials, base_dir)
+    })?;
+
+    Ok(materials)
+}
+
+pub fn load_file(file_path: &str, base_dir: &Path) -> Result<Vec<MtlMaterial>, MeshLoadError> {
+    let file = File::open(file_path).map_err(|error| MeshLoadError::Io {
+        file: file_path.to_string(),
+        error,
+    })?;
+
+    parse_mtl(BufReader::new(file), file_path, base_dir)
+}
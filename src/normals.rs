@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::f32;
+use std::iter;
+
+use na::Vector3;
+
+// Used when every face around a vertex is degenerate
+fn fallback_normal() -> Vector3<f32> {
+    Vector3::z()
+}
+
+fn tri_points(
+    verts: &[Vector3<f32>],
+    &(first, second, third): &(u32, u32, u32),
+) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+    (
+        verts[first as usize],
+        verts[second as usize],
+        verts[third as usize],
+    )
+}
+
+// Unnormalized, so its length is twice the triangle's area
+pub fn face_normal(a: &Vector3<f32>, b: &Vector3<f32>, c: &Vector3<f32>) -> Vector3<f32> {
+    (b - a).cross(&(c - a))
+}
+
+// Collapsed to a line or point, with an area that's negligible next to its
+// longest edge
+pub(crate) fn is_degenerate(a: &Vector3<f32>, b: &Vector3<f32>, c: &Vector3<f32>) -> bool {
+    let longest_edge = f32::max((b - a).norm(), f32::max((c - b).norm(), (a - c).norm()));
+
+    face_normal(a, b, c).norm() <= 1e-6 * longest_edge * longest_edge
+}
+
+pub(crate) fn corner_angle(corner: &Vector3<f32>, a: &Vector3<f32>, b: &Vector3<f32>) -> f32 {
+    let (to_a, to_b) = (a - corner, b - corner);
+
+    if to_a.norm() <= f32::EPSILON || to_b.norm() <= f32::EPSILON {
+        0.0
+    } else {
+        to_a.angle(&to_b)
+    }
+}
+
+fn finish(normals: Vec<Vector3<f32>>) -> Vec<Vector3<f32>> {
+    normals
+        .iter()
+        .map(|normal| {
+            normal
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(fallback_normal)
+        })
+        .collect()
+}
+
+// Per position normals where larger faces have more influence
+pub fn smooth_area_weighted(verts: &[Vector3<f32>], tris: &[(u32, u32, u32)]) -> Vec<Vector3<f32>> {
+    let mut normals = vec![Vector3::zeros(); verts.len()];
+
+    for tri in tris {
+        let (a, b, c) = tri_points(verts, tri);
+        let normal = face_normal(&a, &b, &c);
+
+        normals[tri.0 as usize] += normal;
+        normals[tri.1 as usize] += normal;
+        normals[tri.2 as usize] += normal;
+    }
+
+    finish(normals)
+}
+
+// Per position normals weighted by the angle each face makes at the vertex,
+// which doesn't depend on how the surface was tessellated
+pub fn smooth_angle_weighted(
+    verts: &[Vector3<f32>],
+    tris: &[(u32, u32, u32)],
+) -> Vec<Vector3<f32>> {
+    let mut normals = vec![Vector3::zeros(); verts.len()];
+
+    for tri in tris {
+        let (a, b, c) = tri_points(verts, tri);
+        let normal = match face_normal(&a, &b, &c).try_normalize(f32::EPSILON) {
+            Some(normal) => normal,
+            None => continue,
+        };
+
+        normals[tri.0 as usize] += normal * corner_angle(&a, &b, &c);
+        normals[tri.1 as usize] += normal * corner_angle(&b, &c, &a);
+        normals[tri.2 as usize] += normal * corner_angle(&c, &a, &b);
+    }
+
+    finish(normals)
+}
+
+// One normal per triangle, returned along with the normal index of every
+// corner in `tris` order
+pub fn flat(verts: &[Vector3<f32>], tris: &[(u32, u32, u32)]) -> (Vec<Vector3<f32>>, Vec<u32>) {
+    let normals = tris.iter()
+        .map(|tri| {
+            let (a, b, c) = tri_points(verts, tri);
+            face_normal(&a, &b, &c)
+        })
+        .collect();
+
+    let corners = (0..tris.len() as u32)
+        .flat_map(|tri| iter::repeat_n(tri, 3))
+        .collect();
+
+    (finish(normals), corners)
+}
+
+// Area weighted normals that are only shared between faces in the same
+// smoothing group whose normals are within `crease_angle` (radians) of each
+// other. Group 0 is flat shaded. Returns the normals and the normal index of
+// every corner in `tris` order
+pub fn smoothing_groups(
+    verts: &[Vector3<f32>],
+    tris: &[(u32, u32, u32)],
+    groups: &[u32],
+    crease_angle: f32,
+) -> (Vec<Vector3<f32>>, Vec<u32>) {
+    let face_normals: Vec<_> = tris.iter()
+        .map(|tri| {
+            let (a, b, c) = tri_points(verts, tri);
+            face_normal(&a, &b, &c)
+        })
+        .collect();
+
+    let unit_normals: Vec<_> = face_normals
+        .iter()
+        .map(|normal| normal.try_normalize(f32::EPSILON))
+        .collect();
+
+    // Triangles touching each position
+    let mut adjacent = vec![Vec::new(); verts.len()];
+
+    for (tri_ind, &(first, second, third)) in tris.iter().enumerate() {
+        adjacent[first as usize].push(tri_ind);
+        adjacent[second as usize].push(tri_ind);
+        adjacent[third as usize].push(tri_ind);
+    }
+
+    let cos_crease = crease_angle.cos();
+
+    let smooths_with = |tri_ind: usize, other: usize| -> bool {
+        if groups[tri_ind] == 0 || groups[tri_ind] != groups[other] {
+            return false;
+        }
+
+        match (unit_normals[tri_ind], unit_normals[other]) {
+            (Some(normal), Some(other_normal)) => normal.dot(&other_normal) >= cos_crease,
+            _ => false,
+        }
+    };
+
+    let mut normals = Vec::new();
+    let mut corners = Vec::with_capacity(tris.len() * 3);
+
+    // Corners of the same position that end up with the same normal share it
+    let mut lookup = HashMap::new();
+
+    for (tri_ind, &(first, second, third)) in tris.iter().enumerate() {
+        for &pos in &[first, second, third] {
+            let normal = adjacent[pos as usize]
+                .iter()
+                .filter(|&&other| other == tri_ind || smooths_with(tri_ind, other))
+                .fold(Vector3::zeros(), |acc, &other| acc + face_normals[other]);
+
+            let normal = normal
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(fallback_normal);
+
+            let key = (pos, normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits());
+
+            let index = *lookup.entry(key).or_insert_with(|| {
+                normals.push(normal);
+                normals.len() as u32 - 1
+            });
+
+            corners.push(index);
+        }
+    }
+
+    (normals, corners)
+}
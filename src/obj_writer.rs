@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use gfx::Resources;
+
+use color::Color;
+use material::Material;
+use mesh::Mesh;
+use mesh_loader::MeshLoadError;
+use model::{Model, Submesh};
+use mtl_loader::MtlMaterial;
+
+fn io_error(file: &str, error: io::Error) -> MeshLoadError {
+    MeshLoadError::Io {
+        file: file.to_string(),
+        error,
+    }
+}
+
+// Index of the value in `values`, shared between vertices with the same bits
+fn attribute_index<T: Copy>(
+    value: T,
+    key: Vec<u32>,
+    values: &mut Vec<T>,
+    lookup: &mut HashMap<Vec<u32>, usize>,
+) -> usize {
+    *lookup.entry(key).or_insert_with(|| {
+        values.push(value);
+        values.len() - 1
+    })
+}
+
+// Positions, UVs and normals are written once each and shared between faces.
+// Floats are written with enough digits to read back exactly
+fn write_obj<W: Write>(
+    writer: &mut W,
+    mesh: &Mesh,
+    submeshes: &[Submesh],
+    mtllib: Option<&str>,
+) -> io::Result<()> {
+    let (mut positions, mut uvs, mut normals) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut pos_lookup, mut uv_lookup, mut normal_lookup) =
+        (HashMap::new(), HashMap::new(), HashMap::new());
+
+    // OBJ index triple of every vertex
+    let corners: Vec<_> = mesh.vertices()
+        .iter()
+        .map(|vert| {
+            let bits = |values: &[f32]| values.iter().map(|value| value.to_bits()).collect();
+
+            (
+                attribute_index(vert.pos, bits(&vert.pos), &mut positions, &mut pos_lookup) + 1,
+                attribute_index(vert.uv, bits(&vert.uv), &mut uvs, &mut uv_lookup) + 1,
+                attribute_index(vert.normal, bits(&vert.normal), &mut normals, &mut normal_lookup)
+                    + 1,
+            )
+        })
+        .collect();
+
+    if let Some(mtllib) = mtllib {
+        writeln!(writer, "mtllib {}", mtllib)?;
+    }
+
+    for pos in &positions {
+        writeln!(writer, "v {} {} {}", pos[0], pos[1], pos[2])?;
+    }
+
+    for uv in &uvs {
+        writeln!(writer, "vt {} {}", uv[0], uv[1])?;
+    }
+
+    for normal in &normals {
+        writeln!(writer, "vn {} {} {}", normal[0], normal[1], normal[2])?;
+    }
+
+    let indices = mesh.indices();
+
+    if submeshes.is_empty() {
+        return write_faces(writer, indices, &corners);
+    }
+
+    for submesh in submeshes {
+        let (start, end) = (submesh.start as usize, (submesh.start + submesh.count) as usize);
+
+        writeln!(writer, "g {}", submesh.name)?;
+
+        if let Some(ref material) = submesh.material {
+            writeln!(writer, "usemtl {}", material)?;
+        }
+
+        write_faces(writer, &indices[start..end], &corners)?;
+    }
+
+    Ok(())
+}
+
+fn write_faces<W: Write>(
+    writer: &mut W,
+    indices: &[u32],
+    corners: &[(usize, usize, usize)],
+) -> io::Result<()> {
+    for tri in indices.chunks(3) {
+        write!(writer, "f")?;
+
+        for &index in tri {
+            let (pos, uv, normal) = corners[index as usize];
+            write!(writer, " {}/{}/{}", pos, uv, normal)?;
+        }
+
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+pub fn write_mesh<W: Write>(writer: &mut W, mesh: &Mesh) -> io::Result<()> {
+    write_obj(writer, mesh, &[], None)
+}
+
+// Writes a group per submesh, with usemtl lines for the ones with a material
+pub fn write_model<W: Write>(
+    writer: &mut W,
+    model: &Model,
+    mtllib: Option<&str>,
+) -> io::Result<()> {
+    write_obj(writer, &model.mesh, &model.submeshes, mtllib)
+}
+
+fn write_color<W: Write>(writer: &mut W, directive: &str, color: Color) -> io::Result<()> {
+    let [r, g, b, _]: [f32; 4] = color.into();
+
+    writeln!(writer, "{} {} {} {}", directive, r, g, b)
+}
+
+// Textures only exist on the GPU once a material is built, so textured
+// materials are written without their maps. Use write_mtl_materials to keep
+// the texture paths
+pub fn write_mtl<W: Write, R: Resources>(
+    writer: &mut W,
+    materials: &[(String, Material<R>)],
+) -> io::Result<()> {
+    for (index, (name, material)) in materials.iter().enumerate() {
+        if index > 0 {
+            writeln!(writer)?;
+        }
+
+        writeln!(writer, "newmtl {}", name)?;
+
+        let (diffuse_color, ambient_color, specular_color, specular_power) = match *material {
+            Material::Untextured {
+                diffuse_color,
+                ambient_color,
+                specular_color,
+                specular_power,
+            }
+            | Material::Textured {
+                diffuse_color,
+                ambient_color,
+                specular_color,
+                specular_power,
+                ..
+            } => (diffuse_color, ambient_color, specular_color, specular_power),
+        };
+
+        if let Material::Textured { .. } = *material {
+            writeln!(writer, "# textures are not exported")?;
+        }
+
+        write_color(writer, "Ka", ambient_color)?;
+        write_color(writer, "Kd", diffuse_color)?;
+        write_color(writer, "Ks", specular_color)?;
+        writeln!(writer, "Ns {}", specular_power)?;
+
+        let [.., alpha]: [f32; 4] = diffuse_color.into();
+        writeln!(writer, "d {}", alpha)?;
+        writeln!(writer, "illum 2")?;
+    }
+
+    Ok(())
+}
+
+// Map paths are written as they are, so they have to be absolute or relative
+// to where the library is saved
+pub fn write_mtl_materials<W: Write>(writer: &mut W, materials: &[MtlMaterial]) -> io::Result<()> {
+    for (index, material) in materials.iter().enumerate() {
+        if index > 0 {
+            writeln!(writer)?;
+        }
+
+        writeln!(writer, "newmtl {}", material.name)?;
+        write_color(writer, "Ka", material.ambient_color)?;
+        write_color(writer, "Kd", material.diffuse_color)?;
+        write_color(writer, "Ks", material.specular_color)?;
+        writeln!(writer, "Ns {}", material.specular_power)?;
+        writeln!(writer, "d {}", material.dissolve)?;
+        writeln!(writer, "illum {}", material.illumination)?;
+
+        let maps = [
+            ("map_Kd", &material.diffuse_map),
+            ("map_Ks", &material.specular_map),
+            ("map_Bump", &material.bump_map),
+            ("norm", &material.normal_map),
+        ];
+
+        for &(directive, map) in &maps {
+            if let Some(ref path) = *map {
+                writeln!(writer, "{} {}", directive, path.display())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn create_file(file_path: &str) -> Result<BufWriter<File>, MeshLoadError> {
+    File::create(file_path)
+        .map(BufWriter::new)
+        .map_err(|error| io_error(file_path, error))
+}
+
+pub fn save_file(file_path: &str, mesh: &Mesh) -> Result<(), MeshLoadError> {
+    let mut writer = create_file(file_path)?;
+
+    write_mesh(&mut writer, mesh)
+        .and_then(|_| writer.flush())
+        .map_err(|error| io_error(file_path, error))
+}
+
+// Writes a library with the same name as the OBJ file but an .mtl extension
+// next to it, returning the name to use with mtllib
+fn save_library<R: Resources>(
+    file_path: &str,
+    materials: &[(String, Material<R>)],
+) -> Result<String, MeshLoadError> {
+    let mtl_path = Path::new(file_path).with_extension("mtl");
+    let mtl_file = mtl_path.to_string_lossy().into_owned();
+
+    let mut writer = create_file(&mtl_file)?;
+
+    write_mtl(&mut writer, materials)
+        .and_then(|_| writer.flush())
+        .map_err(|error| io_error(&mtl_file, error))?;
+
+    Ok(mtl_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or(mtl_file))
+}
+
+// The material library is only written when there are materials
+pub fn save_model<R: Resources>(
+    file_path: &str,
+    model: &Model,
+    materials: &[(String, Material<R>)],
+) -> Result<(), MeshLoadError> {
+    let mtllib = if materials.is_empty() {
+        None
+    } else {
+        Some(save_library(file_path, materials)?)
+    };
+
+    let mut writer = create_file(file_path)?;
+
+    write_model(&mut writer, model, mtllib.as_deref())
+        .and_then(|_| writer.flush())
+        .map_err(|error| io_error(file_path, error))
+}
+
+// Writes the mesh as a single group using `material`
+pub fn save_file_with_material<R: Resources>(
+    file_path: &str,
+    mesh: &Mesh,
+    material_name: &str,
+    material: &Material<R>,
+) -> Result<(), MeshLoadError> {
+    let mtllib = save_library(file_path, &[(material_name.to_string(), material.clone())])?;
+
+    let submesh = Submesh {
+        name: String::from("default"),
+        material: Some(material_name.to_string()),
+        start: 0,
+        count: mesh.indices().len() as u32,
+    };
+
+    let mut writer = create_file(file_path)?;
+
+    write_obj(&mut writer, mesh, &[submesh], Some(&mtllib))
+        .and_then(|_| writer.flush())
+        .map_err(|error| io_error(file_path, error))
+}
@@ -1,17 +1,22 @@
 
 use gfx;
-use gfx::{CommandBuffer, Resources};
+use gfx::{CommandBuffer, Resources, Slice};
 
-use na::{Matrix4, Point3, Vector3};
+use na::{Matrix4, Point3, UnitQuaternion, Vector3};
 
 use program::{pipe, Transform};
+use frame::FrameResources;
+use lod::LodChainData;
 use mesh::MeshData;
+use model::ModelData;
 use material::Material;
 
+// Transform relative to the parent node in a Scene, or to the world for
+// objects without one
 pub struct Object<R: Resources> {
-    pub position: Point3<f32>,  // Position
-    pub scale: Vector3<f32>,    // Scale amount
-    pub rotation: Vector3<f32>, // Euler angles
+    pub position: Point3<f32>,            // Position
+    pub scale: Vector3<f32>,              // Scale amount
+    pub rotation: UnitQuaternion<f32>,    // Orientation
     pub material: Material<R>,
 }
 
@@ -20,68 +25,146 @@ impl<R: Resources> Object<R> {
         mat: Material<R>,
         pos: Point3<f32>,
         size: Vector3<f32>,
-        rotation: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
     ) -> Self {
         Object {
             position: pos,
             scale: size,
-            rotation: rotation,
+            rotation,
             material: mat,
         }
     }
 
+    // Roll, pitch and yaw in radians about x, y and z
+    pub fn set_euler_angles(&mut self, roll: f32, pitch: f32, yaw: f32) {
+        self.rotation = UnitQuaternion::from_euler_angles(roll, pitch, yaw);
+    }
+
+    // Applies `rotation` on top of the current one, in the parent's space
+    pub fn rotate(&mut self, rotation: &UnitQuaternion<f32>) {
+        self.rotation = rotation * self.rotation;
+    }
+
+    // Local matrix, Scene::world_matrix includes the parents
     pub fn build_matrix(&self) -> Matrix4<f32> {
         Matrix4::new_translation(&(self.position - Point3::new(0.0, 0.0, 0.0)))
-            * Matrix4::from_euler_angles(self.rotation.x, self.rotation.y, self.rotation.z)
+            * self.rotation.to_homogeneous()
             * Matrix4::new_nonuniform_scaling(&self.scale)
     }
 }
 
-pub fn create_transform<R: Resources>(
-    obj: &Object<R>,
+// Inverse-transpose of the model-view's upper 3x3, which keeps normals
+// perpendicular to the surface under non-uniform scaling. Falls back to the
+// model-view itself when it can't be inverted, e.g. a zero scale
+pub fn normal_matrix(model_view: &Matrix4<f32>) -> Matrix4<f32> {
+    let mut linear = *model_view;
+    linear[(0, 3)] = 0.0;
+    linear[(1, 3)] = 0.0;
+    linear[(2, 3)] = 0.0;
+
+    linear
+        .try_inverse()
+        .map(|inverse| inverse.transpose())
+        .unwrap_or(linear)
+}
+
+// `model` is the object's world matrix
+pub fn create_transform(
+    model: &Matrix4<f32>,
     view: Matrix4<f32>,
     projection: Matrix4<f32>,
 ) -> Transform {
     Transform {
-        model: obj.build_matrix().into(),
+        model: (*model).into(),
         view: view.into(),
         projection: projection.into(),
+        normal: normal_matrix(&(view * model)).into(),
     }
 }
 
-pub fn draw<R: Resources, C: CommandBuffer<R>>(
+// Uploads the material and issues the draw for one slice of the mesh data
+fn draw_slice<R: Resources, C: CommandBuffer<R>>(
     encoder: &mut gfx::Encoder<R, C>,
-    mesh_data: &mut MeshData<R>,
+    frame: &FrameResources<R>,
+    mesh_data: &MeshData<R>,
+    slice: &Slice<R>,
+    program: &gfx::pso::PipelineState<R, pipe::Meta>,
+    material: &Material<R>,
+) {
+    let data = frame.bind(encoder, mesh_data.vertex_buffer(), material);
+
+    // draw commands with buffer data and attached pso
+    encoder.draw(slice, program, &data);
+}
+
+// Draws with the frame's camera, lights and render targets
+pub(crate) fn draw<R: Resources, C: CommandBuffer<R>>(
+    encoder: &mut gfx::Encoder<R, C>,
+    frame: &FrameResources<R>,
+    mesh_data: &MeshData<R>,
     program: &gfx::pso::PipelineState<R, pipe::Meta>,
     obj: &Object<R>,
-    view: &Matrix4<f32>,
-    projection: &Matrix4<f32>,
+    world: &Matrix4<f32>,
 ) {
-    let trans_data = create_transform(obj, *view, *projection);
-
-    encoder
-        .update_buffer(&mesh_data.data_ref_mut().transform, &[trans_data], 0)
-        .unwrap(); //update buffers
-
-    // Set texture
-    if let Material::Textured {
-        ref diffuse_texture,
-        ref specular_texture,
-        ..
-    } = obj.material
-    {
-        mesh_data.update_diffuse_texture(diffuse_texture.clone());
-        mesh_data.update_specular_texture(specular_texture.clone());
-    }
+    frame.update_transform(
+        encoder,
+        create_transform(world, *frame.view(), *frame.projection()),
+    );
 
-    encoder
-        .update_buffer(
-            &mesh_data.data_ref_mut().material,
-            &[obj.material.clone().into()],
-            0,
-        )
-        .unwrap(); //update buffers
+    draw_slice(
+        encoder,
+        frame,
+        mesh_data,
+        mesh_data.slice_ref(),
+        program,
+        &obj.material,
+    );
+}
 
-    // draw commands with buffer data and attached pso
-    encoder.draw(mesh_data.slice_ref(), program, mesh_data.data_ref());
+// Draws the level of detail that fits how large the object appears on screen
+pub(crate) fn draw_lod<R: Resources, C: CommandBuffer<R>>(
+    encoder: &mut gfx::Encoder<R, C>,
+    frame: &FrameResources<R>,
+    lod_data: &LodChainData<R>,
+    program: &gfx::pso::PipelineState<R, pipe::Meta>,
+    obj: &Object<R>,
+    world: &Matrix4<f32>,
+) {
+    let screen_size = lod_data.screen_size(world, frame.view(), frame.projection());
+
+    draw(
+        encoder,
+        frame,
+        lod_data.select(screen_size),
+        program,
+        obj,
+        world,
+    );
+}
+
+// Draws every part of the model, parts without a material of their own use
+// the object's
+pub(crate) fn draw_model<R: Resources, C: CommandBuffer<R>>(
+    encoder: &mut gfx::Encoder<R, C>,
+    frame: &FrameResources<R>,
+    model_data: &ModelData<R>,
+    program: &gfx::pso::PipelineState<R, pipe::Meta>,
+    obj: &Object<R>,
+    world: &Matrix4<f32>,
+) {
+    frame.update_transform(
+        encoder,
+        create_transform(world, *frame.view(), *frame.projection()),
+    );
+
+    for part in model_data.parts() {
+        draw_slice(
+            encoder,
+            frame,
+            model_data.mesh_data_ref(),
+            &part.slice,
+            program,
+            part.material.as_ref().unwrap_or(&obj.material),
+        );
+    }
 }
@@ -0,0 +1,587 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::str::{FromStr, SplitWhitespace};
+
+use na::Vector3;
+
+use color::Color;
+use mesh::Mesh;
+use mesh_loader::MeshLoadError;
+use normals;
+use program::Vertex;
+use triangulation;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        self == Scalar::F32 || self == Scalar::F64
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    // Name, type of the length and type of the items
+    List(String, Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// Values of the body, read one at a time in header order
+trait ValueSource {
+    fn next(&mut self, ty: Scalar) -> Result<f64, String>;
+}
+
+struct AsciiSource<'a> {
+    tokens: SplitWhitespace<'a>,
+}
+
+impl<'a> ValueSource for AsciiSource<'a> {
+    fn next(&mut self, _: Scalar) -> Result<f64, String> {
+        let token = self.tokens
+            .next()
+            .ok_or_else(|| String::from("unexpected end of file"))?;
+
+        f64::from_str(token).map_err(|_| format!("bad value \"{}\"", token))
+    }
+}
+
+struct BinarySource<'a> {
+    data: &'a [u8],
+    offset: usize,
+    big_endian: bool,
+}
+
+impl<'a> ValueSource for BinarySource<'a> {
+    fn next(&mut self, ty: Scalar) -> Result<f64, String> {
+        let size = ty.size();
+
+        if self.offset + size > self.data.len() {
+            return Err(String::from("unexpected end of file"));
+        }
+
+        let mut bytes = [0u8; 8];
+        bytes[..size].copy_from_slice(&self.data[self.offset..self.offset + size]);
+        self.offset += size;
+
+        if self.big_endian {
+            bytes[..size].reverse();
+        }
+
+        Ok(match ty {
+            Scalar::I8 => f64::from(bytes[0] as i8),
+            Scalar::U8 => f64::from(bytes[0]),
+            Scalar::I16 => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+            Scalar::U16 => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+            Scalar::I32 => f64::from(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            Scalar::U32 => f64::from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            Scalar::F32 => f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            Scalar::F64 => f64::from_le_bytes(bytes),
+        })
+    }
+}
+
+fn format_error(file: &str, error: String) -> MeshLoadError {
+    MeshLoadError::Format {
+        file: file.to_string(),
+        error,
+    }
+}
+
+fn io_error(file: &str, error: io::Error) -> MeshLoadError {
+    MeshLoadError::Io {
+        file: file.to_string(),
+        error,
+    }
+}
+
+fn parse_scalar(name: Option<&str>) -> Result<Scalar, String> {
+    let name = name.ok_or_else(|| String::from("missing property type"))?;
+
+    Scalar::parse(name).ok_or_else(|| format!("unknown property type \"{}\"", name))
+}
+
+fn parse_header<B: BufRead>(reader: &mut B) -> Result<(PlyFormat, Vec<Element>), String> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut line = String::new();
+    let mut line_num = 0;
+
+    loop {
+        line.clear();
+        line_num += 1;
+
+        if reader.read_line(&mut line).map_err(|error| error.to_string())? == 0 {
+            return Err(String::from("missing end_header"));
+        }
+
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap_or("");
+
+        if line_num == 1 {
+            if keyword != "ply" {
+                return Err(String::from("not a PLY file"));
+            }
+
+            continue;
+        }
+
+        let context = |error: String| format!("header line {}: {}", line_num, error);
+
+        match keyword {
+            "format" => {
+                format = Some(match tokens.next() {
+                    Some("ascii") => PlyFormat::Ascii,
+                    Some("binary_little_endian") => PlyFormat::BinaryLittleEndian,
+                    Some("binary_big_endian") => PlyFormat::BinaryBigEndian,
+                    _ => return Err(context(String::from("unknown format"))),
+                });
+            }
+            "element" => {
+                let name = tokens.next().unwrap_or("").to_string();
+                let count = tokens
+                    .next()
+                    .and_then(|count| usize::from_str(count).ok())
+                    .ok_or_else(|| context(String::from("bad element count")))?;
+
+                elements.push(Element {
+                    name,
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            "property" => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| context(String::from("property before element")))?;
+
+                let ty = tokens.next();
+
+                let property = if ty == Some("list") {
+                    let count_ty = parse_scalar(tokens.next()).map_err(&context)?;
+                    let item_ty = parse_scalar(tokens.next()).map_err(&context)?;
+
+                    Property::List(tokens.next().unwrap_or("").to_string(), count_ty, item_ty)
+                } else {
+                    let ty = parse_scalar(ty).map_err(&context)?;
+
+                    Property::Scalar(tokens.next().unwrap_or("").to_string(), ty)
+                };
+
+                element.properties.push(property);
+            }
+            "end_header" => break,
+            "comment" | "obj_info" | "" => {}
+            _ => return Err(context(format!("unknown keyword \"{}\"", keyword))),
+        }
+    }
+
+    let format = format.ok_or_else(|| String::from("missing format line"))?;
+
+    Ok((format, elements))
+}
+
+#[derive(Default)]
+struct PlyData {
+    positions: Vec<Vector3<f32>>,
+    normals: Vec<Vector3<f32>>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<Color>,
+    faces: Vec<Vec<u32>>,
+    has_normals: bool,
+}
+
+fn color_channel(value: f64, ty: Scalar) -> u8 {
+    if ty.is_float() {
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    } else {
+        value.clamp(0.0, 255.0) as u8
+    }
+}
+
+fn read_vertex<S: ValueSource>(
+    source: &mut S,
+    element: &Element,
+    data: &mut PlyData,
+) -> Result<(), String> {
+    let mut pos = Vector3::zeros();
+    let mut normal = Vector3::zeros();
+    let mut uv = [0.0, 0.0];
+    let mut color = Color::white();
+
+    for property in &element.properties {
+        let (name, ty) = match *property {
+            Property::Scalar(ref name, ty) => (name.as_str(), ty),
+            Property::List(_, count_ty, item_ty) => {
+                let count = source.next(count_ty)? as usize;
+
+                for _ in 0..count {
+                    source.next(item_ty)?;
+                }
+
+                continue;
+            }
+        };
+
+        let value = source.next(ty)?;
+
+        match name {
+            "x" => pos.x = value as f32,
+            "y" => pos.y = value as f32,
+            "z" => pos.z = value as f32,
+            "nx" => normal.x = value as f32,
+            "ny" => normal.y = value as f32,
+            "nz" => normal.z = value as f32,
+            "u" | "s" | "texture_u" | "texture_s" => uv[0] = value as f32,
+            "v" | "t" | "texture_v" | "texture_t" => uv[1] = value as f32,
+            "red" | "diffuse_red" => color.r = color_channel(value, ty),
+            "green" | "diffuse_green" => color.g = color_channel(value, ty),
+            "blue" | "diffuse_blue" => color.b = color_channel(value, ty),
+            "alpha" => color.a = color_channel(value, ty),
+            _ => {}
+        }
+    }
+
+    data.positions.push(pos);
+    data.normals.push(normal);
+    data.uvs.push(uv);
+    data.colors.push(color);
+
+    Ok(())
+}
+
+fn read_face<S: ValueSource>(
+    source: &mut S,
+    element: &Element,
+    data: &mut PlyData,
+) -> Result<(), String> {
+    let mut face = Vec::new();
+
+    for property in &element.properties {
+        match *property {
+            Property::Scalar(_, ty) => {
+                source.next(ty)?;
+            }
+            Property::List(ref name, count_ty, item_ty) => {
+                let count = source.next(count_ty)? as usize;
+                let is_indices = name == "vertex_indices" || name == "vertex_index";
+
+                for _ in 0..count {
+                    let value = source.next(item_ty)?;
+
+                    if is_indices {
+                        // Casting would turn negative indices into 0
+                        if value < 0.0 || value > f64::from(u32::MAX) {
+                            return Err(format!("vertex index {} out of range", value));
+                        }
+
+                        face.push(value as u32);
+                    }
+                }
+            }
+        }
+    }
+
+    data.faces.push(face);
+
+    Ok(())
+}
+
+fn skip_element<S: ValueSource>(source: &mut S, element: &Element) -> Result<(), String> {
+    for property in &element.properties {
+        match *property {
+            Property::Scalar(_, ty) => {
+                source.next(ty)?;
+            }
+            Property::List(_, count_ty, item_ty) => {
+                let count = source.next(count_ty)? as usize;
+
+                for _ in 0..count {
+                    source.next(item_ty)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_body<S: ValueSource>(source: &mut S, elements: &[Element]) -> Result<PlyData, String> {
+    let mut data = PlyData::default();
+
+    for element in elements {
+        if element.name == "vertex" {
+            data.has_normals = element.properties.iter().any(|property| match *property {
+                Property::Scalar(ref name, _) => name == "nx",
+                _ => false,
+            });
+        }
+
+        for _ in 0..element.count {
+            match element.name.as_str() {
+                "vertex" => read_vertex(source, element, &mut data)?,
+                "face" => read_face(source, element, &mut data)?,
+                _ => skip_element(source, element)?,
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+fn build_mesh(data: PlyData) -> Result<(Mesh, Vec<Color>), String> {
+    let mut tris = Vec::new();
+
+    for face in &data.faces {
+        if let Some(&index) = face.iter().find(|&&index| index as usize >= data.positions.len()) {
+            return Err(format!("vertex index {} out of range", index));
+        }
+
+        let points: Vec<_> = face.iter().map(|&index| data.positions[index as usize]).collect();
+
+        for (first, second, third) in triangulation::triangulate_polygon(&points) {
+            tris.push((face[first], face[second], face[third]));
+        }
+    }
+
+    let normals = if data.has_normals {
+        data.normals.clone()
+    } else {
+        normals::smooth_area_weighted(&data.positions, &tris)
+    };
+
+    let mut mesh = Mesh::new();
+
+    for (i, pos) in data.positions.iter().enumerate() {
+        mesh.add_vertex(&Vertex {
+            pos: (*pos).into(),
+            normal: normals[i].into(),
+            uv: data.uvs[i],
+            tangent: [0.0; 4],
+        });
+    }
+
+    mesh.add_tris(&tris);
+
+    // Vertices split for tangents keep the color they were copied from
+    let mut colors = data.colors;
+    for source in mesh.generate_tangents_with_copies() {
+        let color = colors[source as usize];
+        colors.push(color);
+    }
+
+    Ok((mesh, colors))
+}
+
+// Returns the mesh along with its vertex colors, which are white when the file
+// has none. Missing normals are generated, missing UVs are zero
+pub fn parse_ply<B: BufRead>(
+    mut reader: B,
+    file: &str,
+) -> Result<(Mesh, Vec<Color>), MeshLoadError> {
+    let (format, elements) = parse_header(&mut reader).map_err(|error| format_error(file, error))?;
+
+    let data = if format == PlyFormat::Ascii {
+        let mut body = String::new();
+        reader
+            .read_to_string(&mut body)
+            .map_err(|error| io_error(file, error))?;
+
+        read_body(
+            &mut AsciiSource {
+                tokens: body.split_whitespace(),
+            },
+            &elements,
+        )
+    } else {
+        let mut body = Vec::new();
+        reader
+            .read_to_end(&mut body)
+            .map_err(|error| io_error(file, error))?;
+
+        read_body(
+            &mut BinarySource {
+                data: &body,
+                offset: 0,
+                big_endian: format == PlyFormat::BinaryBigEndian,
+            },
+            &elements,
+        )
+    }.map_err(|error| format_error(file, error))?;
+
+    build_mesh(data).map_err(|error| format_error(file, error))
+}
+
+pub fn load_file_with_colors(file_path: &str) -> Result<(Mesh, Vec<Color>), MeshLoadError> {
+    let file = File::open(file_path).map_err(|error| io_error(file_path, error))?;
+
+    parse_ply(BufReader::new(file), file_path)
+}
+
+pub fn load_file(file_path: &str) -> Result<Mesh, MeshLoadError> {
+    load_file_with_colors(file_path).map(|(mesh, _)| mesh)
+}
+
+fn write_binary<W: Write>(writer: &mut W, bytes: [u8; 4], big_endian: bool) -> io::Result<()> {
+    if big_endian {
+        writer.write_all(&[bytes[3], bytes[2], bytes[1], bytes[0]])
+    } else {
+        writer.write_all(&bytes)
+    }
+}
+
+// Writes positions, normals and UVs, plus one color per vertex if given
+pub fn write_ply<W: Write>(
+    writer: &mut W,
+    mesh: &Mesh,
+    colors: Option<&[Color]>,
+    format: PlyFormat,
+) -> io::Result<()> {
+    let verts = mesh.vertices();
+    let indices = mesh.indices();
+
+    if let Some(colors) = colors {
+        if colors.len() != verts.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "expected one color per vertex",
+            ));
+        }
+    }
+
+    writeln!(writer, "ply")?;
+    writeln!(
+        writer,
+        "format {} 1.0",
+        match format {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        }
+    )?;
+    writeln!(writer, "element vertex {}", verts.len())?;
+
+    for name in &["x", "y", "z", "nx", "ny", "nz", "u", "v"] {
+        writeln!(writer, "property float {}", name)?;
+    }
+
+    if colors.is_some() {
+        for name in &["red", "green", "blue", "alpha"] {
+            writeln!(writer, "property uchar {}", name)?;
+        }
+    }
+
+    writeln!(writer, "element face {}", indices.len() / 3)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    let vert_colors = |i: usize| colors.map(|colors| colors[i]);
+
+    if format == PlyFormat::Ascii {
+        for (i, vert) in verts.iter().enumerate() {
+            let mut values: Vec<String> = vert.pos
+                .iter()
+                .chain(vert.normal.iter())
+                .chain(vert.uv.iter())
+                .map(|value| value.to_string())
+                .collect();
+
+            if let Some(color) = vert_colors(i) {
+                values.extend([color.r, color.g, color.b, color.a].iter().map(u8::to_string));
+            }
+
+            writeln!(writer, "{}", values.join(" "))?;
+        }
+
+        for tri in indices.chunks(3) {
+            writeln!(writer, "3 {} {} {}", tri[0], tri[1], tri[2])?;
+        }
+
+        return Ok(());
+    }
+
+    let big_endian = format == PlyFormat::BinaryBigEndian;
+
+    for (i, vert) in verts.iter().enumerate() {
+        let values = vert.pos
+            .iter()
+            .chain(vert.normal.iter())
+            .chain(vert.uv.iter());
+
+        for value in values {
+            write_binary(writer, value.to_le_bytes(), big_endian)?;
+        }
+
+        if let Some(color) = vert_colors(i) {
+            writer.write_all(&[color.r, color.g, color.b, color.a])?;
+        }
+    }
+
+    for tri in indices.chunks(3) {
+        writer.write_all(&[3])?;
+
+        for &index in tri {
+            write_binary(writer, index.to_le_bytes(), big_endian)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn save_file(
+    file_path: &str,
+    mesh: &Mesh,
+    colors: Option<&[Color]>,
+    format: PlyFormat,
+) -> Result<(), MeshLoadError> {
+    let file = File::create(file_path).map_err(|error| io_error(file_path, error))?;
+    let mut writer = BufWriter::new(file);
+
+    write_ply(&mut writer, mesh, colors, format)
+        .and_then(|_| writer.flush())
+        .map_err(|error| io_error(file_path, error))
+}
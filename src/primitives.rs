@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::f32;
+use std::f32::consts::PI;
+
+use na::{Vector2, Vector3};
+
+use mesh::Mesh;
+use normals;
+use program::Vertex;
+use uv_mapping;
+
+// All shapes are centered on the origin with Y up and come with tangents.
+// Front faces wind counter-clockwise, and the round ones get a seam of
+// duplicated vertices where u wraps from 1 back to 0. Counts too low to close
+// a shape are raised to the smallest that does
+
+// Fewest segments around a round shape
+const MIN_SEGMENTS: u32 = 3;
+
+fn vertex(pos: Vector3<f32>, normal: Vector3<f32>, uv: (f32, f32)) -> Vertex {
+    Vertex {
+        pos: pos.into(),
+        normal: normal.into(),
+        uv: [uv.0, uv.1],
+        tangent: [0.0; 4],
+    }
+}
+
+// Winds the triangle to face the same way as its vertex normals, triangles
+// collapsed to a line or point (like those touching a pole) are skipped
+fn add_facing_tri(mesh: &mut Mesh, (first, second, third): (u32, u32, u32)) {
+    let (face, normal_sum) = {
+        let verts = mesh.vertices();
+        let pos = |index: u32| Vector3::from(verts[index as usize].pos);
+        let normal = |index: u32| Vector3::from(verts[index as usize].normal);
+
+        let (a, b, c) = (pos(first), pos(second), pos(third));
+
+        if normals::is_degenerate(&a, &b, &c) {
+            return;
+        }
+
+        (
+            normals::face_normal(&a, &b, &c),
+            normal(first) + normal(second) + normal(third),
+        )
+    };
+
+    if face.dot(&normal_sum) < 0.0 {
+        mesh.add_tri((first, third, second));
+    } else {
+        mesh.add_tri((first, second, third));
+    }
+}
+
+// Samples `surface` on a (columns + 1) x (rows + 1) grid of u and v in [0, 1]
+// and joins neighbouring samples with two triangles
+fn add_surface<F>(mesh: &mut Mesh, columns: u32, rows: u32, surface: F)
+where
+    F: Fn(f32, f32) -> Vertex,
+{
+    let base = mesh.vertices().len() as u32;
+
+    for row in 0..rows + 1 {
+        for column in 0..columns + 1 {
+            mesh.add_vertex(&surface(
+                column as f32 / columns as f32,
+                row as f32 / rows as f32,
+            ));
+        }
+    }
+
+    let index = |column: u32, row: u32| base + row * (columns + 1) + column;
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let (a, b) = (index(column, row), index(column + 1, row));
+            let (c, d) = (index(column + 1, row + 1), index(column, row + 1));
+
+            add_facing_tri(mesh, (a, b, c));
+            add_facing_tri(mesh, (a, c, d));
+        }
+    }
+}
+
+// Flat disc facing up or down, with UVs projected from above
+fn add_disc(mesh: &mut Mesh, y: f32, radius: f32, segments: u32, facing_up: bool) {
+    let normal = if facing_up {
+        Vector3::y()
+    } else {
+        -Vector3::y()
+    };
+
+    add_surface(mesh, segments, 1, |u, v| {
+        let (sin, cos) = (u * 2.0 * PI).sin_cos();
+        let (x, z) = (v * sin, v * cos);
+
+        vertex(
+            Vector3::new(x * radius, y, z * radius),
+            normal,
+            (0.5 + 0.5 * x, 0.5 - 0.5 * z),
+        )
+    });
+}
+
+// Point on the unit sphere, latitude runs from -pi/2 at the bottom to pi/2
+fn sphere_point(longitude: f32, latitude: f32) -> Vector3<f32> {
+    let (sin_lon, cos_lon) = longitude.sin_cos();
+    let (sin_lat, cos_lat) = latitude.sin_cos();
+
+    Vector3::new(cos_lat * sin_lon, sin_lat, cos_lat * cos_lon)
+}
+
+impl Mesh {
+    // Each face is textured with the whole 0 to 1 UV range
+    pub fn cube(size: f32) -> Self {
+        let mut mesh = Mesh::new();
+        let half = size / 2.0;
+
+        // Normal and the directions u and v increase in, u x v = normal so
+        // textures aren't mirrored
+        let faces = [
+            (Vector3::x(), -Vector3::z(), Vector3::y()),
+            (-Vector3::x(), Vector3::z(), Vector3::y()),
+            (Vector3::y(), Vector3::x(), -Vector3::z()),
+            (-Vector3::y(), Vector3::x(), Vector3::z()),
+            (Vector3::z(), Vector3::x(), Vector3::y()),
+            (-Vector3::z(), -Vector3::x(), Vector3::y()),
+        ];
+
+        for &(normal, u_dir, v_dir) in &faces {
+            add_surface(&mut mesh, 1, 1, |u, v| {
+                let pos = (normal + u_dir * (2.0 * u - 1.0) + v_dir * (2.0 * v - 1.0)) * half;
+
+                vertex(pos, normal, (u, v))
+            });
+        }
+
+        mesh.generate_tangents();
+        mesh
+    }
+
+    // In the XZ plane facing up, split into (subdivisions + 1)^2 quads
+    pub fn plane(width: f32, depth: f32, subdivisions: u32) -> Self {
+        let mut mesh = Mesh::new();
+        let segments = subdivisions + 1;
+
+        add_surface(&mut mesh, segments, segments, |u, v| {
+            let pos = Vector3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth);
+
+            vertex(pos, Vector3::y(), (u, v))
+        });
+
+        mesh.generate_tangents();
+        mesh
+    }
+
+    // `segments` around the Y axis and `rings` from pole to pole
+    pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Self {
+        let mut mesh = Mesh::new();
+        let (segments, rings) = (segments.max(MIN_SEGMENTS), rings.max(2));
+
+        add_surface(&mut mesh, segments, rings, |u, v| {
+            let normal = sphere_point(u * 2.0 * PI, (v - 0.5) * PI);
+
+            vertex(normal * radius, normal, (u, v))
+        });
+
+        mesh.generate_tangents();
+        mesh
+    }
+
+    // Subdivided icosahedron, which spreads vertices more evenly than a UV
+    // sphere. UVs use uv_mapping::spherical
+    pub fn icosphere(radius: f32, subdivisions: u32) -> Self {
+        let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+
+        let mut points: Vec<Vector3<f32>> = [
+            (-1.0, t, 0.0),
+            (1.0, t, 0.0),
+            (-1.0, -t, 0.0),
+            (1.0, -t, 0.0),
+            (0.0, -1.0, t),
+            (0.0, 1.0, t),
+            (0.0, -1.0, -t),
+            (0.0, 1.0, -t),
+            (t, 0.0, -1.0),
+            (t, 0.0, 1.0),
+            (-t, 0.0, -1.0),
+            (-t, 0.0, 1.0),
+        ].iter()
+            .map(|&(x, y, z)| Vector3::new(x, y, z).normalize())
+            .collect();
+
+        let mut tris = vec![
+            (0, 11, 5),
+            (0, 5, 1),
+            (0, 1, 7),
+            (0, 7, 10),
+            (0, 10, 11),
+            (1, 5, 9),
+            (5, 11, 4),
+            (11, 10, 2),
+            (10, 7, 6),
+            (7, 1, 8),
+            (3, 9, 4),
+            (3, 4, 2),
+            (3, 2, 6),
+            (3, 6, 8),
+            (3, 8, 9),
+            (4, 9, 5),
+            (2, 4, 11),
+            (6, 2, 10),
+            (8, 6, 7),
+            (9, 8, 1),
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints = HashMap::new();
+
+            let mut midpoint = |a: u32, b: u32| -> u32 {
+                let key = (u32::min(a, b), u32::max(a, b));
+
+                *midpoints.entry(key).or_insert_with(|| {
+                    let mid = (points[a as usize] + points[b as usize]).normalize();
+                    points.push(mid);
+                    points.len() as u32 - 1
+                })
+            };
+
+            tris = tris.iter()
+                .flat_map(|&(a, b, c)| {
+                    let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+
+                    vec![(a, ab, ca), (b, bc, ab), (c, ca, bc), (ab, bc, ca)]
+                })
+                .collect();
+        }
+
+        let mut mesh = Mesh::new();
+        let mut lookup = HashMap::new();
+
+        for &(a, b, c) in &tris {
+            let corners = [a, b, c];
+            let mut uvs = [Vector2::zeros(); 3];
+
+            for (uv, &corner) in uvs.iter_mut().zip(corners.iter()) {
+                let point = points[corner as usize];
+                *uv = uv_mapping::spherical(&point, &point);
+            }
+
+            uv_mapping::fix_seam(&mut uvs);
+
+            let mut indices = [0; 3];
+
+            for (i, (&corner, uv)) in corners.iter().zip(uvs.iter()).enumerate() {
+                let key = (corner, uv.x.to_bits(), uv.y.to_bits());
+
+                indices[i] = *lookup.entry(key).or_insert_with(|| {
+                    let point = points[corner as usize];
+                    mesh.add_vertex(&vertex(point * radius, point, (uv.x, uv.y)));
+                    mesh.vertices().len() as u32 - 1
+                });
+            }
+
+            add_facing_tri(&mut mesh, (indices[0], indices[1], indices[2]));
+        }
+
+        mesh.generate_tangents();
+        mesh
+    }
+
+    // Along the Y axis, with capped ends
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        let mut mesh = Mesh::new();
+        let segments = segments.max(MIN_SEGMENTS);
+        let half = height / 2.0;
+
+        add_surface(&mut mesh, segments, 1, |u, v| {
+            let normal = sphere_point(u * 2.0 * PI, 0.0);
+            let pos = normal * radius + Vector3::y() * (v * height - half);
+
+            vertex(pos, normal, (u, v))
+        });
+
+        add_disc(&mut mesh, half, radius, segments, true);
+        add_disc(&mut mesh, -half, radius, segments, false);
+
+        mesh.generate_tangents();
+        mesh
+    }
+
+    // Along the Y axis pointing up, with a capped base. The tip has a vertex
+    // per segment so each side keeps its own normal
+    pub fn cone(radius: f32, height: f32, segments: u32) -> Self {
+        let mut mesh = Mesh::new();
+        let segments = segments.max(MIN_SEGMENTS);
+        let half = height / 2.0;
+
+        add_surface(&mut mesh, segments, 1, |u, v| {
+            let (sin, cos) = (u * 2.0 * PI).sin_cos();
+
+            let ring = radius * (1.0 - v);
+            let pos = Vector3::new(sin * ring, v * height - half, cos * ring);
+            let normal = Vector3::new(sin * height, radius, cos * height)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::y);
+
+            vertex(pos, normal, (u, v))
+        });
+
+        add_disc(&mut mesh, -half, radius, segments, false);
+
+        mesh.generate_tangents();
+        mesh
+    }
+
+    // Lying in the XZ plane, `segments` around the ring and `sides` around the
+    // tube
+    pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32) -> Self {
+        let mut mesh = Mesh::new();
+        let (segments, sides) = (segments.max(MIN_SEGMENTS), sides.max(MIN_SEGMENTS));
+
+        add_surface(&mut mesh, segments, sides, |u, v| {
+            let longitude = u * 2.0 * PI;
+            let center = sphere_point(longitude, 0.0) * major_radius;
+            let normal = sphere_point(longitude, v * 2.0 * PI);
+
+            vertex(center + normal * minor_radius, normal, (u, v))
+        });
+
+        mesh.generate_tangents();
+        mesh
+    }
+
+    // Cylinder of `height` along the Y axis with hemispheres on both ends, so
+    // its total height is height + 2 * radius. `rings` is per hemisphere and
+    // v runs evenly along the whole outline
+    pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Self {
+        let mut mesh = Mesh::new();
+        let (segments, rings) = (segments.max(MIN_SEGMENTS), rings.max(1));
+        let half = height / 2.0;
+
+        // Fraction of the outline taken up by each hemisphere
+        let cap = (PI * radius / 2.0) / (PI * radius + height);
+
+        let mut add_section = |rows: u32, lat_range: (f32, f32), y_range: (f32, f32), v_range| {
+            let (v_start, v_end): (f32, f32) = v_range;
+
+            add_surface(&mut mesh, segments, rows, |u, v| {
+                let latitude = lat_range.0 + (lat_range.1 - lat_range.0) * v;
+                let normal = sphere_point(u * 2.0 * PI, latitude);
+                let y = y_range.0 + (y_range.1 - y_range.0) * v;
+
+                vertex(
+                    normal * radius + Vector3::y() * y,
+                    normal,
+                    (u, v_start + (v_end - v_start) * v),
+                )
+            });
+        };
+
+        add_section(rings, (-PI / 2.0, 0.0), (-half, -half), (0.0, cap));
+        add_section(1, (0.0, 0.0), (-half, half), (cap, 1.0 - cap));
+        add_section(rings, (0.0, PI / 2.0), (half, half), (1.0 - cap, 1.0));
+
+        mesh.generate_tangents();
+        mesh
+    }
+}
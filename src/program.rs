@@ -11,6 +11,8 @@ gfx_defines!{
         pos: [f32;3] = "vPos",
         normal: [f32;3] = "vNormal",
         uv: [f32; 2] = "vUV",
+        // Bitangent sign in w, see tangents
+        tangent: [f32; 4] = "vTangent",
     }
 
     constant MaterialData{
@@ -20,6 +22,7 @@ gfx_defines!{
         specular_power: f32 = "m_specularPower",
         use_diffuse_texture: i32 = "m_useDiffuseTexture",
         use_specular_texture: i32 = "m_useSpecularTexture",
+        use_normal_texture: i32 = "m_useNormalTexture",
     }
 
     constant LightData{
@@ -44,6 +47,8 @@ gfx_defines!{
         model: [[f32; 4]; 4] = "model",
         view: [[f32; 4]; 4] = "view",
         projection: [[f32; 4]; 4] = "projection",
+        // Inverse-transpose of view * model, for normals
+        normal: [[f32; 4]; 4] = "normalMatrix",
     }
 
     pipeline pipe{
@@ -52,10 +57,10 @@ gfx_defines!{
         material: gfx::ConstantBuffer<MaterialData> = "materialData",
         diffuse_texture: gfx::TextureSampler<[f32;4]> = "diffuseTexture",
         specular_texture: gfx::TextureSampler<[f32;4]> = "specularTexture",
+        normal_texture: gfx::TextureSampler<[f32;4]> = "normalTexture",
         light_meta: gfx::ConstantBuffer<LightMeta> = "lightMeta",
         lights: gfx::ConstantBuffer<LightData> = "lightData",
-        out: gfx::BlendTarget<ColorFormat> =
-        ("Target0", gfx::state::MASK_ALL, gfx::preset::blend::ALPHA),
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
         out_depth: gfx::DepthTarget<DepthFormat> =
             gfx::preset::depth::LESS_EQUAL_WRITE,
     }
@@ -0,0 +1,132 @@
+use gfx;
+use gfx::Device;
+use gfx::traits::FactoryExt;
+use gfx_device_gl;
+use gfx_window_glutin as gfx_glutin;
+use glutin;
+use glutin::{ContextBuilder, EventsLoop, GlContext, GlWindow, WindowBuilder};
+
+use color::Color;
+use frame::FrameResources;
+use object;
+use program::{pipe, ColorFormat, DepthFormat};
+use scene::{Drawable, Scene};
+use utility;
+
+pub type Resources = gfx_device_gl::Resources;
+
+// Owns the window, the OpenGL device and everything needed to draw a Scene
+pub struct Renderer {
+    window: GlWindow,
+    device: gfx_device_gl::Device,
+    factory: gfx_device_gl::Factory,
+    encoder: gfx::Encoder<Resources, gfx_device_gl::CommandBuffer>,
+    program: gfx::pso::PipelineState<Resources, pipe::Meta>,
+    frame: FrameResources<Resources>,
+    pub clear_color: Color,
+}
+
+impl Renderer {
+    // Opens the window and builds the pipeline from the given shader files
+    pub fn new(
+        window_builder: WindowBuilder,
+        context_builder: ContextBuilder,
+        events_loop: &EventsLoop,
+        vertex_shader_path: &str,
+        fragment_shader_path: &str,
+    ) -> Result<Self, String> {
+        let (window, device, mut factory, color_view, depth_view) =
+            gfx_glutin::init::<ColorFormat, DepthFormat>(
+                window_builder,
+                context_builder,
+                events_loop,
+            );
+
+        let vertex_shader = utility::read_in_file(vertex_shader_path)
+            .map_err(|e| format!("{}: {}", vertex_shader_path, e))?;
+        let fragment_shader = utility::read_in_file(fragment_shader_path)
+            .map_err(|e| format!("{}: {}", fragment_shader_path, e))?;
+
+        let program = factory
+            .create_pipeline_simple(
+                vertex_shader.as_bytes(),
+                fragment_shader.as_bytes(),
+                pipe::new(),
+            )
+            .map_err(|e| e.to_string())?;
+
+        let frame = FrameResources::new(&mut factory, color_view, depth_view)?;
+        let encoder = factory.create_command_buffer().into();
+
+        Ok(Renderer {
+            window,
+            device,
+            factory,
+            encoder,
+            program,
+            frame,
+            clear_color: Color::black(),
+        })
+    }
+
+    pub fn window(&self) -> &GlWindow {
+        &self.window
+    }
+
+    // For building meshes, models and textures to put in a Scene
+    pub fn factory_mut(&mut self) -> &mut gfx_device_gl::Factory {
+        &mut self.factory
+    }
+
+    // Call on WindowEvent::Resized
+    pub fn resize(&mut self, width: u32, height: u32) {
+        // Resize the context (necessary in Walyand and OSX)
+        self.window.resize(width, height);
+
+        let mut color_view = self.frame.color_view().clone();
+        let mut depth_view = self.frame.depth_view().clone();
+        gfx_glutin::update_views(&self.window, &mut color_view, &mut depth_view);
+        self.frame.update_views(color_view, depth_view);
+    }
+
+    // Draws every object in the scene and presents the frame
+    pub fn render(&mut self, scene: &Scene<Resources>) -> Result<(), glutin::ContextError> {
+        self.frame
+            .set_camera(scene.camera.view(), scene.camera.projection_matrix());
+        self.frame
+            .upload_lights(&mut self.encoder, &scene.view_lights());
+
+        self.encoder
+            .clear(self.frame.color_view(), self.clear_color.into());
+        self.encoder.clear_depth(self.frame.depth_view(), 1.0);
+
+        for id in scene.ids() {
+            let drawable = match scene.drawable(id) {
+                Some(drawable) => drawable,
+                None => continue,
+            };
+
+            let obj = scene.object(id);
+            let world = scene.world_matrix(id);
+            let (encoder, frame, program) = (&mut self.encoder, &self.frame, &self.program);
+
+            match *drawable {
+                Drawable::Mesh(ref data) => {
+                    object::draw(encoder, frame, data, program, obj, &world)
+                }
+                Drawable::Model(ref data) => {
+                    object::draw_model(encoder, frame, data, program, obj, &world)
+                }
+                Drawable::Lod(ref data) => {
+                    object::draw_lod(encoder, frame, data, program, obj, &world)
+                }
+            }
+        }
+
+        self.encoder.flush(&mut self.device);
+        self.window.swap_buffers()?;
+        self.device.cleanup();
+
+        Ok(())
+    }
+}
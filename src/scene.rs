@@ -0,0 +1,250 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gfx::Resources;
+
+use na::{Matrix4, Point3};
+
+use camera::Camera;
+use light::Light;
+use lod::LodChainData;
+use mesh::MeshData;
+use model::ModelData;
+use object::Object;
+
+// GPU geometry an object is drawn with. Shared through Rc so several objects
+// can use the same buffers
+pub enum Drawable<R: Resources> {
+    Mesh(Rc<MeshData<R>>),
+    Model(Rc<ModelData<R>>),
+    Lod(Rc<LodChainData<R>>),
+}
+
+impl<R: Resources> Clone for Drawable<R> {
+    fn clone(&self) -> Self {
+        match *self {
+            Drawable::Mesh(ref data) => Drawable::Mesh(data.clone()),
+            Drawable::Model(ref data) => Drawable::Model(data.clone()),
+            Drawable::Lod(ref data) => Drawable::Lod(data.clone()),
+        }
+    }
+}
+
+// Index of a node in the scene, returned by Scene::add and Scene::add_child
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectId(usize);
+
+struct SceneNode<R: Resources> {
+    name: String,
+    object: Object<R>,
+    // Nodes without one only group and move their children
+    drawable: Option<Drawable<R>>,
+    parent: Option<ObjectId>,
+    children: Vec<ObjectId>,
+    // Cached parent world * local, recomputed when dirty
+    world: Cell<Matrix4<f32>>,
+    dirty: Cell<bool>,
+}
+
+// Everything Renderer::render needs to draw a frame. Objects form a tree,
+// each one positioned relative to its parent
+pub struct Scene<R: Resources> {
+    nodes: Vec<SceneNode<R>>,
+    // In world space
+    pub lights: Vec<Light>,
+    // In the space of the node they're attached to
+    attached_lights: Vec<(ObjectId, Light)>,
+    pub camera: Camera,
+}
+
+impl<R: Resources> Scene<R> {
+    pub fn new(camera: Camera) -> Self {
+        Scene {
+            nodes: Vec::new(),
+            lights: Vec::new(),
+            attached_lights: Vec::new(),
+            camera,
+        }
+    }
+
+    // Adds a node at the root of the scene
+    pub fn add(
+        &mut self,
+        name: &str,
+        object: Object<R>,
+        drawable: Option<Drawable<R>>,
+    ) -> ObjectId {
+        self.nodes.push(SceneNode {
+            name: name.to_string(),
+            object,
+            drawable,
+            parent: None,
+            children: Vec::new(),
+            world: Cell::new(Matrix4::identity()),
+            dirty: Cell::new(true),
+        });
+
+        ObjectId(self.nodes.len() - 1)
+    }
+
+    pub fn add_child(
+        &mut self,
+        parent: ObjectId,
+        name: &str,
+        object: Object<R>,
+        drawable: Option<Drawable<R>>,
+    ) -> ObjectId {
+        let id = self.add(name, object, drawable);
+        self.nodes[id.0].parent = Some(parent);
+        self.nodes[parent.0].children.push(id);
+
+        id
+    }
+
+    // Moves a node under a new parent, or to the root with None. The local
+    // transform is kept, so the node moves along with its new parent
+    pub fn set_parent(
+        &mut self,
+        id: ObjectId,
+        parent: Option<ObjectId>,
+    ) -> Result<(), &'static str> {
+        if let Some(parent) = parent {
+            if parent == id || self.is_ancestor(id, parent) {
+                return Err("Node can't be parented to itself or its descendants");
+            }
+        }
+
+        if let Some(old_parent) = self.nodes[id.0].parent {
+            self.nodes[old_parent.0].children.retain(|&child| child != id);
+        }
+
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+
+        self.nodes[id.0].parent = parent;
+        self.mark_dirty(id);
+
+        Ok(())
+    }
+
+    // True if `ancestor` is above `id` in the tree
+    fn is_ancestor(&self, ancestor: ObjectId, id: ObjectId) -> bool {
+        let mut current = self.nodes[id.0].parent;
+
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.nodes[node.0].parent;
+        }
+
+        false
+    }
+
+    fn mark_dirty(&self, id: ObjectId) {
+        let node = &self.nodes[id.0];
+
+        // Children of a dirty node are already dirty
+        if !node.dirty.get() {
+            node.dirty.set(true);
+
+            for &child in &node.children {
+                self.mark_dirty(child);
+            }
+        }
+    }
+
+    // First node with the given name
+    pub fn find(&self, name: &str) -> Option<ObjectId> {
+        self.nodes
+            .iter()
+            .position(|node| node.name == name)
+            .map(ObjectId)
+    }
+
+    pub fn name(&self, id: ObjectId) -> &str {
+        &self.nodes[id.0].name
+    }
+
+    pub fn parent(&self, id: ObjectId) -> Option<ObjectId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn children(&self, id: ObjectId) -> &[ObjectId] {
+        &self.nodes[id.0].children
+    }
+
+    pub fn object(&self, id: ObjectId) -> &Object<R> {
+        &self.nodes[id.0].object
+    }
+
+    // Marks the node and everything below it for a world transform update
+    pub fn object_mut(&mut self, id: ObjectId) -> &mut Object<R> {
+        self.mark_dirty(id);
+        &mut self.nodes[id.0].object
+    }
+
+    pub fn drawable(&self, id: ObjectId) -> Option<&Drawable<R>> {
+        self.nodes[id.0].drawable.as_ref()
+    }
+
+    pub fn set_drawable(&mut self, id: ObjectId, drawable: Option<Drawable<R>>) {
+        self.nodes[id.0].drawable = drawable;
+    }
+
+    pub fn ids(&self) -> Vec<ObjectId> {
+        (0..self.nodes.len()).map(ObjectId).collect()
+    }
+
+    // Local transform combined with every parent's, cached until the node or
+    // one of its parents changes
+    pub fn world_matrix(&self, id: ObjectId) -> Matrix4<f32> {
+        let node = &self.nodes[id.0];
+
+        if node.dirty.get() {
+            let local = node.object.build_matrix();
+            let world = match node.parent {
+                Some(parent) => self.world_matrix(parent) * local,
+                None => local,
+            };
+
+            node.world.set(world);
+            node.dirty.set(false);
+        }
+
+        node.world.get()
+    }
+
+    pub fn world_position(&self, id: ObjectId) -> Point3<f32> {
+        let world = self.world_matrix(id);
+        Point3::new(world[(0, 3)], world[(1, 3)], world[(2, 3)])
+    }
+
+    // The light follows the node, its position and direction are relative to it
+    pub fn attach_light(&mut self, id: ObjectId, light: Light) {
+        self.attached_lights.push((id, light));
+    }
+
+    // All lights in world space, attached ones moved to where their nodes are
+    pub fn world_lights(&self) -> Vec<Light> {
+        self.lights_transformed(&Matrix4::identity())
+    }
+
+    // All lights in the camera's view space, which the shader lights in
+    pub fn view_lights(&self) -> Vec<Light> {
+        self.lights_transformed(&self.camera.view())
+    }
+
+    fn lights_transformed(&self, matrix: &Matrix4<f32>) -> Vec<Light> {
+        self.lights
+            .iter()
+            .map(|light| light.transformed(matrix))
+            .chain(
+                self.attached_lights
+                    .iter()
+                    .map(|&(id, ref light)| light.transformed(&(matrix * self.world_matrix(id)))),
+            )
+            .collect()
+    }
+}
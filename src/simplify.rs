@@ -0,0 +1,413 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::f32;
+
+use na::Vector3;
+
+use mesh::{match_positions, Mesh};
+use normals;
+
+#[derive(Clone, Copy)]
+pub enum SimplifyTarget {
+    // Stops once at most this many triangles are left
+    Triangles(usize),
+    // Stops before any collapse would move a position further than this
+    // distance from the original triangles around it
+    Error(f32),
+}
+
+// Symmetric 4x4 matrix of the summed squared distances to a set of planes
+#[derive(Clone, Copy)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn zero() -> Self {
+        Quadric([0.0; 10])
+    }
+
+    // Plane through `point` with unit `normal`, scaled by `weight`
+    fn from_plane(normal: &Vector3<f32>, point: &Vector3<f32>, weight: f64) -> Self {
+        let (a, b, c) = (f64::from(normal.x), f64::from(normal.y), f64::from(normal.z));
+        let d = -(a * f64::from(point.x) + b * f64::from(point.y) + c * f64::from(point.z));
+
+        Quadric([
+            a * a * weight,
+            a * b * weight,
+            a * c * weight,
+            a * d * weight,
+            b * b * weight,
+            b * c * weight,
+            b * d * weight,
+            c * c * weight,
+            c * d * weight,
+            d * d * weight,
+        ])
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *value += *other;
+        }
+    }
+
+    fn error(&self, point: &Vector3<f32>) -> f64 {
+        let (x, y, z) = (f64::from(point.x), f64::from(point.y), f64::from(point.z));
+        let q = &self.0;
+
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z + q[9]
+    }
+}
+
+// Moving position `from` onto position `to`, stamped with both versions so
+// entries made stale by later collapses can be skipped
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    from_version: u32,
+    to_version: u32,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed so BinaryHeap pops the cheapest collapse first
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+struct Simplifier {
+    // Welded positions, vertices split for seams share one
+    positions: Vec<Vector3<f32>>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    // Positions on an open edge, which are never moved
+    locked: Vec<bool>,
+    // Triangles touching each position
+    adjacent: Vec<Vec<usize>>,
+    // Position and vertex of each triangle corner
+    tri_positions: Vec<[usize; 3]>,
+    tri_verts: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    alive_count: usize,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Simplifier {
+    // Area weighting favours keeping large triangles, but then the cost is no
+    // longer a squared distance. Unweighted, it's the sum of the squared
+    // distances to every plane, which bounds each of them
+    fn new(mesh: &Mesh, area_weighted: bool) -> Self {
+        let verts = mesh.vertices();
+
+        let (vert_positions, positions) = match_positions(verts);
+
+        let tri_verts: Vec<[u32; 3]> = mesh.indices()
+            .chunks(3)
+            .map(|tri| [tri[0], tri[1], tri[2]])
+            .collect();
+
+        let tri_positions: Vec<[usize; 3]> = tri_verts
+            .iter()
+            .map(|tri| {
+                [
+                    vert_positions[tri[0] as usize],
+                    vert_positions[tri[1] as usize],
+                    vert_positions[tri[2] as usize],
+                ]
+            })
+            .collect();
+
+        let mut quadrics = vec![Quadric::zero(); positions.len()];
+        let mut adjacent = vec![Vec::new(); positions.len()];
+        let mut edge_counts = HashMap::new();
+
+        for (tri_ind, tri) in tri_positions.iter().enumerate() {
+            let (a, b, c) = (positions[tri[0]], positions[tri[1]], positions[tri[2]]);
+            let normal = normals::face_normal(&a, &b, &c);
+            let weight = if area_weighted { f64::from(normal.norm()) } else { 1.0 };
+
+            if let Some(unit) = normal.try_normalize(f32::EPSILON) {
+                let quadric = Quadric::from_plane(&unit, &a, weight);
+
+                for &pos in tri {
+                    quadrics[pos].add(&quadric);
+                }
+            }
+
+            for corner in 0..3 {
+                adjacent[tri[corner]].push(tri_ind);
+
+                let (from, to) = (tri[corner], tri[(corner + 1) % 3]);
+                *edge_counts
+                    .entry((usize::min(from, to), usize::max(from, to)))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        let mut locked = vec![false; positions.len()];
+
+        for (&(first, second), &count) in &edge_counts {
+            if count != 2 {
+                locked[first] = true;
+                locked[second] = true;
+            }
+        }
+
+        let tri_count = tri_verts.len();
+        let pos_count = positions.len();
+
+        let mut simplifier = Simplifier {
+            positions,
+            quadrics,
+            versions: vec![0; pos_count],
+            removed: vec![false; pos_count],
+            locked,
+            adjacent,
+            tri_positions,
+            tri_verts,
+            alive: vec![true; tri_count],
+            alive_count: tri_count,
+            heap: BinaryHeap::new(),
+        };
+
+        for pos in 0..pos_count {
+            simplifier.push_edges(pos);
+        }
+
+        simplifier
+    }
+
+    fn neighbours(&self, pos: usize) -> HashSet<usize> {
+        self.adjacent[pos]
+            .iter()
+            .flat_map(|&tri| self.tri_positions[tri].iter().cloned())
+            .filter(|&other| other != pos)
+            .collect()
+    }
+
+    fn push(&mut self, from: usize, to: usize) {
+        if self.locked[from] {
+            return;
+        }
+
+        let mut quadric = self.quadrics[from];
+        quadric.add(&self.quadrics[to]);
+
+        self.heap.push(Collapse {
+            cost: quadric.error(&self.positions[to]),
+            from,
+            to,
+            from_version: self.versions[from],
+            to_version: self.versions[to],
+        });
+    }
+
+    // Queues collapses in both directions along every edge around `pos`
+    fn push_edges(&mut self, pos: usize) {
+        for other in self.neighbours(pos) {
+            self.push(pos, other);
+            self.push(other, pos);
+        }
+    }
+
+    // The vertex each of `from`'s vertices becomes, or None if the collapse
+    // would tear a UV or normal seam. Every vertex at `from` has to share a
+    // triangle with exactly one vertex at `to`
+    fn vertex_mapping(&self, from: usize, to: usize) -> Option<HashMap<u32, u32>> {
+        let mut mapping = HashMap::new();
+        let mut from_verts = HashSet::new();
+
+        for &tri in &self.adjacent[from] {
+            let (positions, verts) = (&self.tri_positions[tri], &self.tri_verts[tri]);
+            let from_corner = positions.iter().position(|&pos| pos == from)?;
+
+            from_verts.insert(verts[from_corner]);
+
+            if let Some(to_corner) = positions.iter().position(|&pos| pos == to) {
+                let previous = mapping.insert(verts[from_corner], verts[to_corner]);
+
+                if previous.is_some() && previous != Some(verts[to_corner]) {
+                    return None;
+                }
+            }
+        }
+
+        if from_verts.iter().all(|vert| mapping.contains_key(vert)) {
+            Some(mapping)
+        } else {
+            None
+        }
+    }
+
+    // Only the two triangles on the edge may share both ends, otherwise the
+    // collapse pinches the surface into a non-manifold shape
+    fn keeps_manifold(&self, from: usize, to: usize) -> bool {
+        let shared_tris = self.adjacent[from]
+            .iter()
+            .filter(|&&tri| self.tri_positions[tri].contains(&to))
+            .count();
+
+        let from_neighbours = self.neighbours(from);
+        let shared_neighbours = self.neighbours(to)
+            .iter()
+            .filter(|pos| from_neighbours.contains(pos))
+            .count();
+
+        shared_tris == 2 && shared_neighbours == 2
+    }
+
+    fn flips_triangle(&self, from: usize, to: usize) -> bool {
+        self.adjacent[from].iter().any(|&tri| {
+            let positions = &self.tri_positions[tri];
+
+            if positions.contains(&to) {
+                return false;
+            }
+
+            let points: Vec<_> = positions.iter().map(|&pos| self.positions[pos]).collect();
+            let moved: Vec<_> = positions
+                .iter()
+                .map(|&pos| self.positions[if pos == from { to } else { pos }])
+                .collect();
+
+            let before = normals::face_normal(&points[0], &points[1], &points[2]);
+            let after = normals::face_normal(&moved[0], &moved[1], &moved[2]);
+
+            match (before.try_normalize(f32::EPSILON), after.try_normalize(f32::EPSILON)) {
+                (Some(before), Some(after)) => before.dot(&after) < 0.2,
+                (Some(_), None) => true,
+                _ => false,
+            }
+        })
+    }
+
+    fn collapse(&mut self, from: usize, to: usize, mapping: &HashMap<u32, u32>) {
+        let tris = self.adjacent[from].clone();
+
+        for tri in tris {
+            if self.tri_positions[tri].contains(&to) {
+                self.alive[tri] = false;
+                self.alive_count -= 1;
+
+                for &pos in &self.tri_positions[tri] {
+                    self.adjacent[pos].retain(|&other| other != tri);
+                }
+            } else {
+                for corner in 0..3 {
+                    if self.tri_positions[tri][corner] == from {
+                        self.tri_positions[tri][corner] = to;
+                        self.tri_verts[tri][corner] = mapping[&self.tri_verts[tri][corner]];
+                    }
+                }
+
+                self.adjacent[to].push(tri);
+            }
+        }
+
+        self.adjacent[from].clear();
+        self.removed[from] = true;
+
+        let quadric = self.quadrics[from];
+        self.quadrics[to].add(&quadric);
+        self.versions[to] += 1;
+
+        self.push_edges(to);
+    }
+
+    fn run(&mut self, target: SimplifyTarget) {
+        let (target_count, max_error) = match target {
+            SimplifyTarget::Triangles(count) => (count, f64::INFINITY),
+            SimplifyTarget::Error(error) => (0, f64::from(error) * f64::from(error)),
+        };
+
+        while self.alive_count > target_count {
+            let Collapse {
+                cost,
+                from,
+                to,
+                from_version,
+                to_version,
+            } = match self.heap.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+
+            if self.removed[from] || self.removed[to] || self.versions[from] != from_version
+                || self.versions[to] != to_version
+            {
+                continue;
+            }
+
+            if cost > max_error {
+                break;
+            }
+
+            if !self.keeps_manifold(from, to) || self.flips_triangle(from, to) {
+                continue;
+            }
+
+            if let Some(mapping) = self.vertex_mapping(from, to) {
+                self.collapse(from, to, &mapping);
+            }
+        }
+    }
+
+    // Remaining triangles with unused vertices dropped, in their original order
+    fn finish(&self, mesh: &Mesh) -> Mesh {
+        let verts = mesh.vertices();
+        let mut remap = vec![None; verts.len()];
+        let mut result = Mesh::new();
+
+        for (tri, _) in self.tri_verts.iter().zip(&self.alive).filter(|&(_, &alive)| alive) {
+            let mut indices = [0; 3];
+
+            for (index, &vert) in indices.iter_mut().zip(tri.iter()) {
+                *index = *remap[vert as usize].get_or_insert_with(|| {
+                    result.add_vertex(&verts[vert as usize]);
+                    result.vertices().len() as u32 - 1
+                });
+            }
+
+            result.add_tri((indices[0], indices[1], indices[2]));
+        }
+
+        result
+    }
+}
+
+impl Mesh {
+    // Quadric error metric edge collapse, where each collapse moves one
+    // position onto a neighbouring one so vertex attributes stay as they
+    // were. Open boundaries are kept as they are, and seams in UVs or normals
+    // are only collapsed along their own length
+    pub fn simplify(&self, target: SimplifyTarget) -> Mesh {
+        let area_weighted = match target {
+            SimplifyTarget::Triangles(_) => true,
+            SimplifyTarget::Error(_) => false,
+        };
+        let mut simplifier = Simplifier::new(self, area_weighted);
+
+        simplifier.run(target);
+        simplifier.finish(self)
+    }
+}
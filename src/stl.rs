@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::f32;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+use na::Vector3;
+
+use mesh::Mesh;
+use mesh_loader::{self, MeshLoadError, ParseErrorKind, TokenError};
+use normals;
+use program::Vertex;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum StlFormat {
+    Ascii,
+    Binary,
+}
+
+const HEADER_SIZE: usize = 80;
+// Normal, three corners and a two byte attribute count
+const FACET_SIZE: usize = 50;
+
+type Facet = (Vector3<f32>, [Vector3<f32>; 3]);
+
+fn io_error(file: &str, error: io::Error) -> MeshLoadError {
+    MeshLoadError::Io {
+        file: file.to_string(),
+        error,
+    }
+}
+
+fn read_vector(data: &[u8]) -> Vector3<f32> {
+    let read = |offset: usize| {
+        f32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+
+    Vector3::new(read(0), read(4), read(8))
+}
+
+fn parse_binary(data: &[u8], file: &str) -> Result<Vec<Facet>, MeshLoadError> {
+    let count = u32::from_le_bytes([
+        data[HEADER_SIZE],
+        data[HEADER_SIZE + 1],
+        data[HEADER_SIZE + 2],
+        data[HEADER_SIZE + 3],
+    ]) as usize;
+
+    if data.len() < HEADER_SIZE + 4 + count * FACET_SIZE {
+        return Err(MeshLoadError::Format {
+            file: file.to_string(),
+            error: format!("expected {} facets, file is too short", count),
+        });
+    }
+
+    Ok(data[HEADER_SIZE + 4..]
+        .chunks(FACET_SIZE)
+        .take(count)
+        .map(|facet| {
+            (
+                read_vector(&facet[0..]),
+                [
+                    read_vector(&facet[12..]),
+                    read_vector(&facet[24..]),
+                    read_vector(&facet[36..]),
+                ],
+            )
+        })
+        .collect())
+}
+
+fn parse_vector(args: &[(usize, &str)], line_len: usize) -> Result<Vector3<f32>, TokenError> {
+    Ok(Vector3::new(
+        mesh_loader::parse_float(args.first(), line_len)?,
+        mesh_loader::parse_float(args.get(1), line_len)?,
+        mesh_loader::parse_float(args.get(2), line_len)?,
+    ))
+}
+
+fn parse_ascii(data: &[u8], file: &str) -> Result<Vec<Facet>, MeshLoadError> {
+    let mut facets = Vec::new();
+    let mut normal = Vector3::zeros();
+    let mut corners = Vec::new();
+
+    mesh_loader::for_each_statement(data, file, |statement| {
+        let tokens = mesh_loader::tokenize(statement.text);
+        let line_len = statement.text.len();
+
+        let (column, keyword) = match tokens.first() {
+            Some(&first) => first,
+            None => return Ok(()),
+        };
+
+        match keyword {
+            "facet" => {
+                // "facet normal nx ny nz"
+                normal = parse_vector(tokens.get(2..).unwrap_or(&[]), line_len)?;
+                corners.clear();
+            }
+            "vertex" => corners.push(parse_vector(&tokens[1..], line_len)?),
+            "endfacet" => {
+                if corners.len() != 3 {
+                    return Err((
+                        column,
+                        ParseErrorKind::MalformedFace(format!("{} vertices", corners.len())),
+                    ));
+                }
+
+                facets.push((normal, [corners[0], corners[1], corners[2]]));
+            }
+            "solid" | "endsolid" | "outer" | "endloop" => {}
+            _ => {
+                return Err((
+                    column,
+                    ParseErrorKind::UnsupportedDirective(keyword.to_string()),
+                ))
+            }
+        }
+
+        Ok(())
+    })?;
+
+    Ok(facets)
+}
+
+// Binary files can also start with "solid", so the size decides
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() < HEADER_SIZE + 4 {
+        return false;
+    }
+
+    let count = u32::from_le_bytes([
+        data[HEADER_SIZE],
+        data[HEADER_SIZE + 1],
+        data[HEADER_SIZE + 2],
+        data[HEADER_SIZE + 3],
+    ]) as usize;
+
+    data.len() == HEADER_SIZE + 4 + count * FACET_SIZE
+        || !String::from_utf8_lossy(&data[..5]).eq_ignore_ascii_case("solid")
+}
+
+// Flat shaded, corners only share a vertex when both position and normal
+// match. Facets with a missing normal, or one pointing against their
+// winding, get one from the winding
+pub fn parse_stl(data: &[u8], file: &str) -> Result<Mesh, MeshLoadError> {
+    let facets = if is_binary(data) {
+        parse_binary(data, file)?
+    } else {
+        parse_ascii(data, file)?
+    };
+
+    let mut mesh = Mesh::new();
+    let mut lookup = HashMap::new();
+    let mut vert_count = 0;
+
+    for (normal, corners) in facets {
+        let winding = normals::face_normal(&corners[0], &corners[1], &corners[2])
+            .try_normalize(f32::EPSILON);
+
+        let normal = match (normal.try_normalize(f32::EPSILON), winding) {
+            (Some(normal), Some(winding)) if normal.dot(&winding) <= 0.0 => winding,
+            (Some(normal), _) => normal,
+            (None, winding) => winding.unwrap_or_else(Vector3::z),
+        };
+
+        let mut indices = [0; 3];
+
+        for (i, corner) in corners.iter().enumerate() {
+            let key = [
+                corner.x.to_bits(),
+                corner.y.to_bits(),
+                corner.z.to_bits(),
+                normal.x.to_bits(),
+                normal.y.to_bits(),
+                normal.z.to_bits(),
+            ];
+
+            indices[i] = *lookup.entry(key).or_insert_with(|| {
+                mesh.add_vertex(&Vertex {
+                    pos: (*corner).into(),
+                    normal: normal.into(),
+                    uv: [0.0, 0.0],
+                    tangent: [0.0; 4],
+                });
+
+                vert_count += 1;
+                vert_count - 1
+            });
+        }
+
+        mesh.add_tri((indices[0], indices[1], indices[2]));
+    }
+
+    // Without UVs these only give a consistent direction along the surface
+    mesh.generate_tangents();
+
+    Ok(mesh)
+}
+
+pub fn load_file(file_path: &str) -> Result<Mesh, MeshLoadError> {
+    let mut data = Vec::new();
+
+    File::open(file_path)
+        .and_then(|mut file| file.read_to_end(&mut data))
+        .map_err(|error| io_error(file_path, error))?;
+
+    parse_stl(&data, file_path)
+}
+
+// Normals are recomputed per facet from the positions, since STL has no
+// vertex normals
+pub fn write_stl<W: Write>(writer: &mut W, mesh: &Mesh, format: StlFormat) -> io::Result<()> {
+    let verts = mesh.vertices();
+
+    let facets: Vec<Facet> = mesh.indices()
+        .chunks(3)
+        .map(|tri| {
+            let corners = [
+                Vector3::from(verts[tri[0] as usize].pos),
+                Vector3::from(verts[tri[1] as usize].pos),
+                Vector3::from(verts[tri[2] as usize].pos),
+            ];
+
+            let normal = normals::face_normal(&corners[0], &corners[1], &corners[2])
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::zeros);
+
+            (normal, corners)
+        })
+        .collect();
+
+    if format == StlFormat::Ascii {
+        writeln!(writer, "solid mesh")?;
+
+        for (normal, corners) in facets {
+            writeln!(writer, "facet normal {} {} {}", normal.x, normal.y, normal.z)?;
+            writeln!(writer, "  outer loop")?;
+
+            for corner in &corners {
+                writeln!(writer, "    vertex {} {} {}", corner.x, corner.y, corner.z)?;
+            }
+
+            writeln!(writer, "  endloop")?;
+            writeln!(writer, "endfacet")?;
+        }
+
+        return writeln!(writer, "endsolid mesh");
+    }
+
+    let mut header = [0u8; HEADER_SIZE];
+    header[..9].copy_from_slice(b"rgraphics");
+
+    writer.write_all(&header)?;
+    writer.write_all(&(facets.len() as u32).to_le_bytes())?;
+
+    for (normal, corners) in facets {
+        for vector in Some(&normal).into_iter().chain(corners.iter()) {
+            for value in vector.iter() {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        writer.write_all(&[0, 0])?;
+    }
+
+    Ok(())
+}
+
+pub fn save_file(file_path: &str, mesh: &Mesh, format: StlFormat) -> Result<(), MeshLoadError> {
+    let file = File::create(file_path).map_err(|error| io_error(file_path, error))?;
+    let mut writer = BufWriter::new(file);
+
+    write_stl(&mut writer, mesh, format)
+        .and_then(|_| writer.flush())
+        .map_err(|error| io_error(file_path, error))
+}
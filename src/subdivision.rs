@@ -0,0 +1,445 @@
+use std::collections::{HashMap, HashSet};
+use std::f32;
+
+use na::Vector3;
+
+use mesh::{match_positions, Mesh};
+use normals;
+use program::Vertex;
+use triangulation;
+
+// Faces with any number of corners, for Catmull-Clark on quads as they were
+// modelled rather than after triangulation
+#[derive(Default)]
+pub struct PolygonMesh {
+    pub vertices: Vec<Vertex>,
+    pub faces: Vec<Vec<u32>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scheme {
+    Loop,
+    CatmullClark,
+}
+
+type Edge = (u32, u32);
+
+fn edge_key(first: u32, second: u32) -> Edge {
+    (u32::min(first, second), u32::max(first, second))
+}
+
+// One index space of the control mesh, either positions or UVs, with faces
+// in the same order and corners in the same places in both
+struct Level {
+    values: Vec<Vector3<f32>>,
+    faces: Vec<Vec<u32>>,
+    // Creases, edges with other than two faces are treated as creases too
+    sharp: HashSet<Edge>,
+    // Keeps vertices on creases in place so edges along them stay straight,
+    // used for UVs so the texture still lines up across seams
+    linear_boundaries: bool,
+}
+
+impl Level {
+    // Hard crease rules from Hoppe et al. 1994: crease edge points are
+    // midpoints, vertices on exactly two crease edges follow the curve and
+    // vertices on more are corners that stay put
+    fn subdivide(&self, scheme: Scheme) -> Level {
+        let count = self.values.len();
+
+        let mut edge_lookup = HashMap::new();
+        let mut edges = Vec::new();
+        let mut edge_faces: Vec<Vec<usize>> = Vec::new();
+
+        for (face_ind, face) in self.faces.iter().enumerate() {
+            for corner in 0..face.len() {
+                let key = edge_key(face[corner], face[(corner + 1) % face.len()]);
+
+                let edge = *edge_lookup.entry(key).or_insert_with(|| {
+                    edges.push(key);
+                    edge_faces.push(Vec::new());
+                    edges.len() - 1
+                });
+
+                edge_faces[edge].push(face_ind);
+            }
+        }
+
+        let is_sharp =
+            |edge: usize| edge_faces[edge].len() != 2 || self.sharp.contains(&edges[edge]);
+
+        let face_points: Vec<Vector3<f32>> = self.faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .fold(Vector3::zeros(), |acc, &index| acc + self.values[index as usize])
+                    / face.len() as f32
+            })
+            .collect();
+
+        let edge_points: Vec<Vector3<f32>> = edges
+            .iter()
+            .enumerate()
+            .map(|(edge, &(first, second))| {
+                let (a, b) = (self.values[first as usize], self.values[second as usize]);
+
+                if is_sharp(edge) {
+                    return (a + b) / 2.0;
+                }
+
+                let (left, right) = (edge_faces[edge][0], edge_faces[edge][1]);
+
+                match scheme {
+                    Scheme::CatmullClark => {
+                        (a + b + face_points[left] + face_points[right]) / 4.0
+                    }
+                    Scheme::Loop => {
+                        let opposite = |face: usize| {
+                            self.faces[face]
+                                .iter()
+                                .find(|&&index| index != first && index != second)
+                                .map_or(face_points[face], |&index| self.values[index as usize])
+                        };
+
+                        (a + b) * 0.375 + (opposite(left) + opposite(right)) * 0.125
+                    }
+                }
+            })
+            .collect();
+
+        let mut vert_edges = vec![Vec::new(); count];
+        let mut vert_faces = vec![Vec::new(); count];
+
+        for (edge, &(first, second)) in edges.iter().enumerate() {
+            vert_edges[first as usize].push(edge);
+            vert_edges[second as usize].push(edge);
+        }
+
+        for (face_ind, face) in self.faces.iter().enumerate() {
+            for &index in face {
+                vert_faces[index as usize].push(face_ind);
+            }
+        }
+
+        let other_end = |edge: usize, vert: usize| {
+            let (first, second) = edges[edge];
+            let other = if first as usize == vert { second } else { first };
+
+            self.values[other as usize]
+        };
+
+        let vertex_points = (0..count).map(|vert| {
+            let value = self.values[vert];
+            let sharp: Vec<usize> = vert_edges[vert]
+                .iter()
+                .cloned()
+                .filter(|&edge| is_sharp(edge))
+                .collect();
+
+            if vert_faces[vert].is_empty() || sharp.len() > 2
+                || (self.linear_boundaries && sharp.len() == 2)
+            {
+                return value;
+            }
+
+            if sharp.len() == 2 {
+                let ends = other_end(sharp[0], vert) + other_end(sharp[1], vert);
+                return (value * 6.0 + ends) / 8.0;
+            }
+
+            let valence = vert_edges[vert].len() as f32;
+            let neighbours = vert_edges[vert]
+                .iter()
+                .fold(Vector3::zeros(), |acc, &edge| acc + other_end(edge, vert));
+
+            match scheme {
+                Scheme::CatmullClark => {
+                    let face_count = vert_faces[vert].len() as f32;
+                    let faces = vert_faces[vert]
+                        .iter()
+                        .fold(Vector3::zeros(), |acc, &face| acc + face_points[face])
+                        / face_count;
+                    let midpoints = (value * valence + neighbours) / (2.0 * valence);
+
+                    (faces + midpoints * 2.0 + value * (face_count - 3.0)) / face_count
+                }
+                Scheme::Loop => {
+                    // Warren's weights
+                    let beta = if valence > 3.0 {
+                        3.0 / (8.0 * valence)
+                    } else {
+                        3.0 / 16.0
+                    };
+
+                    value * (1.0 - valence * beta) + neighbours * beta
+                }
+            }
+        });
+
+        let mut values: Vec<Vector3<f32>> = vertex_points.collect();
+        values.extend(edge_points);
+
+        if scheme == Scheme::CatmullClark {
+            values.extend(face_points);
+        }
+
+        let edge_vert =
+            |first: u32, second: u32| (count + edge_lookup[&edge_key(first, second)]) as u32;
+        let face_start = count + edges.len();
+
+        let faces = self.faces
+            .iter()
+            .enumerate()
+            .flat_map(|(face_ind, face)| {
+                let corners = face.len();
+                let edge_after =
+                    |corner: usize| edge_vert(face[corner], face[(corner + 1) % corners]);
+                let edge_before =
+                    |corner: usize| edge_vert(face[(corner + corners - 1) % corners], face[corner]);
+
+                match scheme {
+                    Scheme::CatmullClark => (0..corners)
+                        .map(|corner| {
+                            vec![
+                                face[corner],
+                                edge_after(corner),
+                                (face_start + face_ind) as u32,
+                                edge_before(corner),
+                            ]
+                        })
+                        .collect::<Vec<_>>(),
+                    Scheme::Loop => {
+                        let mut tris: Vec<_> = (0..corners)
+                            .map(|corner| {
+                                vec![face[corner], edge_after(corner), edge_before(corner)]
+                            })
+                            .collect();
+                        tris.push((0..corners).map(edge_after).collect());
+                        tris
+                    }
+                }
+            })
+            .collect();
+
+        let mut sharp = HashSet::new();
+
+        for &(first, second) in &self.sharp {
+            if let Some(&edge) = edge_lookup.get(&(first, second)) {
+                let middle = (count + edge) as u32;
+
+                sharp.insert(edge_key(first, middle));
+                sharp.insert(edge_key(middle, second));
+            }
+        }
+
+        Level {
+            values,
+            faces,
+            sharp,
+            linear_boundaries: self.linear_boundaries,
+        }
+    }
+}
+
+// Splits the control mesh into a position level, where vertices only split
+// by their normals are joined, and a UV level, where only vertices at the
+// same position and UV are. Seams are edges with one face in the UV level,
+// so UVs on either side subdivide independently and linearly along it
+fn subdivide(
+    verts: &[Vertex],
+    faces: &[Vec<u32>],
+    levels: u32,
+    crease_angle: f32,
+    scheme: Scheme,
+) -> Mesh {
+    let faces: Vec<&Vec<u32>> = faces.iter().filter(|face| face.len() >= 3).collect();
+    let (vert_points, points) = match_positions(verts);
+
+    let mut pos_lookup = HashMap::new();
+    let mut uv_lookup = HashMap::new();
+    let mut positions = Level {
+        values: Vec::new(),
+        faces: Vec::with_capacity(faces.len()),
+        sharp: HashSet::new(),
+        linear_boundaries: false,
+    };
+    let mut uvs = Level {
+        values: Vec::new(),
+        faces: Vec::with_capacity(faces.len()),
+        sharp: HashSet::new(),
+        linear_boundaries: true,
+    };
+
+    for face in &faces {
+        let mut pos_face = Vec::with_capacity(face.len());
+        let mut uv_face = Vec::with_capacity(face.len());
+
+        for &index in face.iter() {
+            let vert = &verts[index as usize];
+            let point = vert_points[index as usize];
+            let uv_key = (point, (vert.uv[0] + 0.0).to_bits(), (vert.uv[1] + 0.0).to_bits());
+
+            pos_face.push(*pos_lookup.entry(point).or_insert_with(|| {
+                positions.values.push(points[point]);
+                positions.values.len() as u32 - 1
+            }));
+
+            uv_face.push(*uv_lookup.entry(uv_key).or_insert_with(|| {
+                uvs.values.push(Vector3::new(vert.uv[0], vert.uv[1], 0.0));
+                uvs.values.len() as u32 - 1
+            }));
+        }
+
+        positions.faces.push(pos_face);
+        uvs.faces.push(uv_face);
+    }
+
+    // Edges between faces meeting at more than the crease angle stay sharp
+    let face_normals: Vec<_> = positions
+        .faces
+        .iter()
+        .map(|face| {
+            let points: Vec<_> = face.iter()
+                .map(|&index| positions.values[index as usize])
+                .collect();
+
+            triangulation::polygon_normal(&points).try_normalize(f32::EPSILON)
+        })
+        .collect();
+
+    let mut edge_faces: HashMap<Edge, Vec<usize>> = HashMap::new();
+
+    for (face_ind, face) in positions.faces.iter().enumerate() {
+        for corner in 0..face.len() {
+            edge_faces
+                .entry(edge_key(face[corner], face[(corner + 1) % face.len()]))
+                .or_default()
+                .push(face_ind);
+        }
+    }
+
+    let cos_crease = crease_angle.cos();
+
+    for (edge, adjacent) in edge_faces {
+        if let [left, right] = adjacent[..] {
+            if let (Some(left), Some(right)) = (face_normals[left], face_normals[right]) {
+                if left.dot(&right) < cos_crease {
+                    positions.sharp.insert(edge);
+                }
+            }
+        }
+    }
+
+    for _ in 0..levels {
+        positions = positions.subdivide(scheme);
+        uvs = uvs.subdivide(scheme);
+    }
+
+    finish(&positions, &uvs, crease_angle)
+}
+
+// Triangulates the subdivided faces and gives them normals, smoothed except
+// across creases
+fn finish(positions: &Level, uvs: &Level, crease_angle: f32) -> Mesh {
+    let mut pos_tris = Vec::new();
+    let mut uv_tris = Vec::new();
+
+    for (pos_face, uv_face) in positions.faces.iter().zip(&uvs.faces) {
+        let points: Vec<_> = pos_face
+            .iter()
+            .map(|&index| positions.values[index as usize])
+            .collect();
+
+        for (first, second, third) in triangulation::triangulate_polygon(&points) {
+            pos_tris.push((pos_face[first], pos_face[second], pos_face[third]));
+            uv_tris.push((uv_face[first], uv_face[second], uv_face[third]));
+        }
+    }
+
+    let groups = vec![1; pos_tris.len()];
+    let (normals, corner_normals) =
+        normals::smoothing_groups(&positions.values, &pos_tris, &groups, crease_angle);
+
+    let mut mesh = Mesh::new();
+    let mut lookup = HashMap::new();
+
+    let corners = pos_tris
+        .iter()
+        .zip(&uv_tris)
+        .flat_map(|(&(p0, p1, p2), &(t0, t1, t2))| vec![(p0, t0), (p1, t1), (p2, t2)]);
+
+    let mut indices = Vec::with_capacity(pos_tris.len() * 3);
+
+    for ((pos, uv), &normal) in corners.zip(&corner_normals) {
+        indices.push(*lookup.entry((uv, normal)).or_insert_with(|| {
+            let uv = uvs.values[uv as usize];
+
+            mesh.add_vertex(&Vertex {
+                pos: positions.values[pos as usize].into(),
+                normal: normals[normal as usize].into(),
+                uv: [uv.x, uv.y],
+                tangent: [0.0; 4],
+            });
+
+            mesh.vertices().len() as u32 - 1
+        }));
+    }
+
+    for tri in indices.chunks(3) {
+        mesh.add_tri((tri[0], tri[1], tri[2]));
+    }
+
+    mesh.generate_tangents();
+    mesh
+}
+
+impl PolygonMesh {
+    pub fn new() -> Self {
+        PolygonMesh::default()
+    }
+
+    // Catmull-Clark, every level turns each n-sided face into n quads. Edges
+    // whose faces meet at more than `crease_angle` (radians) are kept sharp,
+    // as are open boundaries and UV seams. Normals are recomputed
+    pub fn subdivide_catmull_clark(&self, levels: u32, crease_angle: f32) -> Mesh {
+        subdivide(&self.vertices, &self.faces, levels, crease_angle, Scheme::CatmullClark)
+    }
+
+    // The faces triangulated as they are, normals are kept
+    pub fn triangulate(&self) -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.add_verticies(&self.vertices);
+
+        for face in &self.faces {
+            let points: Vec<_> = face
+                .iter()
+                .map(|&index| Vector3::from(self.vertices[index as usize].pos))
+                .collect();
+
+            for (first, second, third) in triangulation::triangulate_polygon(&points) {
+                mesh.add_tri((face[first], face[second], face[third]));
+            }
+        }
+
+        mesh.generate_tangents();
+        mesh
+    }
+}
+
+impl Mesh {
+    // Loop subdivision, every level turns each triangle into four. Creases
+    // are kept as in PolygonMesh::subdivide_catmull_clark
+    pub fn subdivide_loop(&self, levels: u32, crease_angle: f32) -> Mesh {
+        subdivide(self.vertices(), &self.faces(), levels, crease_angle, Scheme::Loop)
+    }
+
+    // Catmull-Clark on the triangles, for input that's only available
+    // triangulated. After the first level every face is a quad
+    pub fn subdivide_catmull_clark(&self, levels: u32, crease_angle: f32) -> Mesh {
+        subdivide(self.vertices(), &self.faces(), levels, crease_angle, Scheme::CatmullClark)
+    }
+
+    fn faces(&self) -> Vec<Vec<u32>> {
+        self.indices().chunks(3).map(|tri| tri.to_vec()).collect()
+    }
+}
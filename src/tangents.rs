@@ -0,0 +1,122 @@
+use std::f32;
+
+use na::{Vector2, Vector3};
+
+use normals;
+use program::Vertex;
+
+// Face tangents come from the UV gradients, are weighted by corner angle and
+// made perpendicular to the vertex normal, and the bitangent is
+// tangent.w * cross(normal, tangent). Vertices shared by faces with mirrored
+// UVs are split, since they need tangents with opposite signs: the copies are
+// appended and the mirrored faces' indices moved to them. Returns the vertex
+// each copy was made from, in order
+pub fn generate(verts: &mut Vec<Vertex>, indices: &mut [u32]) -> Vec<u32> {
+    let count = verts.len();
+
+    // Tangent sums for unmirrored and mirrored faces around every vertex
+    let mut sums = vec![[Vector3::zeros(); 2]; count];
+    let mut used = vec![[false; 2]; count];
+    let mut corner_sides = Vec::with_capacity(indices.len());
+
+    for tri in indices.chunks(3) {
+        let pos = |corner: usize| Vector3::from(verts[tri[corner] as usize].pos);
+        let uv = |corner: usize| Vector2::from(verts[tri[corner] as usize].uv);
+
+        let (p0, p1, p2) = (pos(0), pos(1), pos(2));
+        let (edge1, edge2) = (p1 - p0, p2 - p0);
+        let (delta1, delta2) = (uv(1) - uv(0), uv(2) - uv(0));
+
+        let det = delta1.x * delta2.y - delta2.x * delta1.y;
+
+        // Triangles without a usable UV mapping don't contribute
+        if det.abs() <= f32::EPSILON {
+            corner_sides.extend_from_slice(&[0, 0, 0]);
+            continue;
+        }
+
+        let tangent = (edge1 * delta2.y - edge2 * delta1.y) / det;
+        let bitangent = (edge2 * delta1.x - edge1 * delta2.x) / det;
+        let face_normal = normals::face_normal(&p0, &p1, &p2);
+
+        let side = if face_normal.cross(&tangent).dot(&bitangent) < 0.0 {
+            1
+        } else {
+            0
+        };
+
+        let tangent = match tangent.try_normalize(f32::EPSILON) {
+            Some(tangent) => tangent,
+            None => {
+                corner_sides.extend_from_slice(&[0, 0, 0]);
+                continue;
+            }
+        };
+
+        let angles = [
+            normals::corner_angle(&p0, &p1, &p2),
+            normals::corner_angle(&p1, &p2, &p0),
+            normals::corner_angle(&p2, &p0, &p1),
+        ];
+
+        for (&index, angle) in tri.iter().zip(angles.iter()) {
+            sums[index as usize][side] += tangent * *angle;
+            used[index as usize][side] = true;
+            corner_sides.push(side);
+        }
+    }
+
+    // Copy of each vertex used by both mirrored and unmirrored faces
+    let mut mirrored_copies = vec![None; count];
+    let mut sources = Vec::new();
+
+    for index in 0..count {
+        match used[index] {
+            [true, true] => {
+                let mut copy = verts[index];
+                set_tangent(&mut copy, sums[index][1], -1.0);
+                verts.push(copy);
+
+                mirrored_copies[index] = Some(verts.len() as u32 - 1);
+                sources.push(index as u32);
+                set_tangent(&mut verts[index], sums[index][0], 1.0);
+            }
+            [false, true] => set_tangent(&mut verts[index], sums[index][1], -1.0),
+            _ => set_tangent(&mut verts[index], sums[index][0], 1.0),
+        }
+    }
+
+    for (index, &side) in indices.iter_mut().zip(corner_sides.iter()) {
+        if side == 1 {
+            if let Some(copy) = mirrored_copies[*index as usize] {
+                *index = copy;
+            }
+        }
+    }
+
+    sources
+}
+
+fn set_tangent(vert: &mut Vertex, sum: Vector3<f32>, sign: f32) {
+    let normal = Vector3::from(vert.normal);
+
+    // Gram-Schmidt, falling back to any direction along the surface
+    let tangent = (sum - normal * normal.dot(&sum))
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(|| perpendicular(&normal));
+
+    vert.tangent = [tangent.x, tangent.y, tangent.z, sign];
+}
+
+fn perpendicular(normal: &Vector3<f32>) -> Vector3<f32> {
+    let helper = if normal.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+
+    normal
+        .cross(&helper)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vector3::x)
+}
@@ -1,5 +1,3 @@
-use std::error::Error;
-
 use gfx::handle::ShaderResourceView;
 use gfx::Resources;
 use gfx::traits::FactoryExt;
@@ -7,6 +5,8 @@ use gfx::format::Rgba8;
 use gfx::texture as t;
 use image;
 
+use color::Color;
+
 pub type Texture<R> = ShaderResourceView<R, [f32; 4]>;
 
 pub fn load_texture<R: Resources, F: FactoryExt<R>>(
@@ -26,9 +26,42 @@ pub fn load_texture<R: Resources, F: FactoryExt<R>>(
 
             match result {
                 Ok((_, view)) => Ok(view),
-                Err(err) => Err(String::from(err.description())),
+                Err(err) => Err(err.to_string()),
             }
         }
-        Err(err) => Err(String::from(err.description())),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// 1x1 texture of a single color, for material slots without an image
+pub fn create_solid_texture<R: Resources, F: FactoryExt<R>>(
+    factory: &mut F,
+    color: Color,
+) -> Result<Texture<R>, String> {
+    let img = image::RgbaImage::from_pixel(1, 1, image::Rgba([color.r, color.g, color.b, color.a]));
+    let kind = t::Kind::D2(1, 1, t::AaMode::Single);
+
+    match factory.create_texture_immutable_u8::<Rgba8>(kind, &[&img]) {
+        Ok((_, view)) => Ok(view),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// Texture from tightly packed 8 bit RGBA pixels
+pub fn create_texture_rgba<R: Resources, F: FactoryExt<R>>(
+    factory: &mut F,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+) -> Result<Texture<R>, String> {
+    let img = match image::RgbaImage::from_raw(width, height, pixels) {
+        Some(img) => img,
+        None => return Err(String::from("pixel data does not match the image size")),
+    };
+    let kind = t::Kind::D2(width as t::Size, height as t::Size, t::AaMode::Single);
+
+    match factory.create_texture_immutable_u8::<Rgba8>(kind, &[&img]) {
+        Ok((_, view)) => Ok(view),
+        Err(err) => Err(err.to_string()),
     }
 }
@@ -0,0 +1,142 @@
+use std::f32;
+
+use na::{Vector2, Vector3};
+
+// Newell's method, gives a usable normal for concave and non-planar polygons
+pub(crate) fn polygon_normal(points: &[Vector3<f32>]) -> Vector3<f32> {
+    let mut normal = Vector3::zeros();
+
+    for (i, curr) in points.iter().enumerate() {
+        let next = points[(i + 1) % points.len()];
+
+        normal.x += (curr.y - next.y) * (curr.z + next.z);
+        normal.y += (curr.z - next.z) * (curr.x + next.x);
+        normal.z += (curr.x - next.x) * (curr.y + next.y);
+    }
+
+    normal
+}
+
+// Projects the polygon onto the plane perpendicular to its normal, so that
+// its winding is counter-clockwise in 2D
+fn project_to_plane(points: &[Vector3<f32>], normal: &Vector3<f32>) -> Vec<Vector2<f32>> {
+    let normal = normal.normalize();
+
+    let helper = if normal.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+
+    let u_axis = helper.cross(&normal).normalize();
+    let v_axis = normal.cross(&u_axis);
+
+    points
+        .iter()
+        .map(|point| Vector2::new(point.dot(&u_axis), point.dot(&v_axis)))
+        .collect()
+}
+
+fn cross_2d(a: &Vector2<f32>, b: &Vector2<f32>, c: &Vector2<f32>) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn is_convex(points: &[Vector2<f32>]) -> bool {
+    let count = points.len();
+
+    (0..count).all(|i| {
+        cross_2d(
+            &points[i],
+            &points[(i + 1) % count],
+            &points[(i + 2) % count],
+        ) >= 0.0
+    })
+}
+
+fn in_triangle(point: &Vector2<f32>, a: &Vector2<f32>, b: &Vector2<f32>, c: &Vector2<f32>) -> bool {
+    cross_2d(a, b, point) >= 0.0 && cross_2d(b, c, point) >= 0.0 && cross_2d(c, a, point) >= 0.0
+}
+
+fn fan(indices: &[usize], tris: &mut Vec<(usize, usize, usize)>) {
+    for i in 1..indices.len() - 1 {
+        tris.push((indices[0], indices[i], indices[i + 1]));
+    }
+}
+
+fn ear_clip(points: &[Vector2<f32>]) -> Vec<(usize, usize, usize)> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut tris = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+
+        let ear = (0..count).find(|&i| {
+            let prev = remaining[(i + count - 1) % count];
+            let curr = remaining[i];
+            let next = remaining[(i + 1) % count];
+
+            let (a, b, c) = (&points[prev], &points[curr], &points[next]);
+
+            // Reflex or degenerate corners can't be ears
+            if cross_2d(a, b, c) <= 0.0 {
+                return false;
+            }
+
+            remaining
+                .iter()
+                .filter(|&&other| other != prev && other != curr && other != next)
+                .all(|&other| !in_triangle(&points[other], a, b, c))
+        });
+
+        match ear {
+            Some(i) => {
+                let prev = remaining[(i + count - 1) % count];
+                let next = remaining[(i + 1) % count];
+
+                tris.push((prev, remaining[i], next));
+                remaining.remove(i);
+            }
+            // Self intersecting or otherwise broken polygon, fan what is left
+            None => break,
+        }
+    }
+
+    fan(&remaining, &mut tris);
+
+    tris
+}
+
+// Splits a polygon into triangles, returning indices into `points` with the
+// same winding as the polygon. Convex polygons are fanned, concave ones are
+// ear clipped, non-planar ones are projected onto their best fit plane first
+pub fn triangulate_polygon(points: &[Vector3<f32>]) -> Vec<(usize, usize, usize)> {
+    let mut tris = Vec::new();
+
+    if points.len() < 3 {
+        return tris;
+    }
+
+    let indices: Vec<usize> = (0..points.len()).collect();
+
+    if points.len() == 3 {
+        fan(&indices, &mut tris);
+        return tris;
+    }
+
+    let normal = polygon_normal(points);
+
+    // Degenerate polygons have no plane to project onto
+    if normal.norm() <= f32::EPSILON {
+        fan(&indices, &mut tris);
+        return tris;
+    }
+
+    let projected = project_to_plane(points, &normal);
+
+    if is_convex(&projected) {
+        fan(&indices, &mut tris);
+        tris
+    } else {
+        ear_clip(&projected)
+    }
+}
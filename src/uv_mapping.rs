@@ -0,0 +1,68 @@
+use std::f32;
+use std::f32::consts::PI;
+
+use na::{Vector2, Vector3};
+
+// Projections from a position and normal to a UV, picked for loading with
+// mesh_loader::UVSource. Planar and box projections tile the texture once per
+// unit, cylindrical and spherical ones wrap u around the Y axis and need
+// fix_seam on every triangle
+
+pub fn planar_x(pos: &Vector3<f32>, _: &Vector3<f32>) -> Vector2<f32> {
+    Vector2::new(pos.z, pos.y)
+}
+
+pub fn planar_y(pos: &Vector3<f32>, _: &Vector3<f32>) -> Vector2<f32> {
+    Vector2::new(pos.x, pos.z)
+}
+
+pub fn planar_z(pos: &Vector3<f32>, _: &Vector3<f32>) -> Vector2<f32> {
+    Vector2::new(pos.x, pos.y)
+}
+
+fn around_y(pos: &Vector3<f32>) -> f32 {
+    f32::atan2(pos.x, pos.z) / (2.0 * PI) + 0.5
+}
+
+pub fn cylindrical(pos: &Vector3<f32>, _: &Vector3<f32>) -> Vector2<f32> {
+    Vector2::new(around_y(pos), pos.y)
+}
+
+pub fn spherical(pos: &Vector3<f32>, _: &Vector3<f32>) -> Vector2<f32> {
+    let len = pos.norm();
+
+    let v = if len > f32::EPSILON {
+        f32::asin((pos.y / len).clamp(-1.0, 1.0)) / PI + 0.5
+    } else {
+        0.5
+    };
+
+    Vector2::new(around_y(pos), v)
+}
+
+// Planar projection along whichever axis the normal points furthest along,
+// flipped on the negative sides so textures aren't mirrored
+pub fn cube(pos: &Vector3<f32>, normal: &Vector3<f32>) -> Vector2<f32> {
+    let (x, y, z) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+
+    if x >= y && x >= z {
+        Vector2::new(-pos.z * normal.x.signum(), pos.y)
+    } else if y >= z {
+        Vector2::new(pos.x, -pos.z * normal.y.signum())
+    } else {
+        Vector2::new(pos.x * normal.z.signum(), pos.y)
+    }
+}
+
+// Shifts the low side of a triangle that straddles the u = 0/1 seam up by
+// one, so it interpolates across the seam instead of through the whole texture
+pub fn fix_seam(uvs: &mut [Vector2<f32>; 3]) {
+    let min = uvs.iter().fold(f32::INFINITY, |acc, uv| f32::min(acc, uv.x));
+    let max = uvs.iter().fold(f32::NEG_INFINITY, |acc, uv| f32::max(acc, uv.x));
+
+    if max - min > 0.5 {
+        for uv in uvs.iter_mut().filter(|uv| uv.x < 0.5) {
+            uv.x += 1.0;
+        }
+    }
+}
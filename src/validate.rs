@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::f32;
+use std::fmt;
+
+use na::Vector3;
+
+use mesh::{match_positions, Mesh};
+use normals;
+
+// How far a normal's length can be from 1 before it's reported
+const NORMAL_TOLERANCE: f32 = 1e-3;
+
+// Counts of each problem found, along with some statistics. Edges and
+// connectivity are found from positions, see mesh::match_positions, so
+// vertices split for UV or normal seams don't count as boundaries
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    pub out_of_range_indices: usize,
+    pub degenerate_triangles: usize,
+    // Vertices with a NaN or infinite coordinate
+    pub invalid_positions: usize,
+    pub non_unit_normals: usize,
+    // Edges shared by more than two triangles
+    pub non_manifold_edges: usize,
+    // Edges used by only one triangle, which a closed mesh has none of
+    pub boundary_edges: usize,
+    pub connected_components: usize,
+    pub surface_area: f32,
+    // Signed, only meaningful when the mesh is closed
+    pub volume: f32,
+}
+
+impl ValidationReport {
+    // Whether the mesh has none of the problems, boundaries are allowed
+    pub fn is_valid(&self) -> bool {
+        self.out_of_range_indices == 0 && self.degenerate_triangles == 0
+            && self.invalid_positions == 0 && self.non_unit_normals == 0
+            && self.non_manifold_edges == 0
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.boundary_edges == 0 && self.non_manifold_edges == 0
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "vertices:             {}", self.vertex_count)?;
+        writeln!(f, "triangles:            {}", self.triangle_count)?;
+        writeln!(f, "out of range indices: {}", self.out_of_range_indices)?;
+        writeln!(f, "degenerate triangles: {}", self.degenerate_triangles)?;
+        writeln!(f, "NaN/inf positions:    {}", self.invalid_positions)?;
+        writeln!(f, "non-unit normals:     {}", self.non_unit_normals)?;
+        writeln!(f, "non-manifold edges:   {}", self.non_manifold_edges)?;
+        writeln!(f, "boundary edges:       {}", self.boundary_edges)?;
+        writeln!(f, "components:           {}", self.connected_components)?;
+        writeln!(f, "surface area:         {}", self.surface_area)?;
+
+        if self.is_closed() {
+            write!(f, "volume:               {}", self.volume)
+        } else {
+            write!(f, "volume:               {} (not closed)", self.volume)
+        }
+    }
+}
+
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+
+    index
+}
+
+impl Mesh {
+    pub fn validate(&self) -> ValidationReport {
+        let verts = self.vertices();
+
+        let invalid_positions = verts
+            .iter()
+            .filter(|vert| vert.pos.iter().any(|value| !value.is_finite()))
+            .count();
+
+        let non_unit_normals = verts
+            .iter()
+            .filter(|vert| {
+                let len = Vector3::from(vert.normal).norm();
+                !len.is_finite() || (len - 1.0).abs() > NORMAL_TOLERANCE
+            })
+            .count();
+
+        // Vertices at the same position share an index here
+        let (positions, points) = match_positions(verts);
+        let position_count = points.len();
+
+        let mut parents: Vec<usize> = (0..position_count).collect();
+        let mut used = vec![false; position_count];
+        let mut edges = HashMap::new();
+
+        let mut out_of_range_indices = 0;
+        let mut degenerate_triangles = 0;
+        let mut surface_area = 0.0;
+        let mut volume = 0.0;
+
+        for tri in self.indices().chunks(3) {
+            let out_of_range = tri.iter()
+                .filter(|&&index| index as usize >= verts.len())
+                .count();
+
+            if out_of_range > 0 {
+                out_of_range_indices += out_of_range;
+                continue;
+            }
+
+            let pos = |corner: usize| Vector3::from(verts[tri[corner] as usize].pos);
+            let (a, b, c) = (pos(0), pos(1), pos(2));
+            let tri_positions = [
+                positions[tri[0] as usize],
+                positions[tri[1] as usize],
+                positions[tri[2] as usize],
+            ];
+
+            if tri_positions[0] == tri_positions[1] || tri_positions[1] == tri_positions[2]
+                || tri_positions[2] == tri_positions[0]
+                || normals::is_degenerate(&a, &b, &c)
+            {
+                degenerate_triangles += 1;
+            }
+
+            surface_area += normals::face_normal(&a, &b, &c).norm() / 2.0;
+            volume += a.dot(&b.cross(&c)) / 6.0;
+
+            for corner in 0..3 {
+                let (first, second) = (tri_positions[corner], tri_positions[(corner + 1) % 3]);
+
+                used[first] = true;
+
+                if first != second {
+                    *edges
+                        .entry((usize::min(first, second), usize::max(first, second)))
+                        .or_insert(0) += 1;
+
+                    let (first, second) = (find(&mut parents, first), find(&mut parents, second));
+                    parents[first] = second;
+                }
+            }
+        }
+
+        let connected_components = (0..parents.len())
+            .filter(|&pos| used[pos] && find(&mut parents, pos) == pos)
+            .count();
+
+        ValidationReport {
+            vertex_count: verts.len(),
+            triangle_count: self.indices().len() / 3,
+            out_of_range_indices,
+            degenerate_triangles,
+            invalid_positions,
+            non_unit_normals,
+            non_manifold_edges: edges.values().filter(|&&count| count > 2).count(),
+            boundary_edges: edges.values().filter(|&&count| count == 1).count(),
+            connected_components,
+            surface_area,
+            volume,
+        }
+    }
+}
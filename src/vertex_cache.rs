@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+use program::Vertex;
+
+// Size of the post-transform cache on most current hardware
+pub const DEFAULT_CACHE_SIZE: usize = 16;
+
+// Average cache miss ratio, the number of vertices transformed per triangle
+// with a FIFO cache of `cache_size` entries. Ranges from about 0.5 for an
+// ideal order of a large mesh up to 3
+pub fn acmr(indices: &[u32], cache_size: usize) -> f32 {
+    if indices.len() < 3 {
+        return 0.0;
+    }
+
+    let mut cache = VecDeque::with_capacity(cache_size);
+    let mut misses = 0;
+
+    for index in indices {
+        if cache.contains(index) {
+            continue;
+        }
+
+        misses += 1;
+
+        if cache.len() == cache_size {
+            cache.pop_front();
+        }
+
+        cache.push_back(*index);
+    }
+
+    misses as f32 / (indices.len() / 3) as f32
+}
+
+// Reorders triangles with Tipsify (Sander, Nehab and Barczak, 2007): fans
+// out around one vertex at a time, moving on to the neighbour most likely to
+// still be cached. Each triangle keeps its winding
+pub fn optimize(indices: &mut [u32], vertex_count: usize, cache_size: usize) {
+    let tri_count = indices.len() / 3;
+
+    // Triangles using each vertex, and how many of those are left
+    let mut adjacent = vec![Vec::new(); vertex_count];
+
+    for (tri_ind, tri) in indices.chunks(3).enumerate() {
+        for &index in tri {
+            adjacent[index as usize].push(tri_ind);
+        }
+    }
+
+    let mut live: Vec<usize> = adjacent.iter().map(|tris| tris.len()).collect();
+    let mut cache_time = vec![0; vertex_count];
+    let mut emitted = vec![false; tri_count];
+    let mut dead_ends = Vec::new();
+    let mut output = Vec::with_capacity(indices.len());
+
+    let mut time = cache_size + 1;
+    let mut cursor = 0;
+    let mut fanning = if vertex_count > 0 { Some(0) } else { None };
+
+    while let Some(vert) = fanning {
+        let mut candidates = Vec::new();
+
+        for &tri in &adjacent[vert] {
+            if emitted[tri] {
+                continue;
+            }
+
+            for &index in &indices[tri * 3..tri * 3 + 3] {
+                let index = index as usize;
+
+                output.push(index as u32);
+                dead_ends.push(index);
+                candidates.push(index);
+                live[index] -= 1;
+
+                if time - cache_time[index] > cache_size {
+                    cache_time[index] = time;
+                    time += 1;
+                }
+            }
+
+            emitted[tri] = true;
+        }
+
+        // Prefer the candidate that entered the cache earliest and will still
+        // be in it after its remaining triangles are emitted, if none will be
+        // it's treated as a dead end
+        let mut best = None;
+        let mut best_priority = 0;
+
+        for &candidate in &candidates {
+            if live[candidate] == 0 {
+                continue;
+            }
+
+            let age = time - cache_time[candidate];
+            let priority = if age + 2 * live[candidate] <= cache_size {
+                age
+            } else {
+                0
+            };
+
+            if priority > best_priority {
+                best = Some(candidate);
+                best_priority = priority;
+            }
+        }
+
+        fanning = best.or_else(|| {
+            // Dead end, go back to a recent vertex or else the next one in
+            // input order that still has triangles
+            while let Some(index) = dead_ends.pop() {
+                if live[index] > 0 {
+                    return Some(index);
+                }
+            }
+
+            while cursor < vertex_count {
+                if live[cursor] > 0 {
+                    return Some(cursor);
+                }
+
+                cursor += 1;
+            }
+
+            None
+        });
+    }
+
+    indices[..output.len()].copy_from_slice(&output);
+}
+
+// Reorders vertices by their first use in `indices` so they're fetched
+// roughly in sequence, vertices that aren't used go at the end
+pub fn optimize_fetch(verts: &mut Vec<Vertex>, indices: &mut [u32]) {
+    let mut remap = vec![None; verts.len()];
+    let mut order = Vec::with_capacity(verts.len());
+
+    for index in indices.iter_mut() {
+        *index = *remap[*index as usize].get_or_insert_with(|| {
+            order.push(*index as usize);
+            order.len() as u32 - 1
+        });
+    }
+
+    for (index, new_index) in remap.iter().enumerate() {
+        if new_index.is_none() {
+            order.push(index);
+        }
+    }
+
+    *verts = order.iter().map(|&index| verts[index]).collect();
+}
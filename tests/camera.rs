@@ -0,0 +1,85 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use std::f32::consts::{FRAC_PI_3, PI};
+
+use na::{Point3, Vector3};
+
+use rgraphics::camera::{self, Camera};
+
+fn cameras() -> Vec<Camera> {
+    let mut perspective = Camera::perspective(FRAC_PI_3, 800, 600, 0.5, 50.0);
+    let mut orthographic = Camera::orthographic(10.0, 800, 600, 0.5, 50.0);
+
+    for camera in [&mut perspective, &mut orthographic].iter_mut() {
+        camera.position = Point3::new(1.0, 2.0, 3.0);
+        camera.look_at(&Point3::new(-4.0, 0.0, -6.0));
+    }
+
+    vec![perspective, orthographic]
+}
+
+fn assert_close(actual: &Vector3<f32>, expected: &Vector3<f32>) {
+    assert!(
+        (actual - expected).norm() < 1e-4,
+        "{:?} != {:?}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn center_ray_is_the_view_direction() {
+    for camera in cameras() {
+        let (width, height) = camera.viewport();
+        let ray = camera.screen_ray(width as f32 / 2.0, height as f32 / 2.0);
+
+        assert_close(&ray.direction, &camera.direction());
+
+        // Starting on the near plane, straight ahead of the camera
+        let expected = camera.position + camera.direction() * camera.near;
+        assert_close(&ray.origin.coords, &expected.coords);
+    }
+}
+
+#[test]
+fn frustum_contains_the_view_axis() {
+    for camera in cameras() {
+        let frustum = camera.frustum();
+        let along = |distance: f32| camera.position + camera.direction() * distance;
+
+        for &distance in &[0.6, 1.0, 10.0, 49.0] {
+            assert!(frustum.contains_point(&along(distance)), "{} inside", distance);
+        }
+
+        // In front of the near plane, past the far plane and behind
+        for &distance in &[0.4, 51.0, -1.0] {
+            assert!(!frustum.contains_point(&along(distance)), "{} outside", distance);
+        }
+
+        // Far off to the side, with the up vector perpendicular to the view
+        let side = camera.direction().cross(&camera.up).normalize();
+        assert!(!frustum.contains_point(&(along(10.0) + side * 100.0)));
+        assert!(!frustum.contains_point(&(along(10.0) - side * 100.0)));
+    }
+}
+
+#[test]
+fn yaw_pitch_round_trip() {
+    for &yaw in &[0.0, 0.5, -1.2, 2.5, -3.0] {
+        for &pitch in &[0.0, 0.3, -0.7, 1.4, -1.4] {
+            let direction = camera::yaw_pitch_direction(yaw, pitch);
+            assert!((direction.norm() - 1.0).abs() < 1e-5);
+
+            let (yaw_back, pitch_back) = camera::direction_yaw_pitch(&direction);
+            assert!((yaw_back - yaw).abs() < 1e-4, "yaw {} != {}", yaw_back, yaw);
+            assert!((pitch_back - pitch).abs() < 1e-4, "pitch {} != {}", pitch_back, pitch);
+        }
+    }
+
+    // Yaw 0 looks down -z, and comes back in -π..π
+    assert_close(&camera::yaw_pitch_direction(0.0, 0.0), &-Vector3::z());
+
+    let (yaw, _) = camera::direction_yaw_pitch(&camera::yaw_pitch_direction(PI * 1.5, 0.0));
+    assert!((yaw + PI * 0.5).abs() < 1e-4);
+}
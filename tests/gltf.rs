@@ -0,0 +1,89 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use na::{Point3, Vector3};
+
+use rgraphics::gltf_loader;
+use rgraphics::mesh_loader::MeshLoadError;
+
+// One mesh with two primitives: a square drawn as a strip with UVs but no
+// normals or tangents, and one drawn as a fan with both. The mesh hangs off a
+// chain of three nodes
+const STRIP_AND_FAN: &str = "tests/data/strip_and_fan.gltf";
+
+// The same, but the fan has fewer normals than positions
+const SHORT_NORMALS: &str = "tests/data/short_normals.gltf";
+
+#[test]
+fn strips_and_fans_become_triangles() {
+    let scene = gltf_loader::load_file(STRIP_AND_FAN).unwrap();
+
+    assert_eq!(scene.meshes.len(), 1);
+
+    let model = &scene.meshes[0];
+    let ranges: Vec<_> = model.submeshes.iter().map(|sub| (sub.start, sub.count)).collect();
+
+    // Two strip triangles, then four fan triangles
+    assert_eq!(ranges, vec![(0, 6), (6, 12)]);
+    assert_eq!(model.mesh.vertices().len(), 10);
+    assert_eq!(&model.mesh.indices()[..6], &[0, 1, 2, 2, 1, 3]);
+    assert_eq!(&model.mesh.indices()[6..9], &[4, 5, 6]);
+
+    // Every triangle faces +z like the squares do
+    let verts = model.mesh.vertices();
+    for tri in model.mesh.indices().chunks(3) {
+        let pos = |corner: usize| Vector3::from(verts[tri[corner] as usize].pos);
+        let normal = (pos(1) - pos(0)).cross(&(pos(2) - pos(0)));
+
+        assert!(normal.z > 0.0, "{:?} faces away", tri);
+    }
+}
+
+#[test]
+fn missing_attributes_are_generated_per_primitive() {
+    let scene = gltf_loader::load_file(STRIP_AND_FAN).unwrap();
+    let verts = scene.meshes[0].mesh.vertices();
+
+    // The strip gets normals and tangents along u
+    for vert in &verts[..4] {
+        assert!((Vector3::from(vert.normal) - Vector3::z()).norm() < 1e-6);
+        assert_eq!(vert.tangent, [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    // While the fan keeps the tangents it came with
+    for vert in &verts[4..] {
+        assert_eq!(vert.normal, [0.0, 0.0, 1.0]);
+        assert_eq!(vert.tangent, [0.0, 1.0, 0.0, -1.0]);
+        assert_eq!(vert.uv, [0.0, 0.0]);
+    }
+}
+
+#[test]
+fn node_transforms_are_chained() {
+    let scene = gltf_loader::load_file(STRIP_AND_FAN).unwrap();
+
+    let names: Vec<_> = scene.nodes.iter().map(|node| node.name.as_str()).collect();
+    assert_eq!(names, vec!["root", "child", "leaf"]);
+    assert_eq!(scene.roots, vec![0]);
+    assert_eq!(scene.nodes[2].mesh, Some(0));
+
+    // Translated by 1 along x, scaled by 2, then translated by 3 along z
+    let world = scene.world_transforms();
+    let origin = |node: usize| world[node].transform_point(&Point3::origin());
+
+    assert_eq!(origin(0), Point3::new(1.0, 0.0, 0.0));
+    assert_eq!(origin(1), Point3::new(1.0, 0.0, 0.0));
+    assert_eq!(origin(2), Point3::new(1.0, 0.0, 6.0));
+    assert_eq!(world[2].transform_point(&Point3::new(1.0, 1.0, 1.0)), Point3::new(3.0, 2.0, 8.0));
+}
+
+#[test]
+fn short_attributes_are_format_errors() {
+    match gltf_loader::load_file(SHORT_NORMALS) {
+        Err(MeshLoadError::Format { ref error, .. }) => {
+            assert!(error.contains("3 NORMAL values for 6 positions"), "{}", error)
+        }
+        Err(error) => panic!("unexpected error {}", error),
+        Ok(_) => panic!("loaded without an error"),
+    }
+}
@@ -0,0 +1,180 @@
+extern crate rgraphics;
+
+use std::fs;
+
+use rgraphics::mesh_loader::{self, LoadOptions, NormalSource, Recenter, UpAxis};
+use rgraphics::program::Vertex;
+
+// One triangle facing +z, spanning 1 to 3 in x and 2 to 6 in y
+const TRIANGLE: &str = "v 1 2 3
+v 3 2 3
+v 1 6 3
+vt 0 0.25
+vt 1 0.25
+vt 0 1
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1
+";
+
+fn write_obj(name: &str) -> String {
+    fs::create_dir_all("target/load_options").unwrap();
+
+    let file = format!("target/load_options/{}", name);
+    fs::write(&file, TRIANGLE).unwrap();
+    file
+}
+
+// Every triangle corner in order
+fn corners(name: &str, options: &LoadOptions) -> Vec<Vertex> {
+    let mesh = mesh_loader::load_file_with(&write_obj(name), options).unwrap();
+
+    mesh.indices().iter().map(|&index| mesh.vertices()[index as usize]).collect()
+}
+
+fn positions(name: &str, options: &LoadOptions) -> Vec<[f32; 3]> {
+    corners(name, options).iter().map(|vert| vert.pos).collect()
+}
+
+fn assert_close(actual: &[[f32; 3]], expected: &[[f32; 3]]) {
+    assert_eq!(actual.len(), expected.len());
+
+    for (actual, expected) in actual.iter().zip(expected) {
+        let close = actual.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-5);
+        assert!(close, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn default_loads_the_file_as_is() {
+    let corners = corners("as_is.obj", &LoadOptions::default());
+    let positions: Vec<_> = corners.iter().map(|vert| vert.pos).collect();
+    let uvs: Vec<_> = corners.iter().map(|vert| vert.uv).collect();
+
+    assert_eq!(positions, vec![[1.0, 2.0, 3.0], [3.0, 2.0, 3.0], [1.0, 6.0, 3.0]]);
+    assert_eq!(uvs, vec![[0.0, 0.25], [1.0, 0.25], [0.0, 1.0]]);
+}
+
+#[test]
+fn rescale_fits_the_largest_side() {
+    let options = LoadOptions {
+        rescale: true,
+        ..LoadOptions::default()
+    };
+
+    // The y extent of 4 becomes 1
+    assert_close(
+        &positions("rescaled.obj", &options),
+        &[[0.25, 0.5, 0.75], [0.75, 0.5, 0.75], [0.25, 1.5, 0.75]],
+    );
+}
+
+#[test]
+fn recenter_moves_the_center_to_the_origin() {
+    let bounding_box = LoadOptions {
+        recenter: Recenter::BoundingBox,
+        ..LoadOptions::default()
+    };
+
+    assert_close(
+        &positions("bounding_box.obj", &bounding_box),
+        &[[-1.0, -2.0, 0.0], [1.0, -2.0, 0.0], [-1.0, 2.0, 0.0]],
+    );
+
+    // The centroid is a third of the way up, at (5/3, 10/3, 3)
+    let centroid = LoadOptions {
+        recenter: Recenter::Centroid,
+        ..LoadOptions::default()
+    };
+
+    assert_close(
+        &positions("centroid.obj", &centroid),
+        &[
+            [-2.0 / 3.0, -4.0 / 3.0, 0.0],
+            [4.0 / 3.0, -4.0 / 3.0, 0.0],
+            [-2.0 / 3.0, 8.0 / 3.0, 0.0],
+        ],
+    );
+
+    // Scaled first, then centered
+    let both = LoadOptions {
+        rescale: true,
+        recenter: Recenter::BoundingBox,
+        ..LoadOptions::default()
+    };
+
+    assert_close(
+        &positions("rescaled_centered.obj", &both),
+        &[[-0.25, -0.5, 0.0], [0.25, -0.5, 0.0], [-0.25, 0.5, 0.0]],
+    );
+}
+
+#[test]
+fn z_up_is_rotated_to_y_up() {
+    let options = LoadOptions {
+        up_axis: UpAxis::Z,
+        ..LoadOptions::default()
+    };
+    let corners = corners("z_up.obj", &options);
+    let positions: Vec<_> = corners.iter().map(|vert| vert.pos).collect();
+
+    // The file's z becomes y and its y points into the screen
+    assert_eq!(positions, vec![[1.0, 3.0, -2.0], [3.0, 3.0, -2.0], [1.0, 3.0, -6.0]]);
+    assert!(corners.iter().all(|vert| vert.normal == [0.0, 1.0, 0.0]));
+}
+
+#[test]
+fn flip_winding_turns_faces_inside_out() {
+    let options = LoadOptions {
+        flip_winding: true,
+        ..LoadOptions::default()
+    };
+    let flipped = corners("flipped.obj", &options);
+    let positions: Vec<_> = flipped.iter().map(|vert| vert.pos).collect();
+
+    assert_eq!(positions, vec![[1.0, 2.0, 3.0], [1.0, 6.0, 3.0], [3.0, 2.0, 3.0]]);
+
+    // The file's normals are negated to match the new winding
+    assert!(flipped.iter().all(|vert| vert.normal == [0.0, 0.0, -1.0]));
+
+    // As are generated ones, which follow the winding
+    let generated = LoadOptions {
+        normal_source: Some(NormalSource::Flat),
+        ..options
+    };
+    let generated = corners("flipped_generated.obj", &generated);
+
+    assert!(generated.iter().all(|vert| vert.normal == [0.0, 0.0, -1.0]));
+}
+
+#[test]
+fn flip_v_mirrors_the_uvs() {
+    let options = LoadOptions {
+        flip_v: true,
+        ..LoadOptions::default()
+    };
+    let uvs: Vec<_> = corners("flipped_v.obj", &options).iter().map(|vert| vert.uv).collect();
+
+    assert_eq!(uvs, vec![[0.0, 0.75], [1.0, 0.75], [0.0, 0.0]]);
+}
+
+#[test]
+fn polygons_get_the_same_options() {
+    let options = LoadOptions {
+        up_axis: UpAxis::Z,
+        flip_winding: true,
+        flip_v: true,
+        ..LoadOptions::default()
+    };
+    let polygons = mesh_loader::load_polygons_with(&write_obj("polygons.obj"), &options).unwrap();
+
+    assert_eq!(polygons.faces, vec![vec![2, 1, 0]]);
+
+    let corners: Vec<_> = polygons.faces[0]
+        .iter()
+        .map(|&index| polygons.vertices[index as usize])
+        .collect();
+
+    assert_eq!(corners[0].pos, [1.0, 3.0, -6.0]);
+    assert_eq!(corners[0].uv, [0.0, 0.0]);
+    assert!(corners.iter().all(|vert| vert.normal == [0.0, -1.0, 0.0]));
+}
@@ -0,0 +1,237 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use std::f32::consts::PI;
+
+use na::{Matrix4, Vector3};
+
+use rgraphics::mesh::Mesh;
+use rgraphics::program::Vertex;
+
+fn vertex(pos: [f32; 3], uv: [f32; 2]) -> Vertex {
+    Vertex {
+        pos,
+        normal: [0.0, 0.0, 1.0],
+        uv,
+        tangent: [0.0; 4],
+    }
+}
+
+fn face_normal(mesh: &Mesh, tri: &[u32]) -> Vector3<f32> {
+    let pos = |corner: usize| Vector3::from(mesh.vertices()[tri[corner] as usize].pos);
+    (pos(1) - pos(0)).cross(&(pos(2) - pos(0))).normalize()
+}
+
+fn assert_indices_valid(mesh: &Mesh) {
+    assert_eq!(mesh.indices().len() % 3, 0);
+
+    for &index in mesh.indices() {
+        assert!((index as usize) < mesh.vertices().len(), "index {} out of range", index);
+    }
+}
+
+// Two triangles of a unit square with their own copies of the shared corners,
+// the second's copies `offset` along x and given `uv_offset` in u
+fn split_square(offset: f32, uv_offset: f32) -> Mesh {
+    let mut mesh = Mesh::new();
+    mesh.add_verticies(&[
+        vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+        vertex([1.0, 0.0, 0.0], [1.0, 0.0]),
+        vertex([1.0, 1.0, 0.0], [1.0, 1.0]),
+        vertex([offset, 0.0, 0.0], [uv_offset, 0.0]),
+        vertex([1.0 + offset, 1.0, 0.0], [1.0 + uv_offset, 1.0]),
+        vertex([0.0, 1.0, 0.0], [0.0, 1.0]),
+    ]).add_tris(&[(0, 1, 2), (3, 4, 5)]);
+    mesh
+}
+
+// Cube on eight shared corners, so every normal is averaged over three faces
+fn smooth_cube() -> Mesh {
+    let mut mesh = Mesh::new();
+
+    for index in 0..8 {
+        let pos = Vector3::new(
+            if index & 1 == 0 { -1.0 } else { 1.0 },
+            if index & 2 == 0 { -1.0 } else { 1.0 },
+            if index & 4 == 0 { -1.0 } else { 1.0 },
+        );
+
+        mesh.add_vertex(&Vertex {
+            pos: pos.into(),
+            normal: pos.normalize().into(),
+            uv: [0.0, 0.0],
+            tangent: [0.0; 4],
+        });
+    }
+
+    let quads = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ];
+
+    for quad in &quads {
+        mesh.add_tris(&[(quad[0], quad[1], quad[2]), (quad[0], quad[2], quad[3])]);
+    }
+
+    mesh
+}
+
+#[test]
+fn weld_merges_within_epsilon() {
+    let mut mesh = split_square(1e-5, 0.0);
+    mesh.weld(1e-4);
+
+    assert_eq!(mesh.vertices().len(), 4);
+    assert_eq!(mesh.indices(), &[0, 1, 2, 0, 2, 3]);
+
+    // Further apart than epsilon
+    let mut mesh = split_square(1e-3, 0.0);
+    mesh.weld(1e-4);
+
+    assert_eq!(mesh.vertices().len(), 6);
+    assert_indices_valid(&mesh);
+}
+
+#[test]
+fn weld_keeps_uv_seams() {
+    let mut mesh = split_square(0.0, 0.5);
+    mesh.weld(1e-4);
+
+    assert_eq!(mesh.vertices().len(), 6);
+    assert_indices_valid(&mesh);
+
+    // Every face of a cube is its own UV island
+    let mut cube = Mesh::cube(2.0);
+    cube.weld(1e-4);
+
+    assert_eq!(cube.vertices().len(), 24);
+}
+
+#[test]
+fn removals_keep_indices_valid() {
+    let mut mesh = Mesh::new();
+    mesh.add_verticies(&[
+        vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+        vertex([9.0, 9.0, 9.0], [0.0, 0.0]),
+        vertex([1.0, 0.0, 0.0], [0.0, 0.0]),
+        vertex([2.0, 0.0, 0.0], [0.0, 0.0]),
+        vertex([0.0, 1.0, 0.0], [0.0, 0.0]),
+    ]).add_tris(&[
+        (0, 2, 4),
+        // Repeats, one rotated
+        (2, 4, 0),
+        (0, 2, 4),
+        // Opposite winding, kept
+        (0, 4, 2),
+        // Collinear and a repeated index
+        (0, 2, 3),
+        (2, 2, 4),
+    ]);
+
+    mesh.remove_degenerate_tris();
+    assert_eq!(mesh.indices(), &[0, 2, 4, 2, 4, 0, 0, 2, 4, 0, 4, 2]);
+
+    mesh.remove_duplicate_tris();
+    assert_eq!(mesh.indices(), &[0, 2, 4, 0, 4, 2]);
+
+    // Vertices 1 and 3 are no longer used, the rest keep their order
+    mesh.remove_unreferenced_vertices();
+    assert_eq!(mesh.vertices().len(), 3);
+    assert_eq!(mesh.indices(), &[0, 1, 2, 0, 2, 1]);
+    assert_indices_valid(&mesh);
+
+    let positions: Vec<_> = mesh.vertices().iter().map(|vert| vert.pos).collect();
+    assert_eq!(positions, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+}
+
+#[test]
+fn flip_winding_reverses_triangles() {
+    let mut mesh = Mesh::cube(2.0);
+    let volume = mesh.validate().volume;
+
+    mesh.flip_winding();
+
+    assert!((mesh.validate().volume + volume).abs() < 1e-5);
+    assert_indices_valid(&mesh);
+}
+
+#[test]
+fn split_hard_edges_splits_above_the_angle() {
+    // The cube's faces meet at 90 degrees
+    let mut mesh = smooth_cube();
+    mesh.split_hard_edges(100.0 * PI / 180.0);
+
+    assert_eq!(mesh.vertices().len(), 8);
+
+    let mut mesh = smooth_cube();
+    mesh.split_hard_edges(80.0 * PI / 180.0);
+
+    assert_eq!(mesh.vertices().len(), 24);
+    assert_indices_valid(&mesh);
+
+    // Each copy has its face's normal
+    for tri in mesh.indices().chunks(3) {
+        let normal = face_normal(&mesh, tri);
+
+        for &index in tri {
+            let vert_normal = Vector3::from(mesh.vertices()[index as usize].normal);
+            assert!((vert_normal - normal).norm() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn mirroring_transform_keeps_faces_outward() {
+    let mut mesh = Mesh::cube(2.0);
+    let tangent_signs: Vec<_> = mesh.vertices().iter().map(|vert| vert.tangent[3]).collect();
+
+    mesh.transform(&Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0)));
+
+    let report = mesh.validate();
+    assert!(report.is_closed(), "{}", report);
+    assert!((report.volume - 8.0).abs() < 1e-5);
+
+    for tri in mesh.indices().chunks(3) {
+        let normal = face_normal(&mesh, tri);
+
+        for &index in tri {
+            let vert = &mesh.vertices()[index as usize];
+            assert!((Vector3::from(vert.normal) - normal).norm() < 1e-5);
+        }
+    }
+
+    for (vert, sign) in mesh.vertices().iter().zip(tangent_signs) {
+        assert_eq!(vert.tangent[3], -sign);
+    }
+}
+
+#[test]
+fn transform_uses_the_inverse_transpose_for_normals() {
+    // A slanted triangle, its normal along (1, 1, 0)
+    let normal = Vector3::new(1.0, 1.0, 0.0).normalize();
+    let mut mesh = Mesh::new();
+
+    for &pos in &[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 1.0]] {
+        mesh.add_vertex(&Vertex {
+            normal: normal.into(),
+            ..vertex(pos, [0.0, 0.0])
+        });
+    }
+
+    mesh.add_tri((0, 1, 2));
+    mesh.transform(&Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 1.0, 1.0)));
+
+    // Stretching along x tips the normal towards y, unlike the matrix itself
+    let expected = Vector3::new(0.5, 1.0, 0.0).normalize();
+    let tri = mesh.indices().to_vec();
+
+    assert!((face_normal(&mesh, &tri) - expected).norm() < 1e-5);
+
+    for vert in mesh.vertices() {
+        assert!((Vector3::from(vert.normal) - expected).norm() < 1e-5);
+    }
+}
@@ -0,0 +1,135 @@
+extern crate rgraphics;
+
+use std::fs;
+
+use rgraphics::mesh::Mesh;
+use rgraphics::mesh_cache;
+use rgraphics::mesh_loader::{self, LoadOptions, NormalSource, UVSource};
+use rgraphics::model::{Model, Submesh};
+
+fn cube_cache(submeshes: Vec<Submesh>) -> Vec<u8> {
+    let model = Model::new(Mesh::cube(1.0), submeshes);
+    let mut data = Vec::new();
+
+    mesh_cache::write_cache(&mut data, &model, 42).unwrap();
+    data
+}
+
+fn submesh(start: u32, count: u32) -> Submesh {
+    Submesh {
+        name: String::from("part"),
+        material: None,
+        start,
+        count,
+    }
+}
+
+#[test]
+fn valid_cache_round_trips() {
+    let data = cube_cache(vec![submesh(0, 18), submesh(18, 18)]);
+    let cached = mesh_cache::read_cache(&data, "cube").unwrap();
+
+    assert_eq!(cached.source_hash, 42);
+    assert_eq!(cached.model.mesh.indices(), Mesh::cube(1.0).indices());
+    assert_eq!(cached.model.submeshes.len(), 2);
+    assert_eq!(cached.model.submeshes[1].start, 18);
+}
+
+#[test]
+fn submesh_past_the_indices_is_rejected() {
+    let data = cube_cache(vec![submesh(18, 24)]);
+    assert!(mesh_cache::read_cache(&data, "cube").is_err());
+}
+
+#[test]
+fn partial_triangle_submesh_is_rejected() {
+    assert!(mesh_cache::read_cache(&cube_cache(vec![submesh(1, 18)]), "cube").is_err());
+    assert!(mesh_cache::read_cache(&cube_cache(vec![submesh(0, 17)]), "cube").is_err());
+}
+
+#[test]
+fn huge_submesh_count_fails_without_allocating() {
+    let mut data = cube_cache(vec![submesh(0, 36)]);
+
+    // After the magic, version, hash, vertex count and index count
+    data[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    assert!(mesh_cache::read_cache(&data, "cube").is_err());
+}
+
+#[test]
+fn options_change_the_hash() {
+    let hash = |options: &LoadOptions| mesh_cache::source_hash(b"v 0 0 0", options);
+    let with = |uv_source, normal_source| LoadOptions {
+        uv_source,
+        normal_source,
+        ..LoadOptions::default()
+    };
+
+    let uv_sources = [
+        None,
+        Some(UVSource::PlanarX),
+        Some(UVSource::PlanarY),
+        Some(UVSource::PlanarZ),
+        Some(UVSource::Cylindrical),
+        Some(UVSource::Spherical),
+        Some(UVSource::Cube),
+    ];
+    let normal_sources = [
+        None,
+        Some(NormalSource::AreaWeighted),
+        Some(NormalSource::AngleWeighted),
+        Some(NormalSource::Flat),
+        Some(NormalSource::SmoothingGroups(0.5)),
+        Some(NormalSource::SmoothingGroups(1.0)),
+    ];
+
+    let mut hashes = Vec::new();
+
+    for &uv_source in &uv_sources {
+        for &normal_source in &normal_sources {
+            let options = with(uv_source, normal_source);
+
+            // The same options always give the same hash
+            assert_eq!(hash(&options), hash(&with(uv_source, normal_source)));
+            hashes.push(hash(&options));
+        }
+    }
+
+    hashes.sort();
+    hashes.dedup();
+    assert_eq!(hashes.len(), uv_sources.len() * normal_sources.len());
+}
+
+#[test]
+fn cache_is_written_and_reused() {
+    fs::create_dir_all("target/mesh_cache").unwrap();
+
+    let file = "target/mesh_cache/triangle.obj";
+    fs::write(file, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+    let _ = fs::remove_file(mesh_cache::cache_path(file));
+
+    let options = LoadOptions {
+        normal_source: Some(NormalSource::Flat),
+        cache: true,
+        ..LoadOptions::default()
+    };
+
+    let built = mesh_loader::load_model_with(file, &options).unwrap();
+
+    // Only the finished cache is left behind
+    let data = fs::read(mesh_cache::cache_path(file)).unwrap();
+    let leftovers: Vec<_> = fs::read_dir("target/mesh_cache")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+
+    let cached = mesh_cache::read_cache(&data, file).unwrap();
+    assert_eq!(cached.source_hash, mesh_cache::source_hash(&fs::read(file).unwrap(), &options));
+
+    let reused = mesh_loader::load_model_with(file, &options).unwrap();
+    assert_eq!(reused.mesh.indices(), built.mesh.indices());
+    assert_eq!(reused.mesh.vertices().len(), built.mesh.vertices().len());
+}
@@ -0,0 +1,167 @@
+extern crate gfx_device_gl;
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use na::{Matrix4, Point3, UnitQuaternion, Vector3, Vector4};
+
+use rgraphics::color::Color;
+use rgraphics::material::Material;
+use rgraphics::mesh::Mesh;
+use rgraphics::object::{self, Object};
+use rgraphics::program::Transform;
+
+// Column-major like the uniform block, multiplied the way shader.vert does
+fn shader_multiply(matrix: &[[f32; 4]; 4], v: Vector4<f32>) -> Vector4<f32> {
+    (0..4).fold(Vector4::zeros(), |acc, column| {
+        acc + Vector4::from(matrix[column]) * v[column]
+    })
+}
+
+// shader.vert's normalMatrix * vec4(vNormal, 0), normalized like the
+// fragment shader does
+fn shader_normal(transform: &Transform, normal: [f32; 3]) -> Vector3<f32> {
+    let n = shader_multiply(&transform.normal, Vector4::new(normal[0], normal[1], normal[2], 0.0));
+    Vector3::new(n.x, n.y, n.z).normalize()
+}
+
+// What the shader used to do, view * model * vec4(vNormal, 0)
+fn model_view_normal(transform: &Transform, normal: [f32; 3]) -> Vector3<f32> {
+    let n = Vector4::new(normal[0], normal[1], normal[2], 0.0);
+    let n = shader_multiply(&transform.view, shader_multiply(&transform.model, n));
+    Vector3::new(n.x, n.y, n.z).normalize()
+}
+
+// View space position, view * model * vec4(vPos, 1)
+fn shader_position(transform: &Transform, pos: [f32; 3]) -> Vector3<f32> {
+    let p = Vector4::new(pos[0], pos[1], pos[2], 1.0);
+    let p = shader_multiply(&transform.view, shader_multiply(&transform.model, p));
+    Vector3::new(p.x, p.y, p.z)
+}
+
+fn stretched_object() -> Object<gfx_device_gl::Resources> {
+    let mut obj = Object::new(
+        Material::Untextured {
+            diffuse_color: Color::white(),
+            ambient_color: Color::black(),
+            specular_color: Color::white(),
+            specular_power: 1.0,
+        },
+        Point3::new(1.0, -2.0, -5.0),
+        Vector3::new(3.0, 0.5, 1.5),
+        UnitQuaternion::identity(),
+    );
+    obj.set_euler_angles(0.3, -0.7, 1.1);
+    obj
+}
+
+fn view() -> Matrix4<f32> {
+    Matrix4::look_at_rh(
+        &Point3::new(2.0, 1.0, 3.0),
+        &Point3::new(1.0, -2.0, -5.0),
+        &Vector3::y(),
+    )
+}
+
+fn stretched_transform() -> Transform {
+    let projection = Matrix4::new_perspective(4.0 / 3.0, 1.0, 0.1, 100.0);
+
+    object::create_transform(&stretched_object().build_matrix(), view(), projection)
+}
+
+// Diffuse term for a few light directions, as computeLighting does it
+fn lambert(normal: &Vector3<f32>) -> Vec<f32> {
+    [
+        Vector3::new(1.0, 1.0, 1.0),
+        Vector3::new(-1.0, 0.5, 0.2),
+        Vector3::new(0.0, -1.0, 0.3),
+    ].iter()
+        .map(|light| normal.dot(&light.normalize()).max(0.0))
+        .collect()
+}
+
+fn assert_close(actual: &Vector3<f32>, expected: &Vector3<f32>) {
+    assert!(
+        (actual - expected).norm() < 1e-4,
+        "{:?} != {:?}",
+        actual,
+        expected
+    );
+
+    for (a, e) in lambert(actual).iter().zip(lambert(expected).iter()) {
+        assert!((a - e).abs() < 1e-4, "lighting {} != {}", a, e);
+    }
+}
+
+#[test]
+fn flat_faces_match_transformed_geometry() {
+    let transform = stretched_transform();
+    // Faces at odd angles to the scaling axes, axis-aligned ones come out
+    // right even with the model-view
+    let mesh = Mesh::icosphere(1.0, 0);
+    let mut model_view_wrong = false;
+
+    for tri in mesh.indices().chunks(3) {
+        let pos: Vec<[f32; 3]> = tri.iter().map(|&i| mesh.vertices()[i as usize].pos).collect();
+        let (a, b, c) = (Vector3::from(pos[0]), Vector3::from(pos[1]), Vector3::from(pos[2]));
+        let face_normal: [f32; 3] = (b - a).cross(&(c - a)).normalize().into();
+
+        // The face normal of the triangle after it's been transformed
+        let a = shader_position(&transform, pos[0]);
+        let b = shader_position(&transform, pos[1]);
+        let c = shader_position(&transform, pos[2]);
+        let reference = (b - a).cross(&(c - a)).normalize();
+
+        assert_close(&shader_normal(&transform, face_normal), &reference);
+
+        model_view_wrong |= (model_view_normal(&transform, face_normal) - reference).norm() > 0.01;
+    }
+
+    // Otherwise the scale isn't non-uniform enough to test anything
+    assert!(model_view_wrong);
+}
+
+#[test]
+fn sphere_matches_ellipsoid_gradient() {
+    let obj = stretched_object();
+    let transform = stretched_transform();
+
+    for vert in Mesh::icosphere(1.0, 2).vertices() {
+        // The scaled sphere is the ellipsoid x²/a² + y²/b² + z²/c² = 1, its
+        // gradient (x/a², y/b², z/c²) is normal to it, then rotate and view
+        let scaled = Vector3::from(vert.pos).component_mul(&obj.scale);
+        let gradient = scaled.component_div(&obj.scale.component_mul(&obj.scale));
+        let world = obj.rotation * gradient;
+        let reference = (view() * world.to_homogeneous()).fixed_rows::<3>(0).normalize();
+
+        assert_close(&shader_normal(&transform, vert.normal), &reference);
+    }
+}
+
+#[test]
+fn uniform_scale_matches_model_view() {
+    let mut obj = stretched_object();
+    obj.scale = Vector3::from_element(2.5);
+
+    let transform =
+        object::create_transform(&obj.build_matrix(), Matrix4::identity(), Matrix4::identity());
+
+    for vert in Mesh::icosphere(1.0, 1).vertices() {
+        assert_close(
+            &shader_normal(&transform, vert.normal),
+            &model_view_normal(&transform, vert.normal),
+        );
+    }
+}
+
+#[test]
+fn degenerate_scale_does_not_produce_nan() {
+    let mut obj = stretched_object();
+    obj.scale = Vector3::new(1.0, 0.0, 1.0);
+
+    let transform =
+        object::create_transform(&obj.build_matrix(), Matrix4::identity(), Matrix4::identity());
+
+    for row in &transform.normal {
+        assert!(row.iter().all(|value| value.is_finite()));
+    }
+}
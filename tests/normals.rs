@@ -0,0 +1,132 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use std::f32::consts::PI;
+use std::fs;
+
+use na::Vector3;
+
+use rgraphics::mesh_loader::{self, LoadOptions, NormalSource};
+use rgraphics::normals;
+
+fn assert_close(actual: &Vector3<f32>, expected: &Vector3<f32>) {
+    assert!((actual - expected).norm() < 1e-5, "{:?} != {:?}", actual, expected);
+}
+
+// Triangles folded at right angles along the x axis: the first lies in z = 0
+// facing +z, the second in y = 0 facing +y. Both have an area of 0.5
+const FOLD: [(u32, u32, u32); 2] = [(0, 1, 2), (1, 0, 3)];
+
+fn fold_verts() -> Vec<Vector3<f32>> {
+    vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(1.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+    ]
+}
+
+#[test]
+fn flat_normals_follow_each_face() {
+    let (verts, tris) = (fold_verts(), FOLD);
+    let (normals, corners) = normals::flat(&verts, &tris);
+
+    assert_eq!(corners, vec![0, 0, 0, 1, 1, 1]);
+    assert_close(&normals[0], &Vector3::z());
+    assert_close(&normals[1], &Vector3::y());
+}
+
+#[test]
+fn area_weighting_favours_large_faces() {
+    // Meeting at the origin at right angles, one face four times the other
+    let verts = vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(2.0, 0.0, 0.0),
+        Vector3::new(0.0, 2.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+    ];
+    let tris = [(0, 1, 2), (0, 3, 4)];
+
+    let area_weighted = normals::smooth_area_weighted(&verts, &tris);
+    assert_close(&area_weighted[0], &Vector3::new(1.0, 0.0, 4.0).normalize());
+
+    // Both corners are right angles, so by angle they count the same
+    let angle_weighted = normals::smooth_angle_weighted(&verts, &tris);
+    assert_close(&angle_weighted[0], &Vector3::new(1.0, 0.0, 1.0).normalize());
+
+    // Positions on one face only get its normal
+    assert_close(&area_weighted[1], &Vector3::z());
+    assert_close(&angle_weighted[4], &Vector3::x());
+}
+
+#[test]
+fn smoothing_groups_decide_what_is_shared() {
+    let (verts, tris) = (fold_verts(), FOLD);
+    let shared = Vector3::new(0.0, 1.0, 1.0).normalize();
+
+    // Same group and within the crease angle: the fold's edge is smoothed
+    let (normals, corners) = normals::smoothing_groups(&verts, &tris, &[1, 1], PI);
+
+    assert_eq!(normals.len(), 4);
+    assert_eq!(corners[0], corners[4]);
+    assert_eq!(corners[1], corners[3]);
+    assert_close(&normals[corners[0] as usize], &shared);
+    assert_close(&normals[corners[2] as usize], &Vector3::z());
+    assert_close(&normals[corners[5] as usize], &Vector3::y());
+
+    // Different groups, group 0 and a crease angle under 90 degrees all keep
+    // the fold sharp
+    for &(groups, crease_angle) in &[([1, 2], PI), ([0, 0], PI), ([1, 1], PI / 4.0)] {
+        let (normals, corners) = normals::smoothing_groups(&verts, &tris, &groups, crease_angle);
+
+        assert_eq!(normals.len(), 6);
+
+        for (corner, &index) in corners.iter().enumerate() {
+            let expected = if corner < 3 { Vector3::z() } else { Vector3::y() };
+            assert_close(&normals[index as usize], &expected);
+        }
+    }
+}
+
+// The fold as an OBJ, with `groups` put before each face
+fn load_fold(name: &str, groups: [&str; 2], crease_angle: f32) -> Vec<[f32; 3]> {
+    fs::create_dir_all("target/normals").unwrap();
+
+    let file = format!("target/normals/{}", name);
+    let contents = format!(
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\n{}f 1 2 3\n{}f 2 1 4\n",
+        groups[0], groups[1]
+    );
+    fs::write(&file, contents).unwrap();
+
+    let options = LoadOptions {
+        normal_source: Some(NormalSource::SmoothingGroups(crease_angle)),
+        ..LoadOptions::default()
+    };
+    let mesh = mesh_loader::load_file_with(&file, &options).unwrap();
+
+    mesh.indices()
+        .iter()
+        .map(|&index| mesh.vertices()[index as usize].normal)
+        .collect()
+}
+
+#[test]
+fn obj_smoothing_groups() {
+    let shared: [f32; 3] = Vector3::new(0.0, 1.0, 1.0).normalize().into();
+
+    let smooth = load_fold("same_group.obj", ["s 1\n", ""], PI);
+    assert_eq!(smooth[0], shared);
+    assert_eq!(smooth[4], shared);
+
+    for &(name, groups) in &[("two_groups.obj", ["s 1\n", "s 2\n"]), ("off.obj", ["s off\n", ""])] {
+        let sharp = load_fold(name, groups, PI);
+        assert_eq!(sharp[0], [0.0, 0.0, 1.0]);
+        assert_eq!(sharp[4], [0.0, 1.0, 0.0]);
+    }
+
+    // Without any s directives the crease angle alone decides
+    assert_eq!(load_fold("no_groups.obj", ["", ""], PI)[0], shared);
+    assert_eq!(load_fold("no_groups_creased.obj", ["", ""], PI / 4.0)[0], [0.0, 0.0, 1.0]);
+}
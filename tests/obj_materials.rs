@@ -0,0 +1,91 @@
+extern crate rgraphics;
+
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rgraphics::mesh_loader::{self, LoadOptions, MeshLoadError};
+use rgraphics::mtl_loader;
+
+// Per thread since tests run in parallel
+thread_local!(static WARNINGS: Cell<usize> = const { Cell::new(0) });
+
+fn count_warning(_: &MeshLoadError) {
+    WARNINGS.with(|count| count.set(count.get() + 1));
+}
+
+// Loads the model and its materials, returning the material names and how
+// many warnings were given
+fn load(file_path: &str) -> (Vec<String>, usize) {
+    let options = LoadOptions {
+        warn: count_warning,
+        ..LoadOptions::default()
+    };
+
+    let model = mesh_loader::load_model_with(file_path, &options).unwrap();
+    let before = WARNINGS.with(Cell::get);
+    let materials = mesh_loader::load_mtl_materials(file_path, &model, &options).unwrap();
+
+    (
+        materials.into_iter().map(|material| material.name).collect(),
+        WARNINGS.with(Cell::get) - before,
+    )
+}
+
+#[test]
+fn missing_library_is_a_warning() {
+    // cube.obj names cube.mtl, which isn't shipped
+    let (materials, warnings) = load("assets/models/cube.obj");
+
+    assert!(materials.is_empty());
+    assert!(warnings >= 1);
+}
+
+#[test]
+fn unknown_material_is_a_warning() {
+    // suzanne.obj uses a material without naming any library
+    let (materials, warnings) = load("assets/models/suzanne.obj");
+
+    assert!(materials.is_empty());
+    assert!(warnings >= 1);
+}
+
+#[test]
+fn used_materials_are_loaded() {
+    let dir = "target/obj_materials";
+    fs::create_dir_all(dir).unwrap();
+
+    fs::write(
+        format!("{}/quad.mtl", dir),
+        "newmtl red\nKd 1 0 0\nNs 20\n\nnewmtl unused\nKd 0 1 0\n",
+    ).unwrap();
+    fs::write(
+        format!("{}/quad.obj", dir),
+        "mtllib quad.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\n\
+         usemtl red\nf 1//1 2//1 3//1\nusemtl blue\nf 1//1 3//1 4//1\n",
+    ).unwrap();
+
+    let (materials, warnings) = load(&format!("{}/quad.obj", dir));
+
+    assert_eq!(materials, vec![String::from("red")]);
+    assert_eq!(warnings, 1);
+}
+
+#[test]
+fn map_paths_follow_their_options() {
+    let mtl = "newmtl brick\n\
+               map_Kd -blendu off -o 0.5 0.5 -mm 0 1 old bricks.png\n\
+               map_Ks -s 2 specular.png\n\
+               map_Bump -bm 0.8 -clamp on normals\\brick wall.png\n";
+
+    let materials = mtl_loader::parse_mtl(mtl.as_bytes(), "brick.mtl", Path::new("textures"))
+        .unwrap();
+
+    assert_eq!(materials[0].diffuse_map, Some(PathBuf::from("textures/old bricks.png")));
+    assert_eq!(materials[0].specular_map, Some(PathBuf::from("textures/specular.png")));
+    assert_eq!(materials[0].bump_map, Some(PathBuf::from("textures/normals/brick wall.png")));
+
+    // Options without a file name
+    let no_file = "newmtl brick\nmap_Kd -o 1 2 3\n";
+    assert!(mtl_loader::parse_mtl(no_file.as_bytes(), "brick.mtl", Path::new("")).is_err());
+}
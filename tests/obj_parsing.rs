@@ -0,0 +1,228 @@
+extern crate rgraphics;
+
+use std::cell::RefCell;
+use std::fs;
+
+use rgraphics::mesh_loader::{self, LoadOptions, MeshLoadError, ParseErrorKind};
+
+// Per thread since tests run in parallel
+thread_local!(static WARNINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) });
+
+fn record_warning(warning: &MeshLoadError) {
+    WARNINGS.with(|warnings| warnings.borrow_mut().push(warning.to_string()));
+}
+
+fn write_obj(name: &str, contents: &str) -> String {
+    fs::create_dir_all("target/obj_parsing").unwrap();
+
+    let file = format!("target/obj_parsing/{}", name);
+    fs::write(&file, contents).unwrap();
+    file
+}
+
+fn options() -> LoadOptions {
+    LoadOptions {
+        warn: record_warning,
+        ..LoadOptions::default()
+    }
+}
+
+// Line and column of the parse error loading `contents` gives
+fn error_position(name: &str, contents: &str) -> (usize, usize) {
+    match mesh_loader::load_model_with(&write_obj(name, contents), &options()) {
+        Err(MeshLoadError::Parse { line, column, .. }) => (line, column),
+        Err(error) => panic!("unexpected error {}", error),
+        Ok(_) => panic!("loaded without an error"),
+    }
+}
+
+const FREE_FORM: &str = "v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+vp 0.5 0.5
+cstype bspline
+deg 3
+curv 0 1 1 2 3
+parm u 0 0 0 0 1 1 1 1
+end
+cstype bezier
+surf 0 1 0 1 1 2 3
+trim 0 1 1
+end
+shadow_obj shadow.obj
+f 1//1 2//1 3//1
+";
+
+#[test]
+fn free_form_directives_are_skipped() {
+    let file = write_obj("free_form.obj", FREE_FORM);
+
+    WARNINGS.with(|warnings| warnings.borrow_mut().clear());
+    let model = mesh_loader::load_model_with(&file, &options()).unwrap();
+
+    assert_eq!(model.mesh.indices().len(), 3);
+
+    // One warning per directive, at its first use
+    let warnings = WARNINGS.with(|warnings| warnings.borrow().clone());
+    let expected: Vec<_> = [
+        (5, "vp"),
+        (6, "cstype"),
+        (7, "deg"),
+        (8, "curv"),
+        (9, "parm"),
+        (10, "end"),
+        (12, "surf"),
+        (13, "trim"),
+    ].iter()
+        .map(|&(line, name)| format!("{}:{}:1: unsupported directive '{}'", file, line, name))
+        .collect();
+
+    assert_eq!(warnings, expected);
+}
+
+#[test]
+fn continued_line_errors_point_at_their_line() {
+    // The bad number is on the third line of the statement, after 2 spaces
+    let contents = "v 0 0 0\nv 1 \\\n  0 \\\n  zero\n";
+    assert_eq!(error_position("continued.obj", contents), (4, 3));
+
+    // Missing values are reported just past the end of the statement
+    let contents = "v 0 0 0\nv 1 \\\n  0\n";
+    assert_eq!(error_position("continued_missing.obj", contents), (3, 4));
+}
+
+#[test]
+fn columns_count_characters() {
+    // No-break spaces are two bytes each but one column
+    let contents = "v 0 0 0\nv\u{a0}1\u{a0}2\u{a0}x\n";
+    assert_eq!(error_position("non_ascii.obj", contents), (2, 7));
+}
+
+#[test]
+fn bad_values_are_still_errors() {
+    let file = write_obj("bad_face.obj", "v 0 0 0\nvn 0 0 1\nf 1//1 2//1 3//1\n");
+
+    match mesh_loader::load_model_with(&file, &options()) {
+        Err(MeshLoadError::Parse {
+            line: 3,
+            column: 8,
+            reason: ParseErrorKind::IndexOutOfRange { .. },
+            ..
+        }) => {}
+        Err(error) => panic!("unexpected error {}", error),
+        Ok(_) => panic!("loaded without an error"),
+    }
+}
+
+// Position, UV and normal of every triangle corner in order
+fn corners(contents: &str, name: &str) -> Vec<([f32; 3], [f32; 2], [f32; 3])> {
+    let model = mesh_loader::load_model_with(&write_obj(name, contents), &options()).unwrap();
+    let verts = model.mesh.vertices();
+
+    model
+        .mesh
+        .indices()
+        .iter()
+        .map(|&index| {
+            let vert = &verts[index as usize];
+            (vert.pos, vert.uv, vert.normal)
+        })
+        .collect()
+}
+
+fn positions(contents: &str, name: &str) -> Vec<[f32; 3]> {
+    corners(contents, name).into_iter().map(|(pos, ..)| pos).collect()
+}
+
+#[test]
+fn scientific_notation_floats() {
+    let contents = "v 1e0 -2.5E-1 3.0e+1\nv 1E1 0 0\nv 0 1.5e-3 -0\nvn 0 0 1e0\nf 1//1 2//1 3//1\n";
+
+    assert_eq!(
+        positions(contents, "scientific.obj"),
+        vec![[1.0, -0.25, 30.0], [10.0, 0.0, 0.0], [0.0, 0.0015, 0.0]]
+    );
+}
+
+#[test]
+fn negative_indices_are_relative() {
+    // Each face's indices count back from the elements defined so far
+    let contents = "v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+vn 0 0 1
+f -3/-3/-1 -2/-2/-1 -1/-1/-1
+v 5 0 0
+v 6 0 0
+v 5 1 0
+f -3/1/1 -2/2/1 -1/3/1
+";
+
+    let corners = corners(contents, "relative.obj");
+    let positions: Vec<_> = corners.iter().map(|&(pos, ..)| pos).collect();
+    let uvs: Vec<_> = corners.iter().map(|&(_, uv, _)| uv).collect();
+
+    assert_eq!(
+        positions,
+        vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [5.0, 0.0, 0.0],
+            [6.0, 0.0, 0.0],
+            [5.0, 1.0, 0.0],
+        ]
+    );
+    assert_eq!(&uvs[..3], &uvs[3..]);
+
+    // Counting back past the first element is out of range
+    let contents = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf -4//1 -2//1 -1//1\n";
+    assert_eq!(error_position("relative_range.obj", contents), (5, 3));
+}
+
+#[test]
+fn optional_w_is_ignored() {
+    let contents = "v 0 0 0 0.5
+v 1 0 0 1
+v 0 1 0 2
+vt 0.25 0.75 1
+vt 0.5
+vt 1 1 0
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1
+";
+
+    let corners = corners(contents, "w.obj");
+
+    assert_eq!(corners[1].0, [1.0, 0.0, 0.0]);
+    assert_eq!(corners[2].0, [0.0, 1.0, 0.0]);
+
+    // A missing v defaults to 0
+    let uvs: Vec<_> = corners.iter().map(|&(_, uv, _)| uv).collect();
+    assert_eq!(uvs, vec![[0.25, 0.75], [0.5, 0.0], [1.0, 1.0]]);
+}
+
+#[test]
+fn continuations_join_lines() {
+    let joined = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n";
+    let continued = "v 0 \\
+0 0
+v 1 0 0
+v \\
+  0 \\
+  1 \\
+  0
+vn 0 0 1
+f 1//1 \\
+2//1 3//1
+";
+
+    assert_eq!(
+        corners(continued, "continued_ok.obj"),
+        corners(joined, "joined.obj")
+    );
+}
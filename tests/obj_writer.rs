@@ -0,0 +1,114 @@
+extern crate gfx_device_gl;
+extern crate rgraphics;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rgraphics::color::Color;
+use rgraphics::material::Material;
+use rgraphics::mesh::Mesh;
+use rgraphics::mesh_loader;
+use rgraphics::mtl_loader::{self, MtlMaterial};
+use rgraphics::obj_writer;
+
+fn output_path(name: &str) -> String {
+    fs::create_dir_all("target/obj_writer").unwrap();
+    format!("target/obj_writer/{}", name)
+}
+
+fn rgba(color: Color) -> [u8; 4] {
+    [color.r, color.g, color.b, color.a]
+}
+
+// The loader numbers vertices in the order faces use them, so triangles are
+// compared corner by corner rather than by index
+fn assert_same_mesh(actual: &Mesh, expected: &Mesh) {
+    assert_eq!(actual.indices().len(), expected.indices().len());
+    assert_eq!(actual.vertices().len(), expected.vertices().len());
+
+    for (&actual_index, &expected_index) in actual.indices().iter().zip(expected.indices()) {
+        let actual = &actual.vertices()[actual_index as usize];
+        let expected = &expected.vertices()[expected_index as usize];
+
+        assert_eq!(actual.pos, expected.pos);
+        assert_eq!(actual.normal, expected.normal);
+        assert_eq!(actual.uv, expected.uv);
+    }
+}
+
+#[test]
+fn primitive_round_trips() {
+    for &(name, ref mesh) in &[
+        ("cube.obj", Mesh::cube(1.5)),
+        ("icosphere.obj", Mesh::icosphere(0.75, 2)),
+    ] {
+        let file = output_path(name);
+
+        obj_writer::save_file(&file, mesh).unwrap();
+        assert_same_mesh(&mesh_loader::load_file(&file).unwrap(), mesh);
+    }
+}
+
+#[test]
+fn loaded_model_round_trips() {
+    let model = mesh_loader::load_model("assets/models/suzanne.obj").unwrap();
+    let file = output_path("suzanne.obj");
+
+    obj_writer::save_model::<gfx_device_gl::Resources>(&file, &model, &[]).unwrap();
+    let reloaded = mesh_loader::load_model(&file).unwrap();
+
+    assert_same_mesh(&reloaded.mesh, &model.mesh);
+    assert_eq!(reloaded.submeshes.len(), model.submeshes.len());
+
+    for (actual, expected) in reloaded.submeshes.iter().zip(&model.submeshes) {
+        assert_eq!(actual.name, expected.name);
+        assert_eq!(actual.material, expected.material);
+        assert_eq!((actual.start, actual.count), (expected.start, expected.count));
+    }
+}
+
+#[test]
+fn texture_paths_round_trip() {
+    let mut material = MtlMaterial::new("painted");
+    material.diffuse_color = Color::rgb(200, 100, 50);
+    material.specular_power = 32.0;
+    material.dissolve = 0.5;
+    material.diffuse_map = Some(PathBuf::from("textures/diffuse.tga"));
+    material.specular_map = Some(PathBuf::from("textures/specular.tga"));
+    material.bump_map = Some(PathBuf::from("textures/bump.tga"));
+
+    let mut data = Vec::new();
+    obj_writer::write_mtl_materials(&mut data, &[material.clone()]).unwrap();
+
+    let reloaded = mtl_loader::parse_mtl(&data[..], "painted.mtl", Path::new("")).unwrap();
+
+    assert_eq!(reloaded.len(), 1);
+    assert_eq!(reloaded[0].name, material.name);
+    assert_eq!(rgba(reloaded[0].diffuse_color), rgba(material.diffuse_color));
+    assert_eq!(reloaded[0].specular_power, material.specular_power);
+    assert_eq!(reloaded[0].dissolve, material.dissolve);
+    assert_eq!(reloaded[0].diffuse_map, material.diffuse_map);
+    assert_eq!(reloaded[0].specular_map, material.specular_map);
+    assert_eq!(reloaded[0].bump_map, material.bump_map);
+    assert_eq!(reloaded[0].normal_map, None);
+}
+
+#[test]
+fn untextured_material_keeps_its_colors() {
+    let material: Material<gfx_device_gl::Resources> = Material::Untextured {
+        diffuse_color: Color::rgba(255, 0, 0, 128),
+        ambient_color: Color::rgb(10, 10, 10),
+        specular_color: Color::white(),
+        specular_power: 16.0,
+    };
+
+    let mut data = Vec::new();
+    obj_writer::write_mtl(&mut data, &[(String::from("red"), material)]).unwrap();
+
+    let reloaded = mtl_loader::parse_mtl(&data[..], "red.mtl", Path::new("")).unwrap();
+
+    assert_eq!(rgba(reloaded[0].diffuse_color), [255, 0, 0, 255]);
+    assert_eq!(rgba(reloaded[0].ambient_color), [10, 10, 10, 255]);
+    assert_eq!(reloaded[0].specular_power, 16.0);
+    assert!((reloaded[0].dissolve - 128.0 / 255.0).abs() < 1e-6);
+}
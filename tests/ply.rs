@@ -0,0 +1,190 @@
+extern crate rgraphics;
+
+use rgraphics::color::Color;
+use rgraphics::mesh::Mesh;
+use rgraphics::mesh_loader::MeshLoadError;
+use rgraphics::ply::{self, PlyFormat};
+
+fn rgba(color: &Color) -> [u8; 4] {
+    [color.r, color.g, color.b, color.a]
+}
+
+fn colors(mesh: &Mesh) -> Vec<Color> {
+    (0..mesh.vertices().len())
+        .map(|i| Color::rgba((i * 7) as u8, (i * 13) as u8, 255 - i as u8, 128 + i as u8))
+        .collect()
+}
+
+fn round_trip(mesh: &Mesh, colors: Option<&[Color]>, format: PlyFormat) -> (Mesh, Vec<Color>) {
+    let mut data = Vec::new();
+    ply::write_ply(&mut data, mesh, colors, format).unwrap();
+
+    ply::parse_ply(&data[..], "mesh.ply").unwrap()
+}
+
+fn assert_same_mesh(actual: &Mesh, expected: &Mesh) {
+    assert_eq!(actual.indices(), expected.indices());
+    assert_eq!(actual.vertices().len(), expected.vertices().len());
+
+    for (actual, expected) in actual.vertices().iter().zip(expected.vertices()) {
+        assert_eq!(actual.pos, expected.pos);
+        assert_eq!(actual.normal, expected.normal);
+        assert_eq!(actual.uv, expected.uv);
+    }
+}
+
+#[test]
+fn every_format_round_trips() {
+    let mesh = Mesh::uv_sphere(1.25, 12, 8);
+    let colors = colors(&mesh);
+
+    for &format in &[
+        PlyFormat::Ascii,
+        PlyFormat::BinaryLittleEndian,
+        PlyFormat::BinaryBigEndian,
+    ] {
+        let (reloaded, reloaded_colors) = round_trip(&mesh, Some(&colors), format);
+
+        assert_same_mesh(&reloaded, &mesh);
+
+        let expected: Vec<_> = colors.iter().map(rgba).collect();
+        assert_eq!(reloaded_colors.iter().map(rgba).collect::<Vec<_>>(), expected);
+
+        // Without colors every vertex comes back white
+        let (reloaded, reloaded_colors) = round_trip(&mesh, None, format);
+
+        assert_same_mesh(&reloaded, &mesh);
+        assert!(reloaded_colors.iter().all(|color| rgba(color) == [255; 4]));
+    }
+}
+
+// Written by hand rather than by write_ply: s/t UV names, double normals,
+// a comment, a quad and an element the loader doesn't know
+const QUAD: &str = "ply
+format ascii 1.0
+comment a unit quad facing +z
+element vertex 4
+property float x
+property float y
+property float z
+property double nx
+property double ny
+property double nz
+property uchar red
+property uchar green
+property uchar blue
+property float s
+property float t
+element face 1
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 0 0 1 255 0 0 0 0
+1 0 0 0 0 1 0 255 0 1 0
+1 1 0 0 0 1 0 0 255 1 1
+0 1 0 0 0 1 255 255 255 0 1
+4 0 1 2 3
+0 2
+";
+
+#[test]
+fn hand_written_ascii() {
+    let (mesh, colors) = ply::parse_ply(QUAD.as_bytes(), "quad.ply").unwrap();
+
+    // The quad is split in two
+    assert_eq!(mesh.indices().len(), 6);
+    assert_eq!(mesh.vertices().len(), 4);
+
+    let uvs: Vec<_> = mesh.vertices().iter().map(|vert| vert.uv).collect();
+    assert_eq!(uvs, vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+
+    assert_eq!(mesh.vertices()[2].pos, [1.0, 1.0, 0.0]);
+    assert!(mesh.vertices().iter().all(|vert| vert.normal == [0.0, 0.0, 1.0]));
+
+    let colors: Vec<_> = colors.iter().map(rgba).collect();
+    assert_eq!(
+        colors,
+        vec![
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255, 255, 255, 255],
+        ]
+    );
+}
+
+// Two triangles whose UVs mirror across the edge they share, with a color
+// per vertex
+const MIRRORED: &str = "ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+property float u
+property float v
+property uchar red
+property uchar green
+property uchar blue
+element face 2
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 255 0 0
+1 0 0 1 0 0 255 0
+1 1 0 1 1 0 0 255
+2 0 0 0 0 255 255 0
+3 0 1 2
+3 1 3 2
+";
+
+#[test]
+fn split_vertices_keep_their_colors() {
+    let (mesh, colors) = ply::parse_ply(MIRRORED.as_bytes(), "mirrored.ply").unwrap();
+
+    // The shared edge is split for the mirrored tangents
+    assert_eq!(mesh.vertices().len(), 6);
+    assert_eq!(colors.len(), mesh.vertices().len());
+
+    for (vert, color) in mesh.vertices().iter().zip(&colors) {
+        let expected = match vert.pos {
+            [0.0, 0.0, 0.0] => [255, 0, 0, 255],
+            [1.0, 0.0, 0.0] => [0, 255, 0, 255],
+            [1.0, 1.0, 0.0] => [0, 0, 255, 255],
+            _ => [255, 255, 0, 255],
+        };
+
+        assert_eq!(rgba(color), expected);
+    }
+
+    // So the pair can be written straight back out
+    let (reloaded, reloaded_colors) = round_trip(&mesh, Some(&colors), PlyFormat::Ascii);
+    assert_eq!(reloaded.vertices().len(), mesh.vertices().len());
+    assert_eq!(reloaded_colors.len(), colors.len());
+}
+
+// A triangle whose last index is `index`
+fn triangle_with(index: &str) -> String {
+    format!(
+        "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+         property float z\nelement face 1\nproperty list uchar int vertex_indices\n\
+         end_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 {}\n",
+        index
+    )
+}
+
+#[test]
+fn bad_face_indices_are_format_errors() {
+    assert!(ply::parse_ply(triangle_with("2").as_bytes(), "good.ply").is_ok());
+
+    for &index in &["-1", "3", "-4294967296"] {
+        match ply::parse_ply(triangle_with(index).as_bytes(), "bad.ply") {
+            Err(MeshLoadError::Format { ref error, .. }) => {
+                assert!(error.contains("out of range"), "{}", error)
+            }
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("index {} loaded without an error", index),
+        }
+    }
+}
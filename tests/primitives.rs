@@ -0,0 +1,28 @@
+extern crate rgraphics;
+
+use rgraphics::mesh::Mesh;
+
+#[test]
+fn zero_counts_still_close_the_shape() {
+    let shapes = vec![
+        ("uv_sphere", Mesh::uv_sphere(1.0, 0, 0)),
+        ("cylinder", Mesh::cylinder(1.0, 2.0, 0)),
+        ("cone", Mesh::cone(1.0, 2.0, 0)),
+        ("torus", Mesh::torus(1.0, 0.25, 0, 0)),
+        ("capsule", Mesh::capsule(0.5, 1.0, 0, 0)),
+    ];
+
+    for (name, mesh) in shapes {
+        let report = mesh.validate();
+
+        assert!(report.triangle_count > 0, "{} is empty", name);
+        assert!(report.is_valid(), "{}:\n{}", name, report);
+        assert!(report.is_closed(), "{}:\n{}", name, report);
+        assert!(report.volume > 0.0, "{} is inside out", name);
+
+        for vert in mesh.vertices() {
+            let values = vert.pos.iter().chain(&vert.normal).chain(&vert.uv);
+            assert!(values.cloned().all(f32::is_finite), "{} has NaNs", name);
+        }
+    }
+}
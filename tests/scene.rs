@@ -0,0 +1,118 @@
+extern crate gfx_device_gl;
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use std::f32::consts::FRAC_PI_2;
+
+use na::{Point3, UnitQuaternion, Vector3};
+
+use rgraphics::camera::Camera;
+use rgraphics::color::Color;
+use rgraphics::light::{Light, LightType, SpotLightInfo};
+use rgraphics::material::Material;
+use rgraphics::object::Object;
+use rgraphics::scene::Scene;
+
+type Resources = gfx_device_gl::Resources;
+
+fn object(position: Point3<f32>) -> Object<Resources> {
+    Object::new(
+        Material::Untextured {
+            diffuse_color: Color::white(),
+            ambient_color: Color::black(),
+            specular_color: Color::white(),
+            specular_power: 1.0,
+        },
+        position,
+        Vector3::from_element(1.0),
+        UnitQuaternion::identity(),
+    )
+}
+
+// At (0, 0, 5) looking down -z, so the view only moves things by -5 in z
+fn scene() -> Scene<Resources> {
+    let mut camera = Camera::perspective(FRAC_PI_2, 800, 600, 0.1, 100.0);
+    camera.position = Point3::new(0.0, 0.0, 5.0);
+
+    Scene::new(camera)
+}
+
+fn spot_light() -> Light {
+    let info = SpotLightInfo {
+        inner_radius: 0.2,
+        outer_radius: 0.4,
+        falloff: 1.0,
+    };
+
+    Light::new_spot(
+        Point3::new(0.0, 1.0, 0.0),
+        -Vector3::z(),
+        info,
+        Color::white(),
+        Color::white(),
+        Color::black(),
+    )
+}
+
+fn spot(light: &Light) -> (Point3<f32>, Vector3<f32>) {
+    match light.light_type {
+        LightType::Spot(pos, dir, _) => (pos, dir),
+        _ => panic!("expected a spot light"),
+    }
+}
+
+fn assert_close(actual: &Vector3<f32>, expected: &Vector3<f32>) {
+    assert!(
+        (actual - expected).norm() < 1e-4,
+        "{:?} != {:?}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn attached_light_follows_moving_node() {
+    let mut scene = scene();
+    let parent = scene.add("parent", object(Point3::new(0.0, 0.0, -5.0)), None);
+    let child = scene.add_child(parent, "child", object(Point3::new(1.0, 0.0, 0.0)), None);
+    scene.attach_light(child, spot_light());
+
+    let (pos, dir) = spot(&scene.view_lights()[0]);
+    assert_close(&pos.coords, &Vector3::new(1.0, 1.0, -10.0));
+    assert_close(&dir, &-Vector3::z());
+
+    // Turning the parent a quarter around y swings the child from +x to -z
+    {
+        let parent = scene.object_mut(parent);
+        parent.position = Point3::new(2.0, 0.0, -5.0);
+        parent.rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), FRAC_PI_2);
+    }
+
+    let (pos, dir) = spot(&scene.view_lights()[0]);
+    assert_close(&pos.coords, &Vector3::new(2.0, 1.0, -11.0));
+    assert_close(&dir, &-Vector3::x());
+
+    // World space is the same without the camera's -5
+    let (pos, _) = spot(&scene.world_lights()[0]);
+    assert_close(&pos.coords, &Vector3::new(2.0, 1.0, -6.0));
+}
+
+#[test]
+fn scene_lights_move_with_the_camera() {
+    let mut scene = scene();
+    scene.lights.push(Light::new_point(
+        Point3::origin(),
+        Color::white(),
+        Color::white(),
+        Color::black(),
+    ));
+
+    // Looking down +x instead, the origin ends up in front of the camera
+    scene.camera.position = Point3::new(-3.0, 0.0, 0.0);
+    scene.camera.set_direction(Vector3::x());
+
+    match scene.view_lights()[0].light_type {
+        LightType::Point(pos) => assert_close(&pos.coords, &Vector3::new(0.0, 0.0, -3.0)),
+        _ => panic!("expected a point light"),
+    }
+}
@@ -0,0 +1,102 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use na::Vector3;
+
+use rgraphics::mesh::Mesh;
+use rgraphics::simplify::SimplifyTarget;
+
+// Closest point on triangle abc to p, from Real-Time Collision Detection 5.1.5
+fn closest_point(
+    p: &Vector3<f32>,
+    a: &Vector3<f32>,
+    b: &Vector3<f32>,
+    c: &Vector3<f32>,
+) -> Vector3<f32> {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+// Furthest any of the original vertices is from the simplified surface. The
+// simplified vertices are all original ones, so that direction is always 0
+fn max_deviation(original: &Mesh, simplified: &Mesh) -> f32 {
+    let tris: Vec<[Vector3<f32>; 3]> = simplified
+        .indices()
+        .chunks(3)
+        .map(|tri| {
+            let pos = |i: u32| Vector3::from(simplified.vertices()[i as usize].pos);
+            [pos(tri[0]), pos(tri[1]), pos(tri[2])]
+        })
+        .collect();
+
+    original
+        .vertices()
+        .iter()
+        .map(|vert| {
+            let p = Vector3::from(vert.pos);
+
+            tris.iter()
+                .map(|tri| (closest_point(&p, &tri[0], &tri[1], &tri[2]) - p).norm())
+                .fold(f32::INFINITY, f32::min)
+        })
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn error_target_bounds_deviation() {
+    let sphere = Mesh::icosphere(1.0, 3);
+    let mut previous = sphere.indices().len();
+
+    for &error in &[0.05, 0.1, 0.2] {
+        let simplified = sphere.simplify(SimplifyTarget::Error(error));
+        let deviation = max_deviation(&sphere, &simplified);
+
+        assert!(deviation <= error, "{} moved {}", error, deviation);
+
+        // Larger errors allow more collapses
+        assert!(simplified.indices().len() < previous, "{} removed nothing", error);
+        previous = simplified.indices().len();
+    }
+}
+
+#[test]
+fn triangle_target_is_reached() {
+    let sphere = Mesh::icosphere(1.0, 3);
+    let simplified = sphere.simplify(SimplifyTarget::Triangles(200));
+
+    assert!(simplified.indices().len() / 3 <= 200);
+    assert!(max_deviation(&sphere, &simplified) < 0.2);
+}
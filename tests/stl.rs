@@ -0,0 +1,78 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use na::Vector3;
+
+use rgraphics::mesh::Mesh;
+use rgraphics::stl::{self, StlFormat};
+
+fn face_normal(mesh: &Mesh, tri: &[u32]) -> [f32; 3] {
+    let pos = |i: u32| Vector3::from(mesh.vertices()[i as usize].pos);
+    let (a, b, c) = (pos(tri[0]), pos(tri[1]), pos(tri[2]));
+
+    (b - a).cross(&(c - a)).normalize().into()
+}
+
+#[test]
+fn every_format_round_trips() {
+    let mesh = Mesh::cube(2.0);
+
+    for &format in &[StlFormat::Ascii, StlFormat::Binary] {
+        let mut data = Vec::new();
+        stl::write_stl(&mut data, &mesh, format).unwrap();
+
+        let reloaded = stl::parse_stl(&data, "cube.stl").unwrap();
+
+        assert_eq!(reloaded.indices().len(), mesh.indices().len());
+
+        // Same triangles in the same order, each flat shaded
+        for (tri, reloaded_tri) in mesh.indices().chunks(3).zip(reloaded.indices().chunks(3)) {
+            let normal = face_normal(&mesh, tri);
+
+            for (&index, &reloaded_index) in tri.iter().zip(reloaded_tri) {
+                let vert = &reloaded.vertices()[reloaded_index as usize];
+
+                assert_eq!(vert.pos, mesh.vertices()[index as usize].pos);
+                assert!((Vector3::from(vert.normal) - Vector3::from(normal)).norm() < 1e-6);
+            }
+        }
+    }
+}
+
+// The first facet's normal points down although it winds counterclockwise
+// seen from above, the second has none and the third is right
+const WRONG_NORMALS: &str = "solid wrong
+facet normal 0 0 -1
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 0 1 0
+  endloop
+endfacet
+facet normal 0 0 0
+  outer loop
+    vertex 0 0 1
+    vertex 1 0 1
+    vertex 0 1 1
+  endloop
+endfacet
+facet normal 0 0 2
+  outer loop
+    vertex 0 0 2
+    vertex 1 0 2
+    vertex 0 1 2
+  endloop
+endfacet
+endsolid wrong
+";
+
+#[test]
+fn wrong_facet_normal_follows_winding() {
+    let mesh = stl::parse_stl(WRONG_NORMALS.as_bytes(), "wrong.stl").unwrap();
+
+    assert_eq!(mesh.vertices().len(), 9);
+
+    for vert in mesh.vertices() {
+        assert_eq!(vert.normal, [0.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,147 @@
+extern crate rgraphics;
+
+use std::collections::HashSet;
+use std::f32::consts::PI;
+
+use rgraphics::mesh::Mesh;
+use rgraphics::program::Vertex;
+use rgraphics::subdivision::PolygonMesh;
+
+// Eight shared corners and six quads facing out
+fn quad_cube() -> PolygonMesh {
+    let corner = |index: u32| Vertex {
+        pos: [
+            if index & 1 == 0 { -1.0 } else { 1.0 },
+            if index & 2 == 0 { -1.0 } else { 1.0 },
+            if index & 4 == 0 { -1.0 } else { 1.0 },
+        ],
+        normal: [0.0, 1.0, 0.0],
+        uv: [0.0, 0.0],
+        tangent: [0.0; 4],
+    };
+
+    PolygonMesh {
+        vertices: (0..8).map(corner).collect(),
+        faces: vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ],
+    }
+}
+
+fn assert_closed(name: &str, mesh: &Mesh) {
+    let report = mesh.validate();
+
+    assert!(report.is_valid(), "{}:\n{}", name, report);
+    assert!(report.is_closed(), "{}:\n{}", name, report);
+    assert!(report.volume > 0.0, "{} is inside out", name);
+}
+
+#[test]
+fn subdivided_meshes_stay_closed() {
+    let shapes = vec![("cube", Mesh::cube(2.0)), ("icosphere", Mesh::icosphere(1.0, 0))];
+
+    for (name, mesh) in shapes {
+        for levels in 1..3 {
+            assert_closed(name, &mesh.subdivide_loop(levels, PI));
+            assert_closed(name, &mesh.subdivide_catmull_clark(levels, PI));
+        }
+    }
+
+    assert_closed("quad cube", &quad_cube().subdivide_catmull_clark(2, PI));
+}
+
+#[test]
+fn loop_quadruples_the_triangles() {
+    for mesh in &[Mesh::cube(2.0), Mesh::icosphere(1.0, 0)] {
+        let triangles = mesh.indices().len() / 3;
+
+        assert_eq!(mesh.subdivide_loop(1, PI).indices().len() / 3, triangles * 4);
+        assert_eq!(mesh.subdivide_loop(2, PI).indices().len() / 3, triangles * 16);
+    }
+}
+
+#[test]
+fn catmull_clark_makes_quads() {
+    // Triangles become three quads each, quads four
+    let triangulated = Mesh::cube(2.0).subdivide_catmull_clark(1, PI);
+    let quads = quad_cube().subdivide_catmull_clark(1, PI);
+
+    for &(mesh, quad_count) in &[(&triangulated, 12 * 3), (&quads, 6 * 4)] {
+        assert_eq!(mesh.indices().len() / 3, quad_count * 2);
+
+        // Each quad comes out as two triangles on the same four corners
+        for pair in mesh.indices().chunks(6) {
+            let corners: HashSet<_> = pair.iter()
+                .map(|&index| {
+                    let pos = mesh.vertices()[index as usize].pos;
+                    [pos[0].to_bits(), pos[1].to_bits(), pos[2].to_bits()]
+                })
+                .collect();
+
+            assert_eq!(corners.len(), 4);
+        }
+    }
+}
+
+#[test]
+fn creases_stay_on_the_crease() {
+    // The rims of a cylinder are at 90 degrees, its sides at 22.5
+    let cylinder = Mesh::cylinder(1.0, 2.0, 16);
+    let on_rim = |vert: &Vertex| {
+        (vert.pos[1].abs() - 1.0).abs() < 1e-5 && vert.pos[0].hypot(vert.pos[2]) > 0.95
+    };
+
+    let creased = vec![
+        cylinder.subdivide_loop(2, PI / 3.0),
+        cylinder.subdivide_catmull_clark(2, PI / 3.0),
+    ];
+
+    for mesh in &creased {
+        for vert in mesh.vertices() {
+            assert!(vert.pos[1].abs() <= 1.0 + 1e-5, "{:?} is past the cap", vert.pos);
+
+            // Cap vertices keep their flat normals and stay in the cap's plane
+            if vert.normal[1].abs() > 0.99 {
+                assert!((vert.pos[1].abs() - 1.0).abs() < 1e-5, "{:?} left the cap", vert.pos);
+            }
+        }
+
+        assert!(mesh.vertices().iter().any(&on_rim));
+    }
+
+    // Without the creases the rims are rounded off
+    let smooth = cylinder.subdivide_loop(2, PI);
+    assert!(!smooth.vertices().iter().any(&on_rim));
+}
+
+#[test]
+fn uv_seams_are_not_averaged() {
+    // The sphere's seam runs down the x = 0, z > 0 half plane, with u = 0 on
+    // one side and u = 1 on the other. Triangles at the poles take u from
+    // their own column, so only rows between the first rings are checked
+    let sphere = Mesh::uv_sphere(1.0, 16, 8);
+
+    for mesh in &[sphere.subdivide_loop(2, PI), sphere.subdivide_catmull_clark(2, PI)] {
+        let seam: Vec<_> = mesh.vertices()
+            .iter()
+            .filter(|vert| vert.pos[0].abs() < 1e-5 && vert.pos[2] > 0.0 && vert.pos[1].abs() < 0.9)
+            .collect();
+
+        assert!(!seam.is_empty());
+        assert!(seam.iter().all(|vert| vert.uv[0] == 0.0 || vert.uv[0] == 1.0));
+        assert!(seam.iter().any(|vert| vert.uv[0] == 0.0));
+        assert!(seam.iter().any(|vert| vert.uv[0] == 1.0));
+
+        // Each side keeps its own copy of every seam vertex
+        for vert in &seam {
+            let other_side = seam.iter()
+                .any(|other| other.pos == vert.pos && other.uv[0] == 1.0 - vert.uv[0]);
+            assert!(other_side, "{:?} has no other side", vert.pos);
+        }
+    }
+}
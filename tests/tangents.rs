@@ -0,0 +1,102 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use na::Vector3;
+
+use rgraphics::mesh::Mesh;
+use rgraphics::program::Vertex;
+
+fn vertex(pos: [f32; 3], uv: [f32; 2]) -> Vertex {
+    Vertex {
+        pos,
+        normal: [0.0, 0.0, 1.0],
+        uv,
+        tangent: [0.0; 4],
+    }
+}
+
+fn tangent(vert: &Vertex) -> Vector3<f32> {
+    Vector3::new(vert.tangent[0], vert.tangent[1], vert.tangent[2])
+}
+
+// Two triangles in the z = 0 plane sharing the edge from 1 to 2, the second
+// with its U running back the other way
+fn mirrored_quads() -> Mesh {
+    let mut mesh = Mesh::new();
+    mesh.add_verticies(&[
+        vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+        vertex([1.0, 0.0, 0.0], [1.0, 0.0]),
+        vertex([1.0, 1.0, 0.0], [1.0, 1.0]),
+        vertex([2.0, 0.0, 0.0], [0.0, 0.0]),
+    ]).add_tris(&[(0, 1, 2), (1, 3, 2)]);
+    mesh
+}
+
+#[test]
+fn plane_tangent_follows_u() {
+    // U runs along -y on this quad, V along +x
+    let mut mesh = Mesh::new();
+    mesh.add_verticies(&[
+        vertex([0.0, 1.0, 0.0], [0.0, 0.0]),
+        vertex([0.0, 0.0, 0.0], [1.0, 0.0]),
+        vertex([1.0, 0.0, 0.0], [1.0, 1.0]),
+        vertex([1.0, 1.0, 0.0], [0.0, 1.0]),
+    ]).add_tris(&[(0, 1, 2), (0, 2, 3)]);
+
+    mesh.generate_tangents();
+
+    assert_eq!(mesh.vertices().len(), 4);
+
+    for vert in mesh.vertices() {
+        assert!((tangent(vert) - Vector3::new(0.0, -1.0, 0.0)).norm() < 1e-6);
+        assert_eq!(vert.tangent[3], 1.0);
+    }
+}
+
+#[test]
+fn mirrored_uvs_split_the_seam() {
+    let mut mesh = mirrored_quads();
+    let sources = mesh.generate_tangents_with_copies();
+
+    // Both seam vertices are copied for the mirrored face
+    assert_eq!(sources, vec![1, 2]);
+    assert_eq!(mesh.vertices().len(), 6);
+    assert_eq!(mesh.indices(), &[0, 1, 2, 4, 3, 5]);
+
+    for (i, vert) in mesh.vertices().iter().enumerate() {
+        let (direction, sign) = if i < 3 { (1.0, 1.0) } else { (-1.0, -1.0) };
+
+        assert!((tangent(vert) - Vector3::new(direction, 0.0, 0.0)).norm() < 1e-6);
+        assert_eq!(vert.tangent[3], sign, "vertex {}", i);
+    }
+
+    // Copies only differ in their tangent
+    for (copy, &source) in mesh.vertices()[4..].iter().zip(&sources) {
+        let source = &mesh.vertices()[source as usize];
+
+        assert_eq!(copy.pos, source.pos);
+        assert_eq!(copy.normal, source.normal);
+        assert_eq!(copy.uv, source.uv);
+    }
+}
+
+#[test]
+fn tangents_are_orthogonal_to_normals() {
+    let shapes = vec![
+        ("cube", Mesh::cube(2.0)),
+        ("uv_sphere", Mesh::uv_sphere(1.0, 16, 8)),
+        ("torus", Mesh::torus(1.0, 0.25, 16, 8)),
+        ("cone", Mesh::cone(1.0, 2.0, 12)),
+    ];
+
+    for (name, mesh) in shapes {
+        for vert in mesh.vertices() {
+            let normal = Vector3::from(vert.normal);
+            let tangent = tangent(vert);
+
+            assert!((tangent.norm() - 1.0).abs() < 1e-5, "{} tangent isn't unit", name);
+            assert!(normal.dot(&tangent).abs() < 1e-5, "{} tangent isn't orthogonal", name);
+            assert!(vert.tangent[3] == 1.0 || vert.tangent[3] == -1.0, "{} has no sign", name);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use std::fs;
+
+use na::Vector3;
+
+use rgraphics::mesh_loader::{self, LoadOptions, NormalSource};
+use rgraphics::triangulation;
+
+fn points(coords: &[(f32, f32)]) -> Vec<Vector3<f32>> {
+    coords.iter().map(|&(x, y)| Vector3::new(x, y, 0.0)).collect()
+}
+
+fn l_shape() -> Vec<Vector3<f32>> {
+    points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)])
+}
+
+// Twice the signed area in the XY plane, positive when counter-clockwise
+fn signed_area(a: &Vector3<f32>, b: &Vector3<f32>, c: &Vector3<f32>) -> f32 {
+    (b - a).cross(&(c - a)).z
+}
+
+fn polygon_area(points: &[Vector3<f32>]) -> f32 {
+    (1..points.len() - 1)
+        .map(|i| signed_area(&points[0], &points[i], &points[i + 1]))
+        .sum()
+}
+
+// Checks the triangles exactly cover the polygon with its winding
+fn assert_covers(points: &[Vector3<f32>], tris: &[(usize, usize, usize)]) {
+    assert_eq!(tris.len(), points.len() - 2);
+
+    let mut area = 0.0;
+
+    for &(a, b, c) in tris {
+        let tri_area = signed_area(&points[a], &points[b], &points[c]);
+
+        // Ears cut across a reflex corner would wind backwards
+        assert!(tri_area > 0.0, "({}, {}, {}) winds the wrong way", a, b, c);
+        area += tri_area;
+    }
+
+    assert!((area - polygon_area(points)).abs() < 1e-4);
+}
+
+#[test]
+fn concave_polygons_are_ear_clipped() {
+    // An L, an arrow head and a comb, all counter-clockwise
+    let shapes = vec![
+        l_shape(),
+        points(&[(0.0, 0.0), (2.0, 1.0), (0.0, 2.0), (0.5, 1.0)]),
+        points(&[
+            (0.0, 0.0),
+            (5.0, 0.0),
+            (5.0, 2.0),
+            (4.0, 2.0),
+            (4.0, 1.0),
+            (3.0, 1.0),
+            (3.0, 2.0),
+            (2.0, 2.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+        ]),
+    ];
+
+    for shape in shapes {
+        assert_covers(&shape, &triangulation::triangulate_polygon(&shape));
+
+        // Starting the loop at the reflex corner doesn't matter
+        for start in 1..shape.len() {
+            let mut rotated = shape[start..].to_vec();
+            rotated.extend_from_slice(&shape[..start]);
+
+            assert_covers(&rotated, &triangulation::triangulate_polygon(&rotated));
+        }
+    }
+}
+
+#[test]
+fn clockwise_polygons_keep_their_winding() {
+    let mut shape = l_shape();
+    shape.reverse();
+
+    let tris = triangulation::triangulate_polygon(&shape);
+
+    assert_eq!(tris.len(), 4);
+    for &(a, b, c) in &tris {
+        assert!(signed_area(&shape[a], &shape[b], &shape[c]) < 0.0);
+    }
+}
+
+#[test]
+fn concave_obj_faces_stay_inside() {
+    fs::create_dir_all("target/triangulation").unwrap();
+
+    let file = "target/triangulation/l_shape.obj";
+    fs::write(
+        file,
+        "v 0 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nf 3 4 5 6 1 2\n",
+    ).unwrap();
+
+    let options = LoadOptions {
+        normal_source: Some(NormalSource::Flat),
+        ..LoadOptions::default()
+    };
+    let mesh = mesh_loader::load_file_with(file, &options).unwrap();
+
+    let mut area = 0.0;
+
+    for tri in mesh.indices().chunks(3) {
+        let pos = |corner: usize| Vector3::from(mesh.vertices()[tri[corner] as usize].pos);
+        let tri_area = signed_area(&pos(0), &pos(1), &pos(2));
+
+        assert!(tri_area > 0.0);
+        area += tri_area;
+    }
+
+    // Twice the L's area of 3
+    assert!((area - 6.0).abs() < 1e-4);
+    assert!(mesh.vertices().iter().all(|vert| vert.normal == [0.0, 0.0, 1.0]));
+}
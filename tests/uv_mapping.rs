@@ -0,0 +1,128 @@
+extern crate nalgebra as na;
+extern crate rgraphics;
+
+use std::fs;
+
+use na::{Vector2, Vector3};
+
+use rgraphics::mesh_loader::{self, LoadOptions, NormalSource, UVSource};
+use rgraphics::uv_mapping;
+
+fn assert_close(actual: Vector2<f32>, expected: (f32, f32)) {
+    let expected = Vector2::new(expected.0, expected.1);
+    assert!((actual - expected).norm() < 1e-5, "{:?} != {:?}", actual, expected);
+}
+
+#[test]
+fn planar_projections_drop_an_axis() {
+    let pos = Vector3::new(1.0, 2.0, 3.0);
+    let normal = Vector3::y();
+
+    assert_close(uv_mapping::planar_x(&pos, &normal), (3.0, 2.0));
+    assert_close(uv_mapping::planar_y(&pos, &normal), (1.0, 3.0));
+    assert_close(uv_mapping::planar_z(&pos, &normal), (1.0, 2.0));
+}
+
+#[test]
+fn cylindrical_wraps_around_y() {
+    let normal = Vector3::y();
+    let map = |x: f32, y: f32, z: f32| uv_mapping::cylindrical(&Vector3::new(x, y, z), &normal);
+
+    // u starts and ends behind, at -z, and v is the height
+    assert_close(map(-1.0, 0.0, 0.0), (0.25, 0.0));
+    assert_close(map(0.0, 2.0, 1.0), (0.5, 2.0));
+    assert_close(map(1.0, -1.0, 0.0), (0.75, -1.0));
+    assert_close(map(0.0, 0.0, -1.0), (1.0, 0.0));
+    assert_close(map(-1e-6, 0.0, -1.0), (0.0, 0.0));
+}
+
+#[test]
+fn spherical_goes_from_pole_to_pole() {
+    let normal = Vector3::y();
+    let map = |x: f32, y: f32, z: f32| uv_mapping::spherical(&Vector3::new(x, y, z), &normal);
+
+    // v doesn't depend on the radius
+    assert_close(map(1.0, 0.0, 0.0), (0.75, 0.5));
+    assert_close(map(0.0, 0.0, 3.0), (0.5, 0.5));
+    assert_close(map(0.0, 1.0, 1.0), (0.5, 0.75));
+    assert_eq!(map(0.0, 2.0, 0.0).y, 1.0);
+    assert_eq!(map(0.0, -2.0, 0.0).y, 0.0);
+
+    // The center has no direction, it gets the equator
+    assert_eq!(map(0.0, 0.0, 0.0).y, 0.5);
+}
+
+#[test]
+fn cube_projects_along_the_normal() {
+    let pos = Vector3::new(1.0, 2.0, 3.0);
+    let map = |normal: Vector3<f32>| uv_mapping::cube(&pos, &normal);
+
+    // Negative sides are flipped, so each face reads left to right from outside
+    assert_close(map(Vector3::x()), (-3.0, 2.0));
+    assert_close(map(-Vector3::x()), (3.0, 2.0));
+    assert_close(map(Vector3::y()), (1.0, -3.0));
+    assert_close(map(-Vector3::y()), (1.0, 3.0));
+    assert_close(map(Vector3::z()), (1.0, 2.0));
+    assert_close(map(-Vector3::z()), (-1.0, 2.0));
+
+    // Slanted normals pick the axis they point furthest along
+    assert_close(map(Vector3::new(0.2, -0.9, 0.3)), (1.0, 3.0));
+    assert_close(map(Vector3::new(0.5, 0.1, -0.6)), (-1.0, 2.0));
+}
+
+#[test]
+fn fix_seam_moves_the_low_side_up() {
+    let mut across = [Vector2::new(0.9, 0.0), Vector2::new(0.1, 0.5), Vector2::new(0.95, 1.0)];
+    uv_mapping::fix_seam(&mut across);
+
+    assert_close(across[0], (0.9, 0.0));
+    assert_close(across[1], (1.1, 0.5));
+    assert_close(across[2], (0.95, 1.0));
+
+    // Triangles within half the texture are left alone
+    let inside = [Vector2::new(0.2, 0.0), Vector2::new(0.6, 0.0), Vector2::new(0.4, 1.0)];
+    let mut fixed = inside;
+    uv_mapping::fix_seam(&mut fixed);
+
+    assert_eq!(fixed, inside);
+}
+
+#[test]
+fn generated_uvs_split_at_the_seam() {
+    fs::create_dir_all("target/uv_mapping").unwrap();
+
+    // Two triangles behind the origin, the first across the seam at -z. Both
+    // use vertex 1, which sits just past the seam
+    let file = "target/uv_mapping/seam.obj";
+    fs::write(
+        file,
+        "v -0.1 0 -1\nv 0.1 0 -1\nv 0 1 -1\nv -0.5 0 -1\nv -0.5 1 -1\nf 1 2 3\nf 4 1 5\n",
+    ).unwrap();
+
+    let options = LoadOptions {
+        uv_source: Some(UVSource::Cylindrical),
+        normal_source: Some(NormalSource::Flat),
+        ..LoadOptions::default()
+    };
+    let mesh = mesh_loader::load_file_with(file, &options).unwrap();
+
+    // Vertex 1 gets a copy on each side of the seam
+    assert_eq!(mesh.vertices().len(), 6);
+
+    for tri in mesh.indices().chunks(3) {
+        let us: Vec<_> = tri.iter().map(|&index| mesh.vertices()[index as usize].uv[0]).collect();
+        let min = us.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = us.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+
+        assert!(max - min < 0.1, "{:?} interpolates through the texture", us);
+    }
+
+    let copies: Vec<_> = mesh.vertices()
+        .iter()
+        .filter(|vert| vert.pos == [-0.1, 0.0, -1.0])
+        .map(|vert| vert.uv[0])
+        .collect();
+
+    assert_eq!(copies.len(), 2);
+    assert!(((copies[0] - copies[1]).abs() - 1.0).abs() < 1e-6);
+}
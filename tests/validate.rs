@@ -0,0 +1,112 @@
+extern crate rgraphics;
+
+use rgraphics::mesh::Mesh;
+use rgraphics::program::Vertex;
+
+fn mesh(positions: &[[f32; 3]], tris: &[(u32, u32, u32)]) -> Mesh {
+    let mut mesh = Mesh::new();
+
+    for &pos in positions {
+        mesh.add_vertex(&Vertex {
+            pos,
+            normal: [0.0, 0.0, 1.0],
+            uv: [0.0, 0.0],
+            tangent: [0.0; 4],
+        });
+    }
+
+    mesh.add_tris(tris);
+    mesh
+}
+
+// Outward facing tetrahedron
+const TETRAHEDRON: [(u32, u32, u32); 4] = [(0, 2, 1), (0, 1, 3), (1, 2, 3), (2, 0, 3)];
+
+fn tetrahedron_positions() -> Vec<[f32; 3]> {
+    vec![
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ]
+}
+
+#[test]
+fn closed_meshes_are_closed() {
+    let report = mesh(&tetrahedron_positions(), &TETRAHEDRON).validate();
+
+    assert!(report.is_valid(), "{}", report);
+    assert!(report.is_closed(), "{}", report);
+    assert_eq!(report.connected_components, 1);
+    assert!((report.volume - 1.0 / 6.0).abs() < 1e-6);
+
+    let report = Mesh::cube(2.0).validate();
+
+    assert!(report.is_closed(), "{}", report);
+    assert!((report.volume - 8.0).abs() < 1e-5);
+    assert!((report.surface_area - 24.0).abs() < 1e-5);
+}
+
+#[test]
+fn open_meshes_have_boundaries() {
+    // The tetrahedron without its base
+    let report = mesh(&tetrahedron_positions(), &TETRAHEDRON[1..]).validate();
+
+    assert!(report.is_valid(), "{}", report);
+    assert!(!report.is_closed());
+    assert_eq!(report.boundary_edges, 3);
+    assert_eq!(report.non_manifold_edges, 0);
+}
+
+#[test]
+fn non_manifold_edges_are_found() {
+    // Three triangles hinged on the edge from 0 to 1
+    let positions = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, -1.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ];
+    let report = mesh(&positions, &[(0, 1, 2), (1, 0, 3), (0, 1, 4)]).validate();
+
+    assert_eq!(report.non_manifold_edges, 1);
+    assert!(!report.is_valid());
+    assert!(!report.is_closed());
+    assert_eq!(report.connected_components, 1);
+}
+
+#[test]
+fn degenerate_triangles_are_found() {
+    let positions = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [2.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        // Same position as the first vertex
+        [0.0, 0.0, 0.0],
+    ];
+
+    // A good triangle, a collinear one, a repeated index and a repeated position
+    let report = mesh(&positions, &[(0, 1, 3), (0, 1, 2), (0, 0, 3), (0, 4, 3)]).validate();
+
+    assert_eq!(report.degenerate_triangles, 3);
+    assert!(!report.is_valid());
+}
+
+#[test]
+fn negative_zero_is_the_same_position() {
+    // The base uses its own copies of the vertices, with -0.0 where the
+    // sides have 0.0
+    let mut positions = tetrahedron_positions();
+    positions.extend_from_slice(&[[-0.0, 0.0, -0.0], [1.0, -0.0, 0.0], [-0.0, 1.0, -0.0]]);
+
+    let mut tris = TETRAHEDRON.to_vec();
+    tris[0] = (4, 6, 5);
+
+    let report = mesh(&positions, &tris).validate();
+
+    assert!(report.is_valid(), "{}", report);
+    assert!(report.is_closed(), "{}", report);
+    assert_eq!(report.connected_components, 1);
+}
@@ -0,0 +1,115 @@
+extern crate rgraphics;
+
+use std::collections::HashMap;
+
+use rgraphics::mesh::Mesh;
+use rgraphics::vertex_cache::{self, DEFAULT_CACHE_SIZE};
+
+// The same mesh with its triangles in a scrambled but repeatable order, so
+// there is something for the optimizer to do. The bundled models are checked
+// by the vertex_cache bench
+fn shuffled(mesh: &Mesh) -> Mesh {
+    let mut tris: Vec<_> = mesh.indices()
+        .chunks(3)
+        .map(|tri| (tri[0], tri[1], tri[2]))
+        .collect();
+
+    // Fisher-Yates with a fixed LCG
+    let mut state = 12345u32;
+
+    for i in (1..tris.len()).rev() {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        tris.swap(i, (state >> 8) as usize % (i + 1));
+    }
+
+    let mut result = Mesh::new();
+
+    for vert in mesh.vertices() {
+        result.add_vertex(vert);
+    }
+
+    result.add_tris(&tris);
+    result
+}
+
+// Every triangle as its corner positions, rotated to start at the smallest
+// so the same triangle compares equal whatever its vertex indices
+fn triangle_counts(mesh: &Mesh) -> HashMap<[[u32; 3]; 3], usize> {
+    let mut counts = HashMap::new();
+
+    for tri in mesh.indices().chunks(3) {
+        let mut corners: Vec<[u32; 3]> = tri.iter()
+            .map(|&index| {
+                let pos = mesh.vertices()[index as usize].pos;
+                [pos[0].to_bits(), pos[1].to_bits(), pos[2].to_bits()]
+            })
+            .collect();
+
+        let first = (0..3).min_by_key(|&corner| corners[corner]).unwrap();
+        corners.rotate_left(first);
+
+        *counts.entry([corners[0], corners[1], corners[2]]).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+// Optimizes the mesh, checking it has the same triangles afterwards, and
+// returns the ACMR before and after
+fn check_mesh(mut mesh: Mesh) -> (f32, f32) {
+    let before = mesh.acmr(DEFAULT_CACHE_SIZE);
+    let triangles = triangle_counts(&mesh);
+
+    mesh.optimize_vertex_cache(DEFAULT_CACHE_SIZE);
+    let after = mesh.acmr(DEFAULT_CACHE_SIZE);
+
+    mesh.optimize_vertex_fetch();
+
+    assert_eq!(mesh.acmr(DEFAULT_CACHE_SIZE), after);
+    assert_eq!(triangle_counts(&mesh), triangles);
+
+    // Fetch order follows the index order
+    let mut next = 0;
+
+    for &index in mesh.indices() {
+        assert!(index <= next);
+
+        if index == next {
+            next += 1;
+        }
+    }
+
+    (before, after)
+}
+
+#[test]
+fn acmr_of_known_orders() {
+    // A strip of quads along x, each new triangle adds one vertex
+    let strip: Vec<u32> = (0..100u32)
+        .flat_map(|i| vec![i, i + 1, i + 2])
+        .collect();
+
+    assert_eq!(vertex_cache::acmr(&strip, DEFAULT_CACHE_SIZE), 102.0 / 100.0);
+    assert_eq!(vertex_cache::acmr(&[0, 1, 2, 3, 4, 5], 3), 3.0);
+    assert_eq!(vertex_cache::acmr(&[0, 1, 2, 0, 1, 2], 3), 1.5);
+    assert_eq!(vertex_cache::acmr(&[], 3), 0.0);
+}
+
+#[test]
+fn optimize_cube() {
+    // Already as good as it gets, every face has its own vertices
+    let (before, after) = check_mesh(Mesh::cube(1.0));
+    assert!(after <= before);
+}
+
+#[test]
+fn optimize_icosphere() {
+    let (before, after) = check_mesh(shuffled(&Mesh::icosphere(1.0, 4)));
+    assert!(after < before && after < 1.0);
+}
+
+#[test]
+fn optimize_torus() {
+    let (before, after) = check_mesh(shuffled(&Mesh::torus(1.0, 0.25, 64, 32)));
+    assert!(after < before && after < 1.0);
+}